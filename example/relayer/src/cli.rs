@@ -1,10 +1,18 @@
 use clap::{Parser, Subcommand};
 
+use chainhook_sdk::types::StacksNetwork;
 use chainhook_sdk::utils::Context;
 
 use crate::{
-    config::{generator::generate_config, Config},
-    service::{collect_guardians_public_keys, ping_bridge_service, start_bridge_service},
+    config::{
+        generator::generate_config, overrides::redact, validation::validate_config_file, Config,
+        SignerConfig,
+    },
+    service::{
+        collect_guardians_public_keys, log_on_chain_prices, ping_bridge_service,
+        start_bridge_service,
+    },
+    signer::{load_signer, remote},
 };
 
 #[derive(Parser, Debug)]
@@ -22,6 +30,9 @@ enum Command {
     /// Run a service streaming blocks and submitting price feeds updates
     #[clap(subcommand)]
     Service(ServiceCommand),
+    /// Sign transactions on behalf of a relayer
+    #[clap(subcommand)]
+    Signer(SignerCommand),
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -30,6 +41,12 @@ enum ConfigCommand {
     /// Generate new config
     #[clap(name = "new", bin_name = "new", aliases = &["generate"])]
     New(NewConfig),
+    /// Print the config in effect, overrides applied and secrets redacted
+    #[clap(name = "show", bin_name = "show")]
+    Show(ShowConfig),
+    /// Check a config file, reporting every problem with its line
+    #[clap(name = "validate", bin_name = "validate")]
+    Validate(ValidateConfig),
 }
 
 #[derive(Parser, PartialEq, Clone, Debug)]
@@ -55,6 +72,29 @@ struct NewConfig {
         conflicts_with = "devnet"
     )]
     pub mainnet: bool,
+    /// Path of the config file to create
+    #[clap(long = "output", default_value = "Bridge.toml")]
+    pub output: String,
+    /// Overwrite the file if it exists
+    #[clap(long = "force")]
+    pub force: bool,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct ShowConfig {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: String,
+    /// Override a config field, e.g. bridge.price_updates_per_minute=10
+    #[clap(long = "set", value_name = "SECTION.FIELD=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct ValidateConfig {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: String,
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -66,16 +106,49 @@ enum ServiceCommand {
     /// Fetch the price feed ids and check the balance of the hot wallet account
     #[clap(name = "ping", bin_name = "ping")]
     Ping(PingCommand),
+    /// Read the prices currently stored on chain for the configured price feeds
+    #[clap(name = "prices", bin_name = "prices")]
+    Prices(PricesCommand),
     /// Sniff public keys from active guardian set
     #[clap(name = "sniff", bin_name = "sniff")]
     CollectVAAs(CollectVAAsCommand),
 }
 
+#[derive(Subcommand, PartialEq, Clone, Debug)]
+#[clap(bin_name = "signer")]
+enum SignerCommand {
+    /// Serve the remote signer protocol with the key of a config file
+    #[clap(name = "start", bin_name = "start")]
+    Start(StartSignerCommand),
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct StartSignerCommand {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: String,
+    /// Override a config field, e.g. bridge.price_updates_per_minute=10
+    #[clap(long = "set", value_name = "SECTION.FIELD=VALUE")]
+    pub overrides: Vec<String>,
+    /// Port to listen on, on localhost
+    #[clap(long = "port", default_value = "20446")]
+    pub port: u16,
+    /// Index of the account to serve, in stacks.accounts
+    #[clap(long = "account", default_value = "0")]
+    pub account: usize,
+}
+
 #[derive(Parser, PartialEq, Clone, Debug)]
 struct StartCommand {
     /// Load config file path
     #[clap(long = "config-path")]
     pub config_path: String,
+    /// Override a config field, e.g. bridge.price_updates_per_minute=10
+    #[clap(long = "set", value_name = "SECTION.FIELD=VALUE")]
+    pub overrides: Vec<String>,
+    /// Simulate the price updates against the chain tip without broadcasting them
+    #[clap(long = "dry-run")]
+    pub dry_run: bool,
     // /// Start REST API for managing configuration
     // #[clap(long = "start-http-api")]
     // pub start_http_api: bool,
@@ -86,6 +159,19 @@ struct CollectVAAsCommand {
     /// Load config file path
     #[clap(long = "config-path")]
     pub config_path: String,
+    /// Override a config field, e.g. bridge.price_updates_per_minute=10
+    #[clap(long = "set", value_name = "SECTION.FIELD=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct PricesCommand {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: String,
+    /// Override a config field, e.g. bridge.price_updates_per_minute=10
+    #[clap(long = "set", value_name = "SECTION.FIELD=VALUE")]
+    pub overrides: Vec<String>,
 }
 
 #[derive(Parser, PartialEq, Clone, Debug)]
@@ -93,6 +179,9 @@ struct PingCommand {
     /// Load config file path
     #[clap(long = "config-path")]
     pub config_path: String,
+    /// Override a config field, e.g. bridge.price_updates_per_minute=10
+    #[clap(long = "set", value_name = "SECTION.FIELD=VALUE")]
+    pub overrides: Vec<String>,
 }
 
 pub fn main() {
@@ -111,45 +200,112 @@ pub fn main() {
         }
     };
 
-    match hiro_system_kit::nestable_block_on(handle_command(opts, ctx)) {
-        Err(e) => {
-            println!("{e}");
-            std::process::exit(1);
-        }
-        Ok(_) => {}
+    if let Err(e) = hiro_system_kit::nestable_block_on(handle_command(opts, ctx)) {
+        println!("{e}");
+        std::process::exit(1);
     }
 }
 
 async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
     match opts.command {
-        Command::Config(ConfigCommand::New(_options)) => {
-            use std::fs::File;
+        Command::Config(ConfigCommand::New(options)) => {
+            use std::fs::OpenOptions;
             use std::io::Write;
-            use std::path::PathBuf;
-            let config_content = generate_config();
-            let mut file_path = PathBuf::new();
-            file_path.push("Bridge.toml");
-            let mut file = File::create(&file_path)
-                .map_err(|e| format!("unable to open file {}\n{}", file_path.display(), e))?;
+            let network = match (options.devnet, options.testnet, options.mainnet) {
+                (true, _, _) => StacksNetwork::Devnet,
+                (_, true, _) => StacksNetwork::Testnet,
+                (_, _, true) => StacksNetwork::Mainnet,
+                _ => return Err("network required (--devnet, --testnet or --mainnet)".into()),
+            };
+            let config_content = generate_config(&network);
+            // The generated config must load as is
+            let config_file = toml::from_str(&config_content)
+                .map_err(|e| format!("generated config malformatted {}", e))?;
+            Config::from_config_file(config_file)
+                .map_err(|e| format!("generated config invalid: {}", e))?;
+            let mut file = match options.force {
+                true => OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&options.output),
+                false => OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&options.output),
+            }
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => {
+                    format!("file {} exists, overwrite it with --force", options.output)
+                }
+                _ => format!("unable to open file {}\n{}", options.output, e),
+            })?;
             file.write_all(config_content.as_bytes())
-                .map_err(|e| format!("unable to write file {}\n{}", file_path.display(), e))?;
-            println!("Created file Chainhook.toml");
+                .map_err(|e| format!("unable to write file {}\n{}", options.output, e))?;
+            println!("Created file {}", options.output);
+        }
+        Command::Config(ConfigCommand::Show(options)) => {
+            let mut config =
+                Config::load_toml(&options.config_path, std::env::vars(), &options.overrides)?;
+            redact(&mut config);
+            let config =
+                toml::to_string(&config).map_err(|e| format!("unable to print config: {}", e))?;
+            print!("{}", config);
+        }
+        Command::Config(ConfigCommand::Validate(options)) => {
+            let problems = validate_config_file(&options.config_path)?;
+            for problem in problems.iter() {
+                match problem.line {
+                    Some(line) => println!("{}:{}: {}", options.config_path, line, problem.message),
+                    None => println!("{}: {}", options.config_path, problem.message),
+                }
+            }
+            if !problems.is_empty() {
+                return Err(format!(
+                    "{} problem(s) found in {}",
+                    problems.len(),
+                    options.config_path
+                ));
+            }
+            println!("{}: config valid", options.config_path);
         }
         Command::Service(ServiceCommand::Start(options)) => {
             // Start service
-            let config = Config::from_file_path(&options.config_path)?;
+            let mut config =
+                Config::load(&options.config_path, std::env::vars(), &options.overrides)?;
+            config.bridge.dry_run = options.dry_run;
             start_bridge_service(&config, &ctx)?;
         }
         Command::Service(ServiceCommand::Ping(options)) => {
             // Start service
-            let config = Config::from_file_path(&options.config_path)?;
+            let config = Config::load(&options.config_path, std::env::vars(), &options.overrides)?;
             ping_bridge_service(&config, &ctx).await?;
         }
+        Command::Service(ServiceCommand::Prices(options)) => {
+            let config = Config::load(&options.config_path, std::env::vars(), &options.overrides)?;
+            log_on_chain_prices(&config, &ctx).await?;
+        }
         Command::Service(ServiceCommand::CollectVAAs(options)) => {
             // Start service
-            let config = Config::from_file_path(&options.config_path)?;
+            let config = Config::load(&options.config_path, std::env::vars(), &options.overrides)?;
             collect_guardians_public_keys(&config, &ctx).await;
         }
+        Command::Signer(SignerCommand::Start(options)) => {
+            let config = Config::load(&options.config_path, std::env::vars(), &options.overrides)?;
+            let signer_config = config
+                .stacks
+                .signers
+                .get(options.account)
+                .ok_or(format!("account {} not configured", options.account))?;
+            if let SignerConfig::Remote { .. } = signer_config {
+                return Err(format!(
+                    "account {} must hold its key to be served",
+                    options.account
+                ));
+            }
+            let signer = load_signer(signer_config)?;
+            remote::serve(signer.as_ref(), options.port, &ctx)?;
+        }
     }
     Ok(())
 }
@@ -1,25 +1,41 @@
+use std::collections::BTreeMap;
+
 use chainhook_sdk::observer::EventObserverConfigOverrides;
+use toml::Value;
+
+use super::secrets::Secret;
+use super::PythNetwork;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ConfigFile {
     pub pyth: PythConfigFile,
     pub stacks: StacksConfigFile,
     pub bridge: BridgeConfigFile,
+    pub wormhole: Option<WormholeConfigFile>,
     pub event_observer: Option<EventObserverConfigOverrides>,
-}
-
-#[derive(Clone, Debug)]
-pub enum PythNetwork {
-    Devnet,
-    Testnet,
-    Mainnet,
+    /// Fields no config option matches, in this section and the ones below, reported
+    /// by the config checks.
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, Value>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct PythConfigFile {
-    pub network: String,
+    pub network: PythNetwork,
     pub price_service_url: String,
+    pub price_service_api: Option<String>,
+    pub price_service_encoding: Option<String>,
     pub price_feeds_ids: Vec<String>,
+    pub default_price_feed_policy: Option<PriceFeedPolicyFile>,
+    pub price_feeds_policies: Option<BTreeMap<String, PriceFeedPolicyFile>>,
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PriceFeedPolicyFile {
+    pub deviation_threshold_bps: Option<u64>,
+    pub heartbeat_secs: Option<u64>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -27,14 +43,60 @@ pub struct StacksConfigFile {
     pub network: String,
     pub stacks_node_rpc_url: String,
     pub pyth_oracle_contract_address: String,
-    pub mnemonic: String,
-    pub derivation_path: String,
+    pub pyth_governance_contract_address: Option<String>,
+    pub pyth_oracle_entrypoint: Option<String>,
+    #[serde(flatten)]
+    pub signer: SignerConfigFile,
+    pub accounts: Option<Vec<SignerConfigFile>>,
     pub start_block: u64,
 }
 
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SignerConfigFile {
+    pub signer: Option<String>,
+    pub mnemonic: Option<Secret>,
+    pub mnemonic_env: Option<String>,
+    pub mnemonic_file: Option<String>,
+    pub mnemonic_prompt: Option<bool>,
+    pub derivation_path: Option<String>,
+    pub private_key: Option<Secret>,
+    pub keystore_path: Option<String>,
+    pub keystore_password: Option<Secret>,
+    pub remote_signer_url: Option<String>,
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, Value>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct BridgeConfigFile {
     pub price_updates_per_minute: u64,
     pub enable_microblocks: Option<bool>,
+    pub microblock_anchor_mode: Option<String>,
+    pub microblock_updates_per_minute: Option<u64>,
     pub enable_rbf: bool,
+    pub rbf_fee_bump_percent: Option<u64>,
+    pub rbf_min_fee_increment: Option<u64>,
+    pub rbf_max_fee: Option<u64>,
+    pub rbf_rebroadcast_after_blocks: Option<u64>,
+    pub fee_estimate_tier: Option<String>,
+    pub fee_floor: Option<u64>,
+    pub fee_ceiling: Option<u64>,
+    pub fee_budget_hourly: Option<u64>,
+    pub fee_budget_daily: Option<u64>,
+    pub fee_escalation_after_blocks: Option<u64>,
+    pub fee_escalation_percent: Option<u64>,
+    pub balance_warning_threshold: Option<u64>,
+    pub balance_floor: Option<u64>,
+    pub account_stuck_after_blocks: Option<u64>,
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct WormholeConfigFile {
+    pub guardian_set_source: Option<String>,
+    pub guardian_sets_path: Option<String>,
+    pub core_contract_address: Option<String>,
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, Value>,
 }
@@ -1,25 +1,199 @@
-pub fn generate_config() -> String {
-    let conf = format!(
-        r#"[pyth]
-network = "mainnet"
-price_service_url = "https://xc-mainnet.pyth.network"
+use chainhook_sdk::types::StacksNetwork;
+
+/// Config of a relayer on `network`, relaying the price feeds of Pyth mainnet to
+/// Stacks mainnet, and of Pyth testnet to the test networks.
+pub fn generate_config(network: &StacksNetwork) -> String {
+    let (pyth_network, price_service_url, price_feeds): (_, _, &[(&str, &str)]) = match network {
+        StacksNetwork::Mainnet => (
+            "mainnet",
+            "https://hermes.pyth.network",
+            &[
+                (
+                    "0xe62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43",
+                    "BTC-USD",
+                ),
+                (
+                    "0xec7a775f46379b5e943c3526b1c8d54cd49749176b0b98e02dde68d1bd335c17",
+                    "STX-USD",
+                ),
+            ],
+        ),
+        _ => (
+            "testnet",
+            "https://hermes-beta.pyth.network",
+            &[(
+                "0xf9c0172ba10dfa4d19088d94f5bf61d3b54d5bd7483a322a982e1373ee8ea31b",
+                "BTC-USD",
+            )],
+        ),
+    };
+    let price_feeds_ids = price_feeds
+        .iter()
+        .map(|(price_feed_id, symbol)| format!("    \"{}\", # {}", price_feed_id, symbol))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let policy_price_feed_id = price_feeds[0].0;
+    let (stacks_network, stacks_node_rpc_url, pyth_oracle_contract_address, start_block) =
+        match network {
+            StacksNetwork::Mainnet => (
+                "mainnet",
+                "https://api.hiro.so",
+                "SP2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY2P2PKN0.pyth-oracle-v2",
+                0,
+            ),
+            StacksNetwork::Testnet => (
+                "testnet",
+                "https://api.testnet.hiro.so",
+                "ST2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY1JKX06E.pyth-oracle-v2",
+                0,
+            ),
+            // Contracts of Clarinet.toml, deployed by the deployer account of settings/Devnet.toml
+            _ => (
+                "devnet",
+                "http://localhost:20443",
+                "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.pyth-oracle-v2",
+                6,
+            ),
+        };
+    let (bitcoin_network, bitcoind_rpc_url, bitcoind_rpc_credentials) = match network {
+        StacksNetwork::Mainnet => (
+            "mainnet",
+            "http://localhost:8332",
+            "# bitcoind_rpc_username = \"\"\n# bitcoind_rpc_password = \"\"",
+        ),
+        StacksNetwork::Testnet => (
+            "testnet",
+            "http://localhost:18332",
+            "# bitcoind_rpc_username = \"\"\n# bitcoind_rpc_password = \"\"",
+        ),
+        _ => (
+            "regtest",
+            "http://localhost:18443",
+            "bitcoind_rpc_username = \"devnet\"\nbitcoind_rpc_password = \"devnet\"",
+        ),
+    };
+    format!(
+        r#"# Fields can be overridden with STACKS_PYTH_RELAYER__SECTION__FIELD environment
+# variables, or --set section.field=value flags
+[pyth]
+# Pyth mainnet for Stacks mainnet, Pyth testnet for the test networks
+network = "{pyth_network}"
+price_service_url = "{price_service_url}"
+price_service_api = "hermes-v2" # or "hermes-v2-stream", "legacy"
+# price_service_encoding = "hex" # or "base64", with hermes-v2 and hermes-v2-stream
 price_feeds_ids = [
-    "0xe62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43", # BTC-USD
-    "0xec7a775f46379b5e943c3526b1c8d54cd49749176b0b98e02dde68d1bd335c17", # STX-USD
+{price_feeds_ids}
 ]
-# Price feeds ids available here: https://pyth.network/developers/price-feed-ids
+# Price feeds ids of each Pyth network available here: https://pyth.network/developers/price-feed-ids
+
+# Only push a price feed when it moved by deviation_threshold_bps from the on-chain
+# price, or when the on-chain price is older than heartbeat_secs
+# [pyth.default_price_feed_policy]
+# deviation_threshold_bps = 50
+# heartbeat_secs = 3600
+# [pyth.price_feeds_policies."{policy_price_feed_id}"]
+# deviation_threshold_bps = 25
 
 [stacks]
-network = "mainnet"
-stacks_node_rpc_url = "http://localhost:20443"
-pyth_oracle_contract_address = ""
-mnemonic = "prevent gallery kind limb income control noise together echo rival record wedding sense uncover school version force bleak nuclear include danger skirt enact arrow"
+network = "{stacks_network}"
+stacks_node_rpc_url = "{stacks_node_rpc_url}"
+pyth_oracle_contract_address = "{pyth_oracle_contract_address}"
+# pyth_oracle_entrypoint = "verify-and-update-price-feeds" # or "update-prices-feeds" with the "legacy" price_service_api
+# pyth_governance_contract_address = ""
+# Blocks below start_block are ignored
+start_block = {start_block}
+# Transactions are signed with the key derived from a mnemonic by default, read from
+# the environment variable mnemonic_env, from mnemonic_file (only readable by its
+# owner), typed in at startup with mnemonic_prompt, or set as is with mnemonic
+mnemonic_env = "PYTH_RELAYER_MNEMONIC"
+# mnemonic_file = "/run/secrets/relayer-mnemonic"
+# mnemonic_prompt = true
+derivation_path = "m/44'/5757'/0'/0/0"
+# Or with
+# signer = "private-key" # with private_key
+# signer = "keystore" # with keystore_path and keystore_password
+# signer = "remote" # with remote_signer_url
+# Price updates can rotate across several accounts instead, each taking the
+# signer options above
+# [[stacks.accounts]]
+# mnemonic_file = "/run/secrets/relayer-mnemonic-1"
+# [[stacks.accounts]]
+# signer = "private-key"
+# private_key = "..."
 
 [bridge]
 price_updates_per_minute = 5
 enable_rbf = true
+# Replace pending transactions after rbf_rebroadcast_after_blocks blocks, bumping
+# their fee (µSTX) by rbf_fee_bump_percent, at least rbf_min_fee_increment, up to rbf_max_fee
+# rbf_fee_bump_percent = 25
+# rbf_min_fee_increment = 1000
+# rbf_max_fee = 1000000
+# rbf_rebroadcast_after_blocks = 1
+# Fees (µSTX) estimated by the node with the "low", "medium" or "high" tier, kept
+# between fee_floor and fee_ceiling, and raised by fee_escalation_percent every
+# fee_escalation_after_blocks blocks while price updates stay pending. Price updates
+# pause once the fees spent over the last hour or day reach their budget.
+# fee_estimate_tier = "medium"
+# fee_floor = 0
+# fee_ceiling = 1000000
+# fee_budget_hourly = 5000000
+# fee_budget_daily = 50000000
+# fee_escalation_after_blocks = 2
+# fee_escalation_percent = 25
+# Warn once the balance (µSTX) of a relayer account drops below
+# balance_warning_threshold, stop submitting price updates from it below balance_floor
+# balance_warning_threshold = 100000000
+# balance_floor = 10000000
+# New price updates avoid the accounts with a transaction pending for
+# account_stuck_after_blocks blocks
+# account_stuck_after_blocks = 3
 enable_microblocks = true
+# Microblock updates, rate limited on their own
+# microblock_anchor_mode = "any" # or "offchain-only"
+# microblock_updates_per_minute = 5
+
+# The stacks node pushes its blocks to the relayer, with an events_observer
+# endpoint set to localhost:ingestion_port in its config
+[event_observer]
+ingestion_port = 20456
+stacks_network = "{stacks_network}"
+bitcoin_network = "{bitcoin_network}"
+bitcoind_rpc_url = "{bitcoind_rpc_url}"
+{bitcoind_rpc_credentials}
 "#
-    );
-    return conf;
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::secrets::SecretSource;
+    use crate::config::{Config, SignerConfig};
+
+    #[test]
+    fn load_generated_configs() {
+        for (network, name) in [
+            (StacksNetwork::Devnet, "devnet"),
+            (StacksNetwork::Testnet, "testnet"),
+            (StacksNetwork::Mainnet, "mainnet"),
+        ] {
+            let path =
+                std::env::temp_dir().join(format!("relayer-{}-{}.toml", name, std::process::id()));
+            std::fs::write(&path, generate_config(&network)).unwrap();
+            let config = Config::from_file_path(&path.to_string_lossy());
+            std::fs::remove_file(&path).unwrap();
+
+            let config = config.unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(config.stacks.network.is_mainnet(), name == "mainnet");
+            // Not taken for an override, the prefix of which is STACKS_PYTH_RELAYER__
+            assert!(matches!(
+                config.stacks.signers.as_slice(),
+                [SignerConfig::Mnemonic {
+                    mnemonic: SecretSource::Env(var),
+                    ..
+                }] if var == "PYTH_RELAYER_MNEMONIC"
+            ));
+        }
+    }
 }
@@ -1,34 +1,103 @@
 pub mod file;
 pub mod generator;
+pub mod overrides;
+pub mod secrets;
+pub mod validation;
 
 use chainhook_sdk::observer::EventObserverConfig;
+use chainhook_sdk::stacks_rpc_client::clarity::codec::TransactionAnchorMode;
 use chainhook_sdk::stacks_rpc_client::clarity::vm::types::QualifiedContractIdentifier;
-use chainhook_sdk::stacks_rpc_client::crypto::Wallet;
 use chainhook_sdk::types::StacksNetwork;
 
-use file::ConfigFile;
+use crate::pyth::normalize_price_feed_id;
+
+use file::{ConfigFile, PriceFeedPolicyFile, SignerConfigFile};
+use overrides::ConfigOverride;
+use secrets::{Secret, SecretSource};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Read};
+use std::time::Duration;
+use validation::check_config_file;
 
 #[derive(Clone, Debug)]
 pub struct Config {
     pub pyth: PythConfig,
     pub stacks: StacksConfig,
     pub bridge: BridgeConfig,
+    pub wormhole: WormholeConfig,
     pub event_observer: EventObserverConfig,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum PythNetwork {
     Testnet,
     Mainnet,
 }
 
+impl PythNetwork {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PythNetwork::Testnet => "testnet",
+            PythNetwork::Mainnet => "mainnet",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PythConfig {
     pub network: PythNetwork,
     pub price_service_url: String,
+    pub price_service_api: PriceServiceApi,
     pub price_feeds_ids: Vec<String>,
+    /// Policies keyed by normalized price feed id.
+    pub price_feeds_policies: HashMap<String, PriceFeedPolicy>,
+}
+
+impl PythConfig {
+    pub fn price_feed_policy(&self, price_feed_id: &str) -> PriceFeedPolicy {
+        self.price_feeds_policies
+            .get(&normalize_price_feed_id(price_feed_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Conditions under which a price feed gets pushed on chain. Without any bound,
+/// the price feed is pushed on every update.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PriceFeedPolicy {
+    /// Push when the price moved by at least this many basis points from the on-chain price.
+    pub deviation_threshold_bps: Option<u64>,
+    /// Push when the on-chain price is older than this many seconds.
+    pub heartbeat_secs: Option<u64>,
+}
+
+/// Endpoint used for retrieving price updates from the price service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PriceServiceApi {
+    /// `/api/latest_vaas`, returning base64 encoded VAAs in the order of the requested ids.
+    LegacyLatestVaas,
+    /// Hermes `/v2/updates/price/latest`, returning PNAU updates.
+    HermesV2(BinaryEncoding),
+    /// Hermes `/v2/updates/price/stream`, pushing PNAU updates as they are published.
+    HermesV2Stream(BinaryEncoding),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BinaryEncoding {
+    Hex,
+    Base64,
+}
+
+impl BinaryEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryEncoding::Hex => "hex",
+            BinaryEncoding::Base64 => "base64",
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -36,77 +105,694 @@ pub struct StacksConfig {
     pub network: StacksNetwork,
     pub stacks_node_rpc_url: String,
     pub pyth_oracle_contract_address: QualifiedContractIdentifier,
-    pub wallet: Wallet,
+    pub pyth_governance_contract_address: QualifiedContractIdentifier,
+    pub pyth_oracle_entrypoint: OracleEntrypoint,
+    /// Accounts submitting the price updates, in rotation.
+    pub signers: Vec<SignerConfig>,
     pub start_block: u64,
 }
 
+/// Key signing the relayer transactions, see `crate::signer`.
+#[derive(Clone, Debug)]
+pub enum SignerConfig {
+    /// BIP39 mnemonic, read when loading the signer, and the derivation path of the key.
+    Mnemonic {
+        mnemonic: SecretSource,
+        derivation: String,
+        mainnet: bool,
+    },
+    /// Hex encoded secp256k1 private key.
+    PrivateKey(Secret),
+    /// Web3 Secret Storage keystore file, and its password.
+    Keystore { path: String, password: Secret },
+    /// URL of a signer speaking the remote signer protocol.
+    Remote { url: String },
+}
+
+const DEFAULT_DERIVATION_PATH: &str = "m/44'/5757'/0'/0/0";
+
+impl SignerConfig {
+    /// Parse the signer options of an account, `section` locating them in error messages.
+    fn from_config_file(
+        signer_config_file: &SignerConfigFile,
+        section: &str,
+        mainnet: bool,
+    ) -> Result<SignerConfig, String> {
+        let signer = match signer_config_file.signer.as_deref() {
+            None | Some("mnemonic") => {
+                let mut sources = vec![];
+                if let Some(mnemonic) = &signer_config_file.mnemonic {
+                    sources.push(SecretSource::Value(mnemonic.clone()));
+                }
+                if let Some(var) = &signer_config_file.mnemonic_env {
+                    sources.push(SecretSource::Env(var.clone()));
+                }
+                if let Some(path) = &signer_config_file.mnemonic_file {
+                    sources.push(SecretSource::File(path.clone()));
+                }
+                if signer_config_file.mnemonic_prompt.unwrap_or(false) {
+                    sources.push(SecretSource::Prompt);
+                }
+                if sources.len() > 1 {
+                    return Err(format!(
+                        "{0}.mnemonic, {0}.mnemonic_env, {0}.mnemonic_file and {0}.mnemonic_prompt are exclusive",
+                        section
+                    ));
+                }
+                SignerConfig::Mnemonic {
+                    mnemonic: sources.pop().ok_or(format!(
+                        "{0}.mnemonic, {0}.mnemonic_env, {0}.mnemonic_file or {0}.mnemonic_prompt required by signer",
+                        section
+                    ))?,
+                    derivation: signer_config_file
+                        .derivation_path
+                        .clone()
+                        .unwrap_or(DEFAULT_DERIVATION_PATH.into()),
+                    mainnet,
+                }
+            }
+            Some("private-key") => SignerConfig::PrivateKey(
+                signer_config_file
+                    .private_key
+                    .clone()
+                    .ok_or(format!("{}.private_key required by signer", section))?,
+            ),
+            Some("keystore") => SignerConfig::Keystore {
+                path: signer_config_file
+                    .keystore_path
+                    .clone()
+                    .ok_or(format!("{}.keystore_path required by signer", section))?,
+                password: signer_config_file
+                    .keystore_password
+                    .clone()
+                    .ok_or(format!("{}.keystore_password required by signer", section))?,
+            },
+            Some("remote") => SignerConfig::Remote {
+                url: signer_config_file
+                    .remote_signer_url
+                    .clone()
+                    .ok_or(format!("{}.remote_signer_url required by signer", section))?,
+            },
+            Some(signer) => {
+                return Err(format!(
+                    "{}.signer '{}' unsupported (mnemonic, private-key, keystore, remote)",
+                    section, signer
+                ))
+            }
+        };
+        Ok(signer)
+    }
+}
+
+/// `pyth-oracle-v2` function used for pushing price updates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OracleEntrypoint {
+    VerifyAndUpdatePriceFeeds,
+    /// `update-prices-feeds`, taking a `(list 4 (buff 2048))` of VAAs.
+    LegacyUpdatePricesFeeds,
+}
+
+impl OracleEntrypoint {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OracleEntrypoint::VerifyAndUpdatePriceFeeds => "verify-and-update-price-feeds",
+            OracleEntrypoint::LegacyUpdatePricesFeeds => "update-prices-feeds",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct BridgeConfig {
     pub price_updates_per_minute: u64,
-    pub enable_microblocks: bool,
-    pub enable_rbf: bool,
+    /// Price updates on microblock events, when `enable_microblocks` is set.
+    pub microblocks: Option<MicroblocksPolicy>,
+    /// Replace-by-fee of the pending transactions, when `enable_rbf` is set.
+    pub rbf: Option<RbfPolicy>,
+    pub fee_strategy: FeeStrategy,
+    pub balance_policy: BalancePolicy,
+    /// Blocks a transaction stays pending before new price updates avoid its account.
+    pub account_stuck_after_blocks: u64,
+    /// Simulate the price update transactions without broadcasting them, set by `--dry-run`.
+    pub dry_run: bool,
+}
+
+impl BridgeConfig {
+    /// Delay between two ticks of the price update clock.
+    pub fn price_update_interval(&self) -> Duration {
+        Duration::from_secs(60 / self.price_updates_per_minute)
+    }
+}
+
+/// Thresholds on the STX balance of each relayer account, in µSTX.
+#[derive(Clone, Debug)]
+pub struct BalancePolicy {
+    /// Warn once the balance drops below it.
+    pub warning_threshold: Option<u64>,
+    /// Price updates stop below it.
+    pub floor: u64,
+}
+
+/// Fee used when the node can't estimate a transaction fee, in µSTX.
+const DEFAULT_FEE: u64 = 10_000;
+
+/// Fees of the price update transactions, in µSTX.
+#[derive(Clone, Debug)]
+pub struct FeeStrategy {
+    pub estimate_tier: FeeEstimateTier,
+    pub floor: u64,
+    pub ceiling: u64,
+    /// Fees spent over the last hour, price updates pause past it.
+    pub hourly_budget: Option<u64>,
+    /// Fees spent over the last 24 hours, price updates pause past it.
+    pub daily_budget: Option<u64>,
+    pub escalation: Option<FeeEscalation>,
+}
+
+/// Fee estimates of `/v2/fees/transaction`, from the cheapest to the fastest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeEstimateTier {
+    Low,
+    Medium,
+    High,
+}
+
+impl FeeEstimateTier {
+    /// Index of the estimation in the response of the node.
+    pub fn index(&self) -> usize {
+        match self {
+            FeeEstimateTier::Low => 0,
+            FeeEstimateTier::Medium => 1,
+            FeeEstimateTier::High => 2,
+        }
+    }
+}
+
+/// Fees increase while price updates stay pending.
+#[derive(Clone, Debug)]
+pub struct FeeEscalation {
+    /// Blocks a price update stays pending before each increase.
+    pub after_blocks: u64,
+    /// Increase of the estimated fee at each step, in percent.
+    pub step_percent: u64,
+}
+
+impl FeeStrategy {
+    /// Fee of a transaction given the node estimate, if any, and the number of
+    /// blocks the oldest price update has been pending, within the floor and ceiling.
+    pub fn fee(&self, estimated_fee: Option<u64>, pending_blocks: u64) -> u64 {
+        let fee = estimated_fee.unwrap_or(DEFAULT_FEE);
+        let fee = match &self.escalation {
+            Some(escalation) => {
+                let steps = pending_blocks / escalation.after_blocks;
+                fee.saturating_add(
+                    fee.saturating_mul(escalation.step_percent.saturating_mul(steps)) / 100,
+                )
+            }
+            None => fee,
+        };
+        fee.max(self.floor).min(self.ceiling)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MicroblocksPolicy {
+    /// `OffChainOnly` or `Any`, letting miners include the updates in microblocks.
+    pub anchor_mode: TransactionAnchorMode,
+    /// Rate of the microblock updates, on top of the block updates.
+    pub updates_per_minute: u64,
+}
+
+impl MicroblocksPolicy {
+    /// Minimum delay between two microblock updates.
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(60_000 / self.updates_per_minute)
+    }
+}
+
+/// Pending price update transactions get replaced at the same nonce, with fresh
+/// price updates and a bumped fee, once they missed enough blocks.
+#[derive(Clone, Debug)]
+pub struct RbfPolicy {
+    /// Fee increase of a replacement, in percent of the replaced transaction fee.
+    pub fee_bump_percent: u64,
+    /// Minimum fee increase of a replacement, in µSTX.
+    pub min_fee_increment: u64,
+    /// Transactions are no longer replaced past this fee, in µSTX.
+    pub max_fee: u64,
+    /// Number of blocks a transaction stays pending before getting replaced.
+    pub rebroadcast_after_blocks: u64,
+}
+
+impl RbfPolicy {
+    /// Fee of the transaction replacing one paying `previous_fee`, at least
+    /// `estimated_fee`, or None past the fee ceiling.
+    pub fn replacement_fee(&self, previous_fee: u64, estimated_fee: u64) -> Option<u64> {
+        let bumped_fee = previous_fee.saturating_add(
+            (previous_fee.saturating_mul(self.fee_bump_percent) / 100).max(self.min_fee_increment),
+        );
+        if bumped_fee > self.max_fee {
+            return None;
+        }
+        Some(bumped_fee.max(estimated_fee).min(self.max_fee))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum GuardianSetSource {
+    Builtin,
+    File(String),
+    OnChain,
+}
+
+#[derive(Clone, Debug)]
+pub struct WormholeConfig {
+    pub guardian_set_source: GuardianSetSource,
+    pub core_contract_address: QualifiedContractIdentifier,
 }
 
 impl Config {
     pub fn from_file_path(file_path: &str) -> Result<Config, String> {
-        let file = File::open(file_path)
-            .map_err(|e| format!("unable to read file {}\n{:?}", file_path, e))?;
-        let mut file_reader = BufReader::new(file);
-        let mut file_buffer = vec![];
-        file_reader
-            .read_to_end(&mut file_buffer)
-            .map_err(|e| format!("unable to read file {}\n{:?}", file_path, e))?;
-
+        let file_buffer = read_file(file_path)?;
         let config_file: ConfigFile = match toml::from_slice(&file_buffer) {
             Ok(s) => s,
             Err(e) => {
-                return Err(format!("Config file malformatted {}", e.to_string()));
+                return Err(format!("Config file malformatted {}", e));
             }
         };
         Config::from_config_file(config_file)
     }
 
+    /// Read a config file, its fields overridden by the environment variables `vars`,
+    /// then by `flags`.
+    pub fn load(
+        file_path: &str,
+        vars: impl Iterator<Item = (String, String)>,
+        flags: &[String],
+    ) -> Result<Config, String> {
+        let config_file: ConfigFile = match Config::load_toml(file_path, vars, flags)?.try_into() {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(format!("Config file malformatted {}", e));
+            }
+        };
+        Config::from_config_file(config_file)
+    }
+
+    /// Config file as loaded by [`Config::load`], before being checked.
+    pub fn load_toml(
+        file_path: &str,
+        vars: impl Iterator<Item = (String, String)>,
+        flags: &[String],
+    ) -> Result<toml::Value, String> {
+        let file_buffer = read_file(file_path)?;
+        let mut config: toml::Value = toml::from_slice(&file_buffer)
+            .map_err(|e| format!("Config file malformatted {}", e))?;
+        let flags = flags
+            .iter()
+            .map(|flag| ConfigOverride::from_flag(flag))
+            .collect::<Result<Vec<_>, _>>()?;
+        for config_override in ConfigOverride::from_env(vars).iter().chain(flags.iter()) {
+            config_override.apply(&mut config)?;
+        }
+        Ok(config)
+    }
+
     pub fn from_config_file(config_file: ConfigFile) -> Result<Config, String> {
-        let pyth_network = match config_file.pyth.network.as_str() {
-            "testnet" => PythNetwork::Testnet,
-            "mainnet" => PythNetwork::Mainnet,
-            _ => return Err("network.mode not supported".to_string()),
+        let problems = check_config_file(&config_file);
+        if !problems.is_empty() {
+            return Err(problems.join("\n"));
+        }
+
+        let price_service_encoding = match config_file.pyth.price_service_encoding.as_deref() {
+            None | Some("hex") => BinaryEncoding::Hex,
+            Some("base64") => BinaryEncoding::Base64,
+            Some(encoding) => {
+                return Err(format!(
+                    "pyth.price_service_encoding '{}' unsupported (hex, base64)",
+                    encoding
+                ))
+            }
+        };
+        // Each of the price service API and the oracle entrypoint defaults to the
+        // one matching the other, Hermes v2 and verify-and-update-price-feeds otherwise
+        let legacy_entrypoint =
+            config_file.stacks.pyth_oracle_entrypoint.as_deref() == Some("update-prices-feeds");
+        let price_service_api = match config_file.pyth.price_service_api.as_deref() {
+            None if legacy_entrypoint => PriceServiceApi::LegacyLatestVaas,
+            None => PriceServiceApi::HermesV2(price_service_encoding),
+            Some("legacy") => PriceServiceApi::LegacyLatestVaas,
+            Some("hermes-v2") => PriceServiceApi::HermesV2(price_service_encoding),
+            Some("hermes-v2-stream") => PriceServiceApi::HermesV2Stream(price_service_encoding),
+            Some(api) => {
+                return Err(format!(
+                    "pyth.price_service_api '{}' unsupported (legacy, hermes-v2, hermes-v2-stream)",
+                    api
+                ))
+            }
         };
 
+        let default_price_feed_policy = config_file
+            .pyth
+            .default_price_feed_policy
+            .clone()
+            .unwrap_or_default();
+        let price_feeds_ids = config_file
+            .pyth
+            .price_feeds_ids
+            .iter()
+            .map(|price_feed_id| normalize_price_feed_id(price_feed_id))
+            .collect::<Vec<_>>();
+        let mut price_feeds_policies = HashMap::new();
+        for price_feed_id in price_feeds_ids.iter() {
+            price_feeds_policies.insert(
+                price_feed_id.clone(),
+                PriceFeedPolicy {
+                    deviation_threshold_bps: default_price_feed_policy.deviation_threshold_bps,
+                    heartbeat_secs: default_price_feed_policy.heartbeat_secs,
+                },
+            );
+        }
+        for (price_feed_id, policy) in config_file
+            .pyth
+            .price_feeds_policies
+            .clone()
+            .unwrap_or_default()
+        {
+            let price_feed_id = normalize_price_feed_id(&price_feed_id);
+            let PriceFeedPolicyFile {
+                deviation_threshold_bps,
+                heartbeat_secs,
+            } = policy;
+            let entry = price_feeds_policies.get_mut(&price_feed_id).ok_or(format!(
+                "pyth.price_feeds_policies: price feed 0x{} not listed in price_feeds_ids",
+                price_feed_id
+            ))?;
+            if deviation_threshold_bps.is_some() {
+                entry.deviation_threshold_bps = deviation_threshold_bps;
+            }
+            if heartbeat_secs.is_some() {
+                entry.heartbeat_secs = heartbeat_secs;
+            }
+        }
+
         let stacks_network = StacksNetwork::from_str(&config_file.stacks.network)?;
 
         let event_observer =
             EventObserverConfig::new_using_overrides(config_file.event_observer.as_ref())?;
 
-        let wallet = Wallet {
-            mnemonic: config_file.stacks.mnemonic.clone(),
-            derivation: config_file.stacks.derivation_path,
-            mainnet: stacks_network.is_mainnet(),
+        // A single account in [stacks], or a list of accounts in [[stacks.accounts]]
+        let signers = match &config_file.stacks.accounts {
+            None => vec![SignerConfig::from_config_file(
+                &config_file.stacks.signer,
+                "stacks",
+                stacks_network.is_mainnet(),
+            )?],
+            Some(accounts) => {
+                if config_file.stacks.signer != SignerConfigFile::default() {
+                    return Err(
+                        "stacks.accounts can't be combined with the signer options of [stacks]"
+                            .into(),
+                    );
+                }
+                if accounts.is_empty() {
+                    return Err("stacks.accounts must list at least 1 account".into());
+                }
+                accounts
+                    .iter()
+                    .enumerate()
+                    .map(|(index, account)| {
+                        SignerConfig::from_config_file(
+                            account,
+                            &format!("stacks.accounts[{}]", index),
+                            stacks_network.is_mainnet(),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            }
         };
         let pyth_oracle_contract_address =
             QualifiedContractIdentifier::parse(&config_file.stacks.pyth_oracle_contract_address)
-                .map_err(|_e| format!("unable to parse pyth_oracle_contract_address"))?;
+                .map_err(|_e| {
+                    format!(
+                        "stacks.pyth_oracle_contract_address '{}' invalid",
+                        config_file.stacks.pyth_oracle_contract_address
+                    )
+                })?;
+        // The governance contract is deployed next to the oracle contract by default
+        let pyth_governance_contract_address = QualifiedContractIdentifier::parse(
+            &config_file
+                .stacks
+                .pyth_governance_contract_address
+                .unwrap_or(format!(
+                    "{}.pyth-governance-v1",
+                    pyth_oracle_contract_address.issuer
+                )),
+        )
+        .map_err(|_e| "stacks.pyth_governance_contract_address invalid".to_string())?;
+        let pyth_oracle_entrypoint = match config_file.stacks.pyth_oracle_entrypoint.as_deref() {
+            None if price_service_api == PriceServiceApi::LegacyLatestVaas => {
+                OracleEntrypoint::LegacyUpdatePricesFeeds
+            }
+            None | Some("verify-and-update-price-feeds") => {
+                OracleEntrypoint::VerifyAndUpdatePriceFeeds
+            }
+            Some("update-prices-feeds") => OracleEntrypoint::LegacyUpdatePricesFeeds,
+            Some(entrypoint) => {
+                return Err(format!(
+                    "stacks.pyth_oracle_entrypoint '{}' unsupported (verify-and-update-price-feeds, update-prices-feeds)",
+                    entrypoint
+                ))
+            }
+        };
+        // update-prices-feeds takes the VAAs of price attestations, verify-and-update-price-feeds
+        // the accumulator updates of Hermes v2
+        let legacy_price_service_api = price_service_api == PriceServiceApi::LegacyLatestVaas;
+        if legacy_price_service_api
+            != (pyth_oracle_entrypoint == OracleEntrypoint::LegacyUpdatePricesFeeds)
+        {
+            return Err(format!(
+                "stacks.pyth_oracle_entrypoint '{}' can't submit the updates of pyth.price_service_api '{}'",
+                pyth_oracle_entrypoint.as_str(),
+                config_file.pyth.price_service_api.as_deref().unwrap_or("legacy")
+            ));
+        }
+
+        let microblocks = match config_file.bridge.enable_microblocks.unwrap_or(false) {
+            true => {
+                let anchor_mode = match config_file.bridge.microblock_anchor_mode.as_deref() {
+                    None | Some("any") => TransactionAnchorMode::Any,
+                    Some("offchain-only") => TransactionAnchorMode::OffChainOnly,
+                    Some(anchor_mode) => {
+                        return Err(format!(
+                            "bridge.microblock_anchor_mode '{}' unsupported (any, offchain-only)",
+                            anchor_mode
+                        ))
+                    }
+                };
+                let updates_per_minute = config_file
+                    .bridge
+                    .microblock_updates_per_minute
+                    .unwrap_or(config_file.bridge.price_updates_per_minute);
+                if updates_per_minute == 0 {
+                    return Err("bridge.microblock_updates_per_minute must be at least 1".into());
+                }
+                Some(MicroblocksPolicy {
+                    anchor_mode,
+                    updates_per_minute,
+                })
+            }
+            false => None,
+        };
+        let rbf = match config_file.bridge.enable_rbf {
+            true => Some(RbfPolicy {
+                fee_bump_percent: config_file.bridge.rbf_fee_bump_percent.unwrap_or(25),
+                min_fee_increment: config_file.bridge.rbf_min_fee_increment.unwrap_or(1_000),
+                max_fee: config_file.bridge.rbf_max_fee.unwrap_or(1_000_000),
+                rebroadcast_after_blocks: config_file
+                    .bridge
+                    .rbf_rebroadcast_after_blocks
+                    .unwrap_or(1),
+            }),
+            false => None,
+        };
+        if let Some(rbf) = &rbf {
+            if rbf.fee_bump_percent == 0 && rbf.min_fee_increment == 0 {
+                return Err(
+                    "bridge.rbf_fee_bump_percent and bridge.rbf_min_fee_increment can't both be 0"
+                        .into(),
+                );
+            }
+            if rbf.rebroadcast_after_blocks == 0 {
+                return Err("bridge.rbf_rebroadcast_after_blocks must be at least 1".into());
+            }
+        }
+
+        let estimate_tier = match config_file.bridge.fee_estimate_tier.as_deref() {
+            Some("low") => FeeEstimateTier::Low,
+            None | Some("medium") => FeeEstimateTier::Medium,
+            Some("high") => FeeEstimateTier::High,
+            Some(tier) => {
+                return Err(format!(
+                    "bridge.fee_estimate_tier '{}' unsupported (low, medium, high)",
+                    tier
+                ))
+            }
+        };
+        let escalation = match config_file.bridge.fee_escalation_after_blocks {
+            Some(0) => return Err("bridge.fee_escalation_after_blocks must be at least 1".into()),
+            Some(after_blocks) => Some(FeeEscalation {
+                after_blocks,
+                step_percent: config_file.bridge.fee_escalation_percent.unwrap_or(25),
+            }),
+            None => None,
+        };
+        let fee_strategy = FeeStrategy {
+            estimate_tier,
+            floor: config_file.bridge.fee_floor.unwrap_or(0),
+            ceiling: config_file.bridge.fee_ceiling.unwrap_or(1_000_000),
+            hourly_budget: config_file.bridge.fee_budget_hourly,
+            daily_budget: config_file.bridge.fee_budget_daily,
+            escalation,
+        };
+        if fee_strategy.floor > fee_strategy.ceiling {
+            return Err("bridge.fee_floor can't be above bridge.fee_ceiling".into());
+        }
+        let balance_policy = BalancePolicy {
+            warning_threshold: config_file.bridge.balance_warning_threshold,
+            floor: config_file.bridge.balance_floor.unwrap_or(0),
+        };
+        if matches!(balance_policy.warning_threshold, Some(threshold) if threshold < balance_policy.floor)
+        {
+            return Err(
+                "bridge.balance_warning_threshold can't be below bridge.balance_floor".into(),
+            );
+        }
+
+        let account_stuck_after_blocks = config_file.bridge.account_stuck_after_blocks.unwrap_or(3);
+        if account_stuck_after_blocks == 0 {
+            return Err("bridge.account_stuck_after_blocks must be at least 1".into());
+        }
+
+        let wormhole_config_file = config_file.wormhole.unwrap_or_default();
+        let guardian_set_source = match (
+            wormhole_config_file.guardian_set_source.as_deref(),
+            wormhole_config_file.guardian_sets_path,
+        ) {
+            (None, None) | (Some("builtin"), _) => GuardianSetSource::Builtin,
+            (None, Some(path)) | (Some("file"), Some(path)) => GuardianSetSource::File(path),
+            (Some("onchain"), _) => GuardianSetSource::OnChain,
+            (Some("file"), None) => {
+                return Err("wormhole.guardian_sets_path required by guardian_set_source".into())
+            }
+            (Some(source), _) => {
+                return Err(format!(
+                    "wormhole.guardian_set_source '{}' unsupported (builtin, file, onchain)",
+                    source
+                ))
+            }
+        };
+        // The wormhole core contract is deployed next to the oracle contract by default
+        let core_contract_address = match wormhole_config_file.core_contract_address {
+            Some(core_contract_address) => {
+                QualifiedContractIdentifier::parse(&core_contract_address)
+                    .map_err(|_e| "wormhole.core_contract_address invalid".to_string())?
+            }
+            None => QualifiedContractIdentifier::parse(&format!(
+                "{}.wormhole-core-v2",
+                pyth_oracle_contract_address.issuer
+            ))
+            .map_err(|_e| "wormhole.core_contract_address invalid".to_string())?,
+        };
 
         let config = Config {
             pyth: PythConfig {
-                network: pyth_network,
+                network: config_file.pyth.network.clone(),
                 price_service_url: config_file.pyth.price_service_url.clone(),
+                price_service_api,
                 price_feeds_ids: config_file.pyth.price_feeds_ids.clone(),
+                price_feeds_policies,
             },
             stacks: StacksConfig {
                 stacks_node_rpc_url: config_file.stacks.stacks_node_rpc_url.to_string(),
-                wallet,
+                signers,
                 pyth_oracle_contract_address,
+                pyth_governance_contract_address,
+                pyth_oracle_entrypoint,
                 network: stacks_network,
                 start_block: config_file.stacks.start_block,
             },
             bridge: BridgeConfig {
                 price_updates_per_minute: config_file.bridge.price_updates_per_minute,
-                enable_microblocks: config_file.bridge.enable_microblocks.unwrap_or(false),
-                enable_rbf: config_file.bridge.enable_rbf,
+                microblocks,
+                rbf,
+                fee_strategy,
+                balance_policy,
+                account_stuck_after_blocks,
+                dry_run: false,
+            },
+            wormhole: WormholeConfig {
+                guardian_set_source,
+                core_contract_address,
             },
             event_observer,
         };
         Ok(config)
     }
 }
+
+fn read_file(file_path: &str) -> Result<Vec<u8>, String> {
+    let file =
+        File::open(file_path).map_err(|e| format!("unable to read file {}\n{:?}", file_path, e))?;
+    let mut file_reader = BufReader::new(file);
+    let mut file_buffer = vec![];
+    file_reader
+        .read_to_end(&mut file_buffer)
+        .map_err(|e| format!("unable to read file {}\n{:?}", file_path, e))?;
+    Ok(file_buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rbf_policy() -> RbfPolicy {
+        RbfPolicy {
+            fee_bump_percent: 25,
+            min_fee_increment: 1_000,
+            max_fee: 100_000,
+            rebroadcast_after_blocks: 2,
+        }
+    }
+
+    #[test]
+    fn replacement_fee_bumps_by_percent() {
+        let rbf = rbf_policy();
+        assert_eq!(rbf.replacement_fee(20_000, 0), Some(25_000));
+        // The estimation wins when above the bumped fee
+        assert_eq!(rbf.replacement_fee(20_000, 30_000), Some(30_000));
+    }
+
+    #[test]
+    fn replacement_fee_bumps_by_min_increment() {
+        let rbf = rbf_policy();
+        // 25% of 2000 is below the minimum increment the node accepts
+        assert_eq!(rbf.replacement_fee(2_000, 0), Some(3_000));
+        assert_eq!(rbf.replacement_fee(0, 0), Some(1_000));
+        for previous_fee in [0, 1, 999, 3_999, 4_000, 4_001, 50_000] {
+            let fee = rbf.replacement_fee(previous_fee, 0).unwrap();
+            assert!(fee >= previous_fee + rbf.min_fee_increment);
+        }
+    }
+
+    #[test]
+    fn replacement_fee_capped() {
+        let rbf = rbf_policy();
+        assert_eq!(rbf.replacement_fee(80_000, 0), Some(100_000));
+        // The estimation never lifts the fee past the maximum
+        assert_eq!(rbf.replacement_fee(40_000, 500_000), Some(100_000));
+        // A bump past the maximum isn't replaced, rather than bumped below the increment
+        assert_eq!(rbf.replacement_fee(80_001, 0), None);
+        assert_eq!(rbf.replacement_fee(99_500, 0), None);
+        assert_eq!(rbf.replacement_fee(u64::MAX, 0), None);
+    }
+}
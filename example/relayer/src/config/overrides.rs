@@ -0,0 +1,333 @@
+//! Overrides of the config file fields, from `STACKS_PYTH_RELAYER__SECTION__FIELD`
+//! environment variables and `--set section.field=value` flags.
+
+use toml::Value;
+
+pub const ENV_PREFIX: &str = "STACKS_PYTH_RELAYER__";
+
+/// Fields holding key material, redacted when printing a config.
+const SECRET_FIELDS: &[&str] = &[
+    "mnemonic",
+    "private_key",
+    "keystore_password",
+    "bitcoind_rpc_password",
+];
+
+#[derive(Clone, Debug)]
+pub struct ConfigOverride {
+    /// Keys leading to the field, from the top-level section. Arrays of tables,
+    /// such as `stacks.accounts`, are indexed by position.
+    pub path: Vec<String>,
+    /// TOML value, or a string as is.
+    pub value: String,
+    /// Environment variable or flag the override comes from, for error messages.
+    pub origin: String,
+}
+
+impl ConfigOverride {
+    /// Parse a `section.field=value` flag.
+    pub fn from_flag(flag: &str) -> Result<ConfigOverride, String> {
+        let (path, value) = flag
+            .split_once('=')
+            .ok_or(format!("--set {}: expected section.field=value", flag))?;
+        let path = path
+            .trim()
+            .split('.')
+            .map(|key| key.to_string())
+            .collect::<Vec<_>>();
+        if path.len() < 2 || path.iter().any(|key| key.is_empty()) {
+            return Err(format!("--set {}: expected section.field=value", flag));
+        }
+        Ok(ConfigOverride {
+            path,
+            value: value.to_string(),
+            origin: format!("--set {}", flag),
+        })
+    }
+
+    /// Overrides set in the environment variables `vars`, sorted by variable name.
+    pub fn from_env(vars: impl Iterator<Item = (String, String)>) -> Vec<ConfigOverride> {
+        let mut overrides = vars
+            .filter_map(|(var, value)| {
+                let path = var
+                    .strip_prefix(ENV_PREFIX)?
+                    .split("__")
+                    .map(|key| key.to_lowercase())
+                    .collect::<Vec<_>>();
+                if path.len() < 2 || path.iter().any(|key| key.is_empty()) {
+                    return None;
+                }
+                Some(ConfigOverride {
+                    path,
+                    value,
+                    origin: format!("${}", var),
+                })
+            })
+            .collect::<Vec<_>>();
+        overrides.sort_by(|a, b| a.origin.cmp(&b.origin));
+        overrides
+    }
+
+    /// Set the field in the parsed config file, creating the missing sections.
+    pub fn apply(&self, config: &mut Value) -> Result<(), String> {
+        let (field, sections) = self.path.split_last().expect("empty override path");
+        let mut node = config;
+        for (depth, key) in sections.iter().enumerate() {
+            node = match node {
+                Value::Table(table) => table
+                    .entry(key.clone())
+                    .or_insert_with(|| Value::Table(Default::default())),
+                Value::Array(array) => key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| array.get_mut(index))
+                    .ok_or(format!(
+                        "{}: no entry {} in {}",
+                        self.origin,
+                        key,
+                        sections[..depth].join(".")
+                    ))?,
+                _ => {
+                    return Err(format!(
+                        "{}: {} is not a section",
+                        self.origin,
+                        sections[..depth].join(".")
+                    ))
+                }
+            };
+        }
+        let table = match node {
+            Value::Table(table) => table,
+            _ => {
+                return Err(format!(
+                    "{}: {} is not a section",
+                    self.origin,
+                    sections.join(".")
+                ))
+            }
+        };
+        // Strings are taken as is, other values are parsed as TOML, falling back to a string
+        let value = match table.get(field) {
+            Some(Value::String(_)) => Value::String(self.value.clone()),
+            _ => parse_value(&self.value).unwrap_or(Value::String(self.value.clone())),
+        };
+        table.insert(field.clone(), value);
+        Ok(())
+    }
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    toml::from_str::<toml::value::Table>(&format!("value = {}", value))
+        .ok()?
+        .remove("value")
+}
+
+/// Replace the secrets of a parsed config file, at any depth.
+pub fn redact(config: &mut Value) {
+    match config {
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) && value.is_str() {
+                    *value = Value::String("<redacted>".into());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const CONFIG_PATH: &str = "bridge-testnet.toml";
+
+    fn flags(flags: &[&str]) -> Vec<String> {
+        flags.iter().map(|flag| flag.to_string()).collect()
+    }
+
+    /// Environment variables prefixed by `ENV_PREFIX`.
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(var, value)| (format!("{}{}", ENV_PREFIX, var), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn overrides_precedence() {
+        let env = [
+            ("WORMHOLE__GUARDIAN_SET_SOURCE", "onchain"),
+            ("BRIDGE__PRICE_UPDATES_PER_MINUTE", "10"),
+            ("BRIDGE__ENABLE_MICROBLOCKS", "false"),
+        ];
+        let config = Config::load_toml(
+            CONFIG_PATH,
+            vars(&env),
+            &flags(&["bridge.price_updates_per_minute=12"]),
+        )
+        .unwrap();
+        // Flags over the environment, over the file
+        assert_eq!(
+            config["bridge"]["price_updates_per_minute"].as_integer(),
+            Some(12)
+        );
+        assert_eq!(
+            config["bridge"]["enable_microblocks"].as_bool(),
+            Some(false)
+        );
+        assert_eq!(config["bridge"]["enable_rbf"].as_bool(), Some(true));
+        assert_eq!(
+            config["wormhole"]["guardian_set_source"].as_str(),
+            Some("onchain")
+        );
+        let typed_config = Config::load(CONFIG_PATH, vars(&env), &[]).unwrap();
+        assert_eq!(typed_config.bridge.price_updates_per_minute, 10);
+    }
+
+    #[test]
+    fn overrides_from_env() {
+        let overrides = ConfigOverride::from_env(
+            vars(&[
+                ("STACKS__START_BLOCK", "12"),
+                ("BRIDGE__ENABLE_RBF", "false"),
+                ("BRIDGE", "x"),
+                ("BRIDGE____ENABLE_RBF", "x"),
+            ])
+            .chain([("PATH".to_string(), "/usr/bin".to_string())]),
+        );
+        assert_eq!(
+            overrides
+                .iter()
+                .map(|config_override| (
+                    config_override.path.join("."),
+                    config_override.value.as_str(),
+                    config_override.origin.clone()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "bridge.enable_rbf".to_string(),
+                    "false",
+                    format!("${}BRIDGE__ENABLE_RBF", ENV_PREFIX)
+                ),
+                (
+                    "stacks.start_block".to_string(),
+                    "12",
+                    format!("${}STACKS__START_BLOCK", ENV_PREFIX)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn override_strings_as_is() {
+        let config = Config::load_toml(
+            CONFIG_PATH,
+            std::iter::empty(),
+            &flags(&[
+                "stacks.start_block=12",
+                "stacks.network=12",
+                "stacks.foo=[1, 2]",
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config["stacks"]["start_block"].as_integer(), Some(12));
+        // Strings in the file stay strings
+        assert_eq!(config["stacks"]["network"].as_str(), Some("12"));
+        assert_eq!(
+            config["stacks"]["foo"].as_array().map(|array| array.len()),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn reject_unknown_fields() {
+        let error = Config::load(
+            CONFIG_PATH,
+            std::iter::empty(),
+            &flags(&["bridge.price_update_per_minute=3"]),
+        )
+        .unwrap_err();
+        assert_eq!(error, "bridge.price_update_per_minute unknown");
+        let error = Config::load(
+            CONFIG_PATH,
+            std::iter::empty(),
+            &flags(&["bridge.enable_rbf.x=1"]),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "--set bridge.enable_rbf.x=1: bridge.enable_rbf is not a section"
+        );
+        for flag in ["bridge", "bridge.=1", "=1"] {
+            assert!(ConfigOverride::from_flag(flag).is_err(), "{}", flag);
+        }
+    }
+
+    #[test]
+    fn reject_invalid_values() {
+        for (flag, error) in [
+            (
+                "bridge.price_updates_per_minute=often",
+                "invalid type: string \"often\", expected u64 for key `bridge.price_updates_per_minute`",
+            ),
+            (
+                "bridge.enable_rbf=\"true\"",
+                "invalid type: string \"true\", expected a boolean for key `bridge.enable_rbf`",
+            ),
+            (
+                "pyth.price_feeds_ids=0xec7a775f",
+                // Parsed as a TOML hex integer
+                "invalid type: integer `3967448927`, expected a sequence for key `pyth.price_feeds_ids`",
+            ),
+        ] {
+            let message = Config::load(CONFIG_PATH, std::iter::empty(), &flags(&[flag])).unwrap_err();
+            assert!(message.contains(error), "{}: {}", flag, message);
+        }
+    }
+
+    #[test]
+    fn redact_secret_fields() {
+        let mut config = Config::load_toml(
+            CONFIG_PATH,
+            std::iter::empty(),
+            &flags(&[
+                "stacks.private_key=f00d",
+                "stacks.keystore_password=hunter2",
+                "stacks.mnemonic_env=PYTH_RELAYER_MNEMONIC",
+            ]),
+        )
+        .unwrap();
+        config["stacks"].as_table_mut().unwrap().insert(
+            "accounts".into(),
+            toml::from_str("mnemonic = \"***\"").unwrap(),
+        );
+        redact(&mut config);
+        for secret in [
+            &config["stacks"]["mnemonic"],
+            &config["stacks"]["private_key"],
+            &config["stacks"]["keystore_password"],
+            &config["stacks"]["accounts"]["mnemonic"],
+            &config["event_observer"]["bitcoind_rpc_password"],
+        ] {
+            assert_eq!(secret.as_str(), Some("<redacted>"));
+        }
+        let redacted = toml::to_string(&config).unwrap();
+        for secret in ["f00d", "hunter2"] {
+            assert!(!redacted.contains(secret));
+        }
+        assert_eq!(
+            config["stacks"]["mnemonic_env"].as_str(),
+            Some("PYTH_RELAYER_MNEMONIC")
+        );
+        assert_eq!(
+            config["event_observer"]["bitcoind_rpc_username"].as_str(),
+            Some("devnet")
+        );
+    }
+}
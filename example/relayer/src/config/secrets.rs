@@ -0,0 +1,116 @@
+//! Key material of the config, kept out of the `Debug` output and loaded from the
+//! config file, the environment, a file or an interactive prompt.
+
+use std::fmt;
+use std::io::{BufRead, IsTerminal};
+
+/// String holding key material, redacted from the `Debug` output.
+#[derive(Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(secret: String) -> Secret {
+        Secret(secret)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<redacted>")
+    }
+}
+
+/// Where a secret is read from, once the signer gets loaded.
+#[derive(Clone, Debug)]
+pub enum SecretSource {
+    /// Set in the config file.
+    Value(Secret),
+    /// Name of an environment variable.
+    Env(String),
+    /// Path of a file only readable by its owner.
+    File(String),
+    /// Typed in, or piped to stdin.
+    Prompt,
+}
+
+impl SecretSource {
+    /// Read the secret, `name` describing it in prompts and error messages.
+    pub fn load(&self, name: &str) -> Result<Secret, String> {
+        let secret = match self {
+            SecretSource::Value(secret) => return Ok(secret.clone()),
+            SecretSource::Env(var) => std::env::var(var)
+                .map_err(|e| format!("unable to read {} from ${}: {}", name, var, e))?,
+            SecretSource::File(path) => read_secret_file(path)
+                .map_err(|e| format!("unable to read {} from {}: {}", name, path, e))?,
+            SecretSource::Prompt => {
+                prompt(name).map_err(|e| format!("unable to read {}: {}", name, e))?
+            }
+        };
+        let secret = secret.trim();
+        if secret.is_empty() {
+            return Err(format!("{} is empty", name));
+        }
+        Ok(Secret(secret.to_string()))
+    }
+}
+
+fn read_secret_file(path: &str) -> Result<String, String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path)
+            .map_err(|e| e.to_string())?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            return Err(format!(
+                "file accessible by other users (mode {:o}), restrict it with chmod 600",
+                mode & 0o777
+            ));
+        }
+    }
+    std::fs::read_to_string(path).map_err(|e| e.to_string())
+}
+
+/// Prompt for a secret without echoing it on a terminal, or read the first line
+/// piped to stdin.
+fn prompt(name: &str) -> Result<String, String> {
+    if std::io::stdin().is_terminal() {
+        return rpassword::prompt_password(format!("Enter {}: ", name)).map_err(|e| e.to_string());
+    }
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| e.to_string())?;
+    Ok(line)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn read_secret_file_owner_only() {
+        let path = std::env::temp_dir().join(format!("relayer-secret-{}", std::process::id()));
+        std::fs::write(&path, "secret\n").unwrap();
+        let source = SecretSource::File(path.to_string_lossy().to_string());
+        for mode in [0o644, 0o640, 0o604, 0o620, 0o660, 0o610] {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            let error = source.load("mnemonic").unwrap_err();
+            assert!(error.ends_with("restrict it with chmod 600"), "{}", error);
+        }
+        for mode in [0o600, 0o400, 0o700] {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            assert_eq!(source.load("mnemonic").unwrap().expose(), "secret");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,538 @@
+//! Semantic checks of a config file, reported with the line of the field at fault.
+
+use std::collections::HashSet;
+use std::net::TcpListener;
+
+use chainhook_sdk::observer::EventObserverConfig;
+use chainhook_sdk::stacks_rpc_client::clarity::stacks_common::address::{
+    C32_ADDRESS_VERSION_MAINNET_MULTISIG, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+};
+use chainhook_sdk::stacks_rpc_client::clarity::vm::types::QualifiedContractIdentifier;
+use chainhook_sdk::types::StacksNetwork;
+
+use crate::pyth::normalize_price_feed_id;
+
+use super::file::{ConfigFile, SignerConfigFile};
+use super::{read_file, Config, PythNetwork};
+
+/// Sections of the config file, prefixing the field paths of the problems.
+const SECTIONS: &[&str] = &["pyth", "stacks", "bridge", "wormhole", "event_observer"];
+
+#[derive(Clone, Debug)]
+pub struct ConfigProblem {
+    /// Line of the field at fault, 1-based, when it can be located.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Checks not depending on the environment, each problem starting with the path
+/// of the field at fault.
+pub fn check_config_file(config_file: &ConfigFile) -> Vec<String> {
+    let mut problems = vec![];
+
+    let mut unknown_fields = vec![
+        (String::new(), &config_file.unknown_fields),
+        ("pyth.".into(), &config_file.pyth.unknown_fields),
+        ("stacks.".into(), &config_file.stacks.signer.unknown_fields),
+        ("bridge.".into(), &config_file.bridge.unknown_fields),
+    ];
+    if let Some(wormhole) = &config_file.wormhole {
+        unknown_fields.push(("wormhole.".into(), &wormhole.unknown_fields));
+    }
+    for (index, account) in config_file.stacks.accounts.iter().flatten().enumerate() {
+        unknown_fields.push((
+            format!("stacks.accounts[{}].", index),
+            &account.unknown_fields,
+        ));
+    }
+    for (section, fields) in unknown_fields {
+        for field in fields.keys() {
+            problems.push(format!("{}{} unknown", section, field));
+        }
+    }
+
+    match StacksNetwork::from_str(&config_file.stacks.network) {
+        Err(_) => problems.push(format!(
+            "stacks.network '{}' unsupported (devnet, testnet, mainnet)",
+            config_file.stacks.network
+        )),
+        Ok(stacks_network) => {
+            // Price feeds have different ids on Pyth mainnet and testnet, relayed to
+            // Stacks mainnet and to the test networks respectively
+            let pyth_network = match stacks_network.is_mainnet() {
+                true => PythNetwork::Mainnet,
+                false => PythNetwork::Testnet,
+            };
+            if config_file.pyth.network != pyth_network {
+                problems.push(format!(
+                    "pyth.network '{}' doesn't match stacks.network '{}'",
+                    config_file.pyth.network.as_str(),
+                    config_file.stacks.network
+                ));
+            }
+
+            let contract_addresses = [
+                (
+                    "stacks.pyth_oracle_contract_address",
+                    Some(&config_file.stacks.pyth_oracle_contract_address),
+                ),
+                (
+                    "stacks.pyth_governance_contract_address",
+                    config_file.stacks.pyth_governance_contract_address.as_ref(),
+                ),
+                (
+                    "wormhole.core_contract_address",
+                    config_file
+                        .wormhole
+                        .as_ref()
+                        .and_then(|wormhole| wormhole.core_contract_address.as_ref()),
+                ),
+            ];
+            for (field, contract_address) in contract_addresses {
+                let contract_id = match contract_address
+                    .map(|address| QualifiedContractIdentifier::parse(address))
+                {
+                    Some(Ok(contract_id)) => contract_id,
+                    // Invalid addresses are reported once the config gets built
+                    _ => continue,
+                };
+                let mainnet = matches!(
+                    contract_id.issuer.0,
+                    C32_ADDRESS_VERSION_MAINNET_SINGLESIG | C32_ADDRESS_VERSION_MAINNET_MULTISIG
+                );
+                if mainnet != stacks_network.is_mainnet() {
+                    problems.push(format!(
+                        "{} '{}' is not an address of stacks.network '{}'",
+                        field, contract_id, config_file.stacks.network
+                    ));
+                }
+            }
+        }
+    }
+
+    if config_file.pyth.price_feeds_ids.is_empty() {
+        problems.push("pyth.price_feeds_ids must list at least 1 price feed".into());
+    }
+    let mut price_feeds_ids = HashSet::new();
+    for price_feed_id in config_file.pyth.price_feeds_ids.iter() {
+        let normalized_id = normalize_price_feed_id(price_feed_id);
+        if !matches!(hex::decode(&normalized_id), Ok(bytes) if bytes.len() == 32) {
+            problems.push(format!(
+                "pyth.price_feeds_ids: '{}' is not a 32 bytes hex price feed id",
+                price_feed_id
+            ));
+        } else if !price_feeds_ids.insert(normalized_id) {
+            problems.push(format!(
+                "pyth.price_feeds_ids: '{}' listed twice",
+                price_feed_id
+            ));
+        }
+    }
+
+    // The update clock ticks every 60 / price_updates_per_minute seconds
+    if !(1..=60).contains(&config_file.bridge.price_updates_per_minute) {
+        problems.push(format!(
+            "bridge.price_updates_per_minute {} must be between 1 and 60",
+            config_file.bridge.price_updates_per_minute
+        ));
+    }
+
+    let signers: Vec<(String, &SignerConfigFile)> = match &config_file.stacks.accounts {
+        None => vec![("stacks".into(), &config_file.stacks.signer)],
+        Some(accounts) => accounts
+            .iter()
+            .enumerate()
+            .map(|(index, account)| (format!("stacks.accounts[{}]", index), account))
+            .collect(),
+    };
+    for (section, signer) in signers {
+        if let Some(derivation_path) = &signer.derivation_path {
+            if !is_derivation_path(derivation_path) {
+                problems.push(format!(
+                    "{}.derivation_path '{}' invalid, expected a path such as m/44'/5757'/0'/0/0",
+                    section, derivation_path
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Whether a BIP32 derivation path parses: `m`, then indices below 2^31, hardened by a `'`.
+fn is_derivation_path(derivation_path: &str) -> bool {
+    let mut segments = derivation_path.split('/');
+    if segments.next() != Some("m") {
+        return false;
+    }
+    segments.all(|segment| {
+        let index = segment.strip_suffix('\'').unwrap_or(segment);
+        matches!(index.parse::<u32>(), Ok(index) if index < 1 << 31)
+    })
+}
+
+/// Check a config file as written, without overrides, reporting every problem found.
+/// Problems detected while building the config are reported once the other checks pass.
+pub fn validate_config_file(file_path: &str) -> Result<Vec<ConfigProblem>, String> {
+    let file_buffer = read_file(file_path)?;
+    let source = String::from_utf8_lossy(&file_buffer);
+
+    let config: toml::Value = match toml::from_str(&source) {
+        Ok(config) => config,
+        Err(e) => {
+            let message = e.to_string();
+            // The position is reported on its own
+            let message = match message.rsplit_once(" at line ") {
+                Some((message, _)) => message.to_string(),
+                None => message,
+            };
+            return Ok(vec![ConfigProblem {
+                line: e.line_col().map(|(line, _)| line + 1),
+                message,
+            }]);
+        }
+    };
+    let config_file: ConfigFile = match config.try_into() {
+        Ok(config_file) => config_file,
+        Err(e) => {
+            let message = e.to_string();
+            return Ok(vec![ConfigProblem {
+                line: locate_problem(&source, &message),
+                message,
+            }]);
+        }
+    };
+
+    let mut problems = check_config_file(&config_file);
+    match EventObserverConfig::new_using_overrides(config_file.event_observer.as_ref()) {
+        Ok(event_observer) => {
+            let port = event_observer.ingestion_port;
+            if let Err(e) = TcpListener::bind(("0.0.0.0", port)) {
+                problems.push(format!(
+                    "event_observer.ingestion_port {} unavailable: {}",
+                    port, e
+                ));
+            }
+        }
+        Err(e) => problems.push(format!("event_observer: {}", e)),
+    }
+    if problems.is_empty() {
+        if let Err(e) = Config::from_config_file(config_file) {
+            problems.push(e);
+        }
+    }
+
+    let mut problems = problems
+        .into_iter()
+        .map(|message| ConfigProblem {
+            line: locate_problem(&source, &message),
+            message,
+        })
+        .collect::<Vec<_>>();
+    problems.sort_by_key(|problem| problem.line);
+    Ok(problems)
+}
+
+/// Line of the field a problem starts with, or names between backticks, or of the
+/// value it quotes when the field is a list.
+fn locate_problem(source: &str, message: &str) -> Option<usize> {
+    let field_path = message
+        .split(|c: char| c.is_whitespace() || c == ':' || c == ',' || c == '`')
+        .find(|word| {
+            SECTIONS
+                .iter()
+                .any(|section| word.starts_with(&format!("{}.", section)))
+        })?;
+    // `stacks.accounts[1].derivation_path` is the field of the 2nd [[stacks.accounts]]
+    let mut sections = vec![];
+    let mut index = 0;
+    for key in field_path.split('.') {
+        match key.split_once('[') {
+            Some((key, position)) => {
+                sections.push(key);
+                index = position.trim_end_matches(']').parse().ok()?;
+            }
+            None => sections.push(key),
+        }
+    }
+    let field = sections.pop()?;
+    let section = sections.join(".");
+
+    let quoted_value = message.split('\'').nth(1);
+    let mut header_line = None;
+    let mut occurrences = 0;
+    let mut in_section = false;
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            let header = line.trim_start_matches('[');
+            let header = header[..header.find(']').unwrap_or(header.len())].trim();
+            in_section = false;
+            if header == section {
+                if occurrences == index {
+                    header_line = Some(number + 1);
+                    in_section = true;
+                }
+                occurrences += 1;
+            } else if header
+                .strip_prefix(&format!("{}.{}", section, field))
+                .map_or(false, |rest| rest.is_empty() || rest.starts_with('.'))
+            {
+                // Field written as a table of its own, such as pyth.price_feeds_policies
+                return Some(number + 1);
+            }
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let is_field = line
+            .strip_prefix(field)
+            .map_or(false, |rest| rest.trim_start().starts_with('='));
+        if !is_field {
+            continue;
+        }
+        // Point at the value at fault, its last occurrence for duplicates, within a
+        // list spanning several lines
+        let value_line = quoted_value.and_then(|value| {
+            source
+                .lines()
+                .enumerate()
+                .skip(number)
+                .take_while(|(_, line)| !line.trim().starts_with('['))
+                .filter(|(_, line)| line.contains(value))
+                .last()
+        });
+        return Some(value_line.map_or(number, |(number, _)| number) + 1);
+    }
+    header_line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::generator::generate_config;
+    use crate::config::{BinaryEncoding, OracleEntrypoint, PriceServiceApi};
+
+    const SOURCE: &str = r#"[pyth]
+network = "mainnet"
+price_feeds_ids = [
+    "0xe62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43",
+    "0xec7a775f46379b5e943c3526b1c8d54cd49749176b0b98e02dde68d1bd335c17",
+    "0xe62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43",
+]
+
+[pyth.price_feeds_policies."0xff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace"]
+deviation_threshold_bps = 25
+
+[stacks]
+network = "mainnet"
+start_block = 0
+
+[[stacks.accounts]]
+derivation_path = "m/44'/5757'/0'/0/0"
+
+[[stacks.accounts]]
+mnemonic_env = "PYTH_RELAYER_MNEMONIC"
+derivation_path = "m/44'/5757'/0'/0/x"
+
+[bridge]
+price_updates_per_minute = 120
+"#;
+
+    fn config_file(network: &StacksNetwork, replacements: &[(&str, &str)]) -> ConfigFile {
+        let mut source = generate_config(network);
+        // First occurrences, [pyth] coming first
+        for (from, to) in replacements {
+            assert!(source.contains(from), "{}", from);
+            source = source.replacen(from, to, 1);
+        }
+        toml::from_str(&source).unwrap()
+    }
+
+    #[test]
+    fn locate_problems() {
+        for (message, line) in [
+            ("bridge.price_updates_per_minute 120 must be between 1 and 60", Some(24)),
+            ("stacks.network 'mainnet' doesn't match", Some(13)),
+            // The value quoted, its last occurrence for duplicates
+            (
+                "pyth.price_feeds_ids: '0xe62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43' listed twice",
+                Some(6),
+            ),
+            (
+                "pyth.price_feeds_ids: '0xec7a775f46379b5e943c3526b1c8d54cd49749176b0b98e02dde68d1bd335c17' invalid",
+                Some(5),
+            ),
+            // Within the 2nd [[stacks.accounts]]
+            (
+                "stacks.accounts[1].derivation_path 'm/44'/5757'/0'/0/x' invalid",
+                Some(21),
+            ),
+            ("stacks.accounts[0].derivation_path invalid", Some(17)),
+            // Missing fields point at their section
+            ("stacks.accounts[1].private_key missing", Some(19)),
+            ("stacks.stacks_node_rpc_url missing", Some(12)),
+            ("pyth.price_feeds_policies: price feed 0xff61 not listed", Some(9)),
+            // Fields named between backticks by the deserializer
+            (
+                "invalid type: string \"x\", expected u64 for key `stacks.start_block`",
+                Some(14),
+            ),
+            ("wormhole.core_contract_address invalid", None),
+            ("stacks.accounts[2].derivation_path invalid", None),
+            ("unable to reach node", None),
+        ] {
+            assert_eq!(locate_problem(SOURCE, message), line, "{}", message);
+        }
+    }
+
+    #[test]
+    fn check_generated_configs() {
+        for network in [
+            StacksNetwork::Devnet,
+            StacksNetwork::Testnet,
+            StacksNetwork::Mainnet,
+        ] {
+            let problems = check_config_file(&config_file(&network, &[]));
+            assert!(problems.is_empty(), "{:?}: {:?}", network, problems);
+        }
+    }
+
+    #[test]
+    fn check_networks() {
+        let config = config_file(
+            &StacksNetwork::Mainnet,
+            &[(r#"network = "mainnet""#, r#"network = "testnet""#)],
+        );
+        assert_eq!(
+            check_config_file(&config),
+            vec!["pyth.network 'testnet' doesn't match stacks.network 'mainnet'"]
+        );
+        for network in [StacksNetwork::Devnet, StacksNetwork::Testnet] {
+            let config = config_file(
+                &network,
+                &[(r#"network = "testnet""#, r#"network = "mainnet""#)],
+            );
+            let problems = check_config_file(&config);
+            assert_eq!(problems.len(), 1, "{:?}", problems);
+            assert!(problems[0].starts_with("pyth.network 'mainnet' doesn't match stacks.network"));
+        }
+    }
+
+    #[test]
+    fn check_contract_address_networks() {
+        let config = config_file(
+            &StacksNetwork::Mainnet,
+            &[
+                (
+                    "SP2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY2P2PKN0.pyth-oracle-v2",
+                    "ST2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY1JKX06E.pyth-oracle-v2",
+                ),
+                (
+                    "# pyth_governance_contract_address = \"\"",
+                    "pyth_governance_contract_address = \"SN2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY0QHDANK.pyth-governance-v1\"",
+                ),
+            ],
+        );
+        assert_eq!(
+            check_config_file(&config),
+            vec![
+                "stacks.pyth_oracle_contract_address 'ST2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY1JKX06E.pyth-oracle-v2' is not an address of stacks.network 'mainnet'",
+                "stacks.pyth_governance_contract_address 'SN2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY0QHDANK.pyth-governance-v1' is not an address of stacks.network 'mainnet'",
+            ]
+        );
+        let config = config_file(
+            &StacksNetwork::Testnet,
+            &[(
+                "ST2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY1JKX06E.pyth-oracle-v2",
+                "SM2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY1HJ80FS.pyth-oracle-v2",
+            )],
+        );
+        assert_eq!(check_config_file(&config).len(), 1);
+    }
+
+    #[test]
+    fn pair_oracle_entrypoint_and_price_service_api() {
+        const API: &str = r#"price_service_api = "hermes-v2""#;
+        const ENTRYPOINT: &str = r#"# pyth_oracle_entrypoint = "verify-and-update-price-feeds""#;
+        let load = |price_service_api: Option<&str>, pyth_oracle_entrypoint: Option<&str>| {
+            let api = match price_service_api {
+                Some(api) => format!("price_service_api = \"{}\"", api),
+                None => format!("# {}", API),
+            };
+            let entrypoint = match pyth_oracle_entrypoint {
+                Some(entrypoint) => format!("pyth_oracle_entrypoint = \"{}\"", entrypoint),
+                None => ENTRYPOINT.to_string(),
+            };
+            Config::from_config_file(config_file(
+                &StacksNetwork::Testnet,
+                &[(API, &api), (ENTRYPOINT, &entrypoint)],
+            ))
+        };
+
+        for (price_service_api, pyth_oracle_entrypoint) in [
+            ("hermes-v2", "update-prices-feeds"),
+            ("hermes-v2-stream", "update-prices-feeds"),
+            ("legacy", "verify-and-update-price-feeds"),
+        ] {
+            assert_eq!(
+                load(Some(price_service_api), Some(pyth_oracle_entrypoint)).unwrap_err(),
+                format!(
+                    "stacks.pyth_oracle_entrypoint '{}' can't submit the updates of pyth.price_service_api '{}'",
+                    pyth_oracle_entrypoint, price_service_api
+                )
+            );
+        }
+
+        // Each defaults to the one matching the other
+        for (price_service_api, pyth_oracle_entrypoint, expected_api, expected_entrypoint) in [
+            (
+                None,
+                None,
+                PriceServiceApi::HermesV2(BinaryEncoding::Hex),
+                OracleEntrypoint::VerifyAndUpdatePriceFeeds,
+            ),
+            (
+                Some("legacy"),
+                None,
+                PriceServiceApi::LegacyLatestVaas,
+                OracleEntrypoint::LegacyUpdatePricesFeeds,
+            ),
+            (
+                None,
+                Some("update-prices-feeds"),
+                PriceServiceApi::LegacyLatestVaas,
+                OracleEntrypoint::LegacyUpdatePricesFeeds,
+            ),
+            (
+                Some("hermes-v2-stream"),
+                None,
+                PriceServiceApi::HermesV2Stream(BinaryEncoding::Hex),
+                OracleEntrypoint::VerifyAndUpdatePriceFeeds,
+            ),
+        ] {
+            let config = load(price_service_api, pyth_oracle_entrypoint).unwrap();
+            assert_eq!(config.pyth.price_service_api, expected_api);
+            assert_eq!(config.stacks.pyth_oracle_entrypoint, expected_entrypoint);
+        }
+    }
+
+    #[test]
+    fn report_every_problem() {
+        let config = config_file(
+            &StacksNetwork::Mainnet,
+            &[
+                (
+                    "price_updates_per_minute = 5",
+                    "price_updates_per_minute = 0",
+                ),
+                (r#"network = "mainnet""#, r#"network = "testnet""#),
+            ],
+        );
+        assert_eq!(
+            Config::from_config_file(config).unwrap_err(),
+            "pyth.network 'testnet' doesn't match stacks.network 'mainnet'\nbridge.price_updates_per_minute 0 must be between 1 and 60"
+        );
+    }
+}
@@ -8,8 +8,12 @@ extern crate serde;
 
 pub mod cli;
 pub mod config;
+pub mod pyth;
 pub mod service;
+pub mod signer;
+pub mod stacks;
 pub mod utils;
+pub mod wormhole;
 
 fn main() {
     cli::main();
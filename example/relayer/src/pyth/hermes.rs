@@ -0,0 +1,280 @@
+//! Client for the Hermes v2 price service API.
+
+use std::collections::{BTreeMap, HashMap};
+
+use base64::{engine::general_purpose, Engine as _};
+use chainhook_sdk::utils::Context;
+
+use crate::config::BinaryEncoding;
+
+use super::normalize_price_feed_id;
+use super::pnau::{Pnau, PriceFeedMessage};
+
+#[derive(Deserialize, Debug)]
+struct LatestPriceUpdatesResponse {
+    binary: BinaryPriceUpdates,
+    parsed: Option<Vec<ParsedPriceUpdate>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BinaryPriceUpdates {
+    encoding: String,
+    data: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ParsedPriceUpdate {
+    id: String,
+    price: ParsedPrice,
+}
+
+#[derive(Deserialize, Debug)]
+struct ParsedPrice {
+    price: String,
+    conf: String,
+    expo: i32,
+    publish_time: u64,
+}
+
+impl ParsedPrice {
+    fn matches(&self, message: &PriceFeedMessage) -> bool {
+        self.price == message.price.to_string()
+            && self.conf == message.conf.to_string()
+            && self.expo == message.expo
+            && self.publish_time == message.publish_time
+    }
+}
+
+pub fn decode_binary(encoding: &BinaryEncoding, data: &str) -> Result<Vec<u8>, String> {
+    match encoding {
+        BinaryEncoding::Hex => {
+            hex::decode(data.trim_start_matches("0x")).map_err(|e| e.to_string())
+        }
+        BinaryEncoding::Base64 => general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Price update of a single price feed, wrapped in its own PNAU.
+#[derive(Clone, Debug)]
+pub struct PriceFeedUpdate {
+    pub publish_time: u64,
+    pub price_update: Vec<u8>,
+}
+
+pub fn price_updates_query(price_feeds_ids: &[String], encoding: &BinaryEncoding) -> String {
+    let mut query = price_feeds_ids
+        .iter()
+        .map(|price_feed_id| format!("ids[]={}", price_feed_id))
+        .collect::<Vec<_>>();
+    query.push(format!("encoding={}", encoding.as_str()));
+    query.push("parsed=true".into());
+    query.join("&")
+}
+
+/// Decode a price updates response, shared by `/v2/updates/price/latest` and the
+/// events of `/v2/updates/price/stream`. Hermes bundles the price messages in PNAU
+/// blobs; each message is extracted in its own PNAU, keyed by normalized price feed id.
+pub fn decode_price_updates(
+    body: &[u8],
+    encoding: &BinaryEncoding,
+    ctx: &Context,
+) -> Result<BTreeMap<String, PriceFeedUpdate>, String> {
+    let response = serde_json::from_slice::<LatestPriceUpdatesResponse>(body)
+        .map_err(|e| format!("unable to decode price updates: {}", e))?;
+    if response.binary.encoding != encoding.as_str() {
+        return Err(format!(
+            "price updates encoded in {}, expected {}",
+            response.binary.encoding,
+            encoding.as_str()
+        ));
+    }
+
+    let parsed_prices = response
+        .parsed
+        .unwrap_or_default()
+        .into_iter()
+        .map(|parsed| (normalize_price_feed_id(&parsed.id), parsed.price))
+        .collect::<HashMap<_, _>>();
+
+    let mut price_updates = BTreeMap::new();
+    for data in response.binary.data.iter() {
+        let bytes = decode_binary(encoding, data)
+            .map_err(|e| format!("unable to decode price update: {}", e))?;
+        let pnau = Pnau::parse(&bytes).map_err(|e| format!("unable to decode PNAU: {}", e))?;
+        for price_feed_pnau in pnau.split() {
+            let message = &price_feed_pnau.updates[0].message;
+            let price_feed_id = hex::encode(message.price_identifier);
+            match parsed_prices.get(&price_feed_id) {
+                Some(parsed_price) if !parsed_price.matches(message) => warn!(
+                    ctx.expect_logger(),
+                    "0x{}: parsed price {} does not match binary price update {}",
+                    price_feed_id,
+                    parsed_price.price,
+                    message.price
+                ),
+                Some(parsed_price) => debug!(
+                    ctx.expect_logger(),
+                    "0x{}: price {} ± {} (expo: {}) published at {}",
+                    price_feed_id,
+                    parsed_price.price,
+                    parsed_price.conf,
+                    parsed_price.expo,
+                    parsed_price.publish_time
+                ),
+                None => {}
+            }
+            let price_feed_update = PriceFeedUpdate {
+                publish_time: message.publish_time,
+                price_update: price_feed_pnau.serialize()?,
+            };
+            price_updates.insert(price_feed_id, price_feed_update);
+        }
+    }
+    Ok(price_updates)
+}
+
+/// Retrieve the latest price updates through `/v2/updates/price/latest`, keyed by
+/// the price feed id as written in `price_feeds_ids`.
+pub async fn fetch_latest_price_updates(
+    price_service_url: &str,
+    price_feeds_ids: &[String],
+    encoding: &BinaryEncoding,
+    ctx: &Context,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let response = reqwest::get(format!(
+        "{}/v2/updates/price/latest?{}",
+        price_service_url,
+        price_updates_query(price_feeds_ids, encoding)
+    ))
+    .await
+    .map_err(|e| format!("unable to fetch price updates: {}", e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "unable to fetch price updates: {} {}",
+            status, message
+        ));
+    }
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| format!("unable to fetch price updates: {}", e))?;
+    let mut price_updates = decode_price_updates(&body, encoding, ctx)?;
+
+    let mut keyed_price_updates = vec![];
+    for price_feed_id in price_feeds_ids.iter() {
+        match price_updates.remove(&normalize_price_feed_id(price_feed_id)) {
+            Some(update) => keyed_price_updates.push((price_feed_id.clone(), update.price_update)),
+            None => warn!(
+                ctx.expect_logger(),
+                "No price update returned for price feed {}", price_feed_id
+            ),
+        }
+    }
+    Ok(keyed_price_updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pyth::pnau::tests::PNAU_STX_USD;
+
+    const STX_USD: &str = "ec7a775f46379b5e943c3526b1c8d54cd49749176b0b98e02dde68d1bd335c17";
+
+    /// `/v2/updates/price/latest?ids[]=<STX_USD>&encoding=<encoding>&parsed=true`
+    /// response, carrying `data`.
+    fn latest_price_updates_body(encoding: &str, data: &str) -> Vec<u8> {
+        serde_json::json!({
+            "binary": {
+                "encoding": encoding,
+                "data": [data]
+            },
+            "parsed": [{
+                "id": STX_USD,
+                "price": {
+                    "price": "46098556",
+                    "conf": "37359",
+                    "expo": -8,
+                    "publish_time": 1695751649
+                },
+                "ema_price": {
+                    "price": "46167004",
+                    "conf": "36191",
+                    "expo": -8,
+                    "publish_time": 1695751649
+                },
+                "metadata": {
+                    "slot": 98677868,
+                    "proof_available_time": 1695751650,
+                    "prev_publish_time": 1695751648
+                }
+            }]
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn ctx() -> Context {
+        Context {
+            logger: Some(hiro_system_kit::log::setup_logger()),
+            tracer: false,
+        }
+    }
+
+    #[test]
+    fn decode_hex_and_base64() {
+        let pnau = hex::decode(PNAU_STX_USD).unwrap();
+        for (encoding, data) in [
+            (BinaryEncoding::Hex, format!("0x{}", PNAU_STX_USD)),
+            (BinaryEncoding::Hex, PNAU_STX_USD.to_string()),
+            (
+                BinaryEncoding::Base64,
+                general_purpose::STANDARD.encode(&pnau),
+            ),
+        ] {
+            let body = latest_price_updates_body(encoding.as_str(), &data);
+            let price_updates = decode_price_updates(&body, &encoding, &ctx()).unwrap();
+            assert_eq!(price_updates.len(), 1);
+            let price_update = &price_updates[STX_USD];
+            assert_eq!(price_update.publish_time, 1695751649);
+            assert_eq!(price_update.price_update, pnau);
+        }
+    }
+
+    #[test]
+    fn key_by_normalized_price_feed_id() {
+        // Two price messages in a single PNAU, the second one of another price feed
+        let mut pnau = Pnau::parse(&hex::decode(PNAU_STX_USD).unwrap()).unwrap();
+        let mut update = pnau.updates[0].clone();
+        update.message_bytes[1..33].copy_from_slice(&[0xAB; 32]);
+        update.message = PriceFeedMessage::parse(&update.message_bytes).unwrap();
+        pnau.updates.push(update);
+        let body = latest_price_updates_body("hex", &hex::encode(pnau.serialize().unwrap()));
+
+        let price_updates = decode_price_updates(&body, &BinaryEncoding::Hex, &ctx()).unwrap();
+        assert_eq!(
+            price_updates.keys().collect::<Vec<_>>(),
+            vec![&"ab".repeat(32), &STX_USD.to_string()]
+        );
+        let price_feed_id = format!("0x{}", STX_USD.to_uppercase());
+        let price_update = &price_updates[&normalize_price_feed_id(&price_feed_id)];
+        let price_feed_pnau = Pnau::parse(&price_update.price_update).unwrap();
+        assert_eq!(price_feed_pnau.updates.len(), 1);
+        assert_eq!(
+            price_feed_pnau.updates[0].message_bytes,
+            pnau.updates[0].message_bytes
+        );
+    }
+
+    #[test]
+    fn encoding_mismatch() {
+        let body = latest_price_updates_body("hex", PNAU_STX_USD);
+        assert_eq!(
+            decode_price_updates(&body, &BinaryEncoding::Base64, &ctx()).unwrap_err(),
+            "price updates encoded in hex, expected base64"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Keccak-160 merkle tree used by the Pyth accumulator, as verified by
+//! `hk-merkle-tree-keccak160-v1`.
+
+use sha3::{Digest, Keccak256};
+
+const MERKLE_LEAF_PREFIX: u8 = 0;
+const MERKLE_NODE_PREFIX: u8 = 1;
+
+fn keccak160(chunks: &[&[u8]]) -> [u8; 20] {
+    let mut hasher = Keccak256::new();
+    for chunk in chunks {
+        hasher.update(chunk);
+    }
+    let mut hash = [0u8; 20];
+    hash.copy_from_slice(&hasher.finalize()[..20]);
+    hash
+}
+
+pub fn hash_leaf(leaf: &[u8]) -> [u8; 20] {
+    keccak160(&[&[MERKLE_LEAF_PREFIX], leaf])
+}
+
+/// Nodes are sorted before being hashed, so proofs don't carry the side of each sibling.
+pub fn hash_nodes(node_1: &[u8; 20], node_2: &[u8; 20]) -> [u8; 20] {
+    let (left, right) = if node_2 < node_1 {
+        (node_2, node_1)
+    } else {
+        (node_1, node_2)
+    };
+    keccak160(&[&[MERKLE_NODE_PREFIX], left, right])
+}
+
+/// Walk the proof from the leaf and check that it leads to the root.
+pub fn check_proof(root: &[u8; 20], leaf: &[u8], proof: &[[u8; 20]]) -> bool {
+    let computed_root = proof
+        .iter()
+        .fold(hash_leaf(leaf), |node, sibling| hash_nodes(&node, sibling));
+    &computed_root == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// STX-USD price message of the Hermes update at slot 98677868, with its proof
+    /// and the root committed by the VAA.
+    const ROOT: &str = "dbdd79b598d499596393b24358343774fd54e63c";
+    const LEAF: &str = "00ec7a775f46379b5e943c3526b1c8d54cd49749176b0b98e02dde68d1bd335c170000000002bf687c00000000000091effffffff80000000065131de10000000065131de00000000002c073dc0000000000008d5f";
+    const PROOF: [&str; 9] = [
+        "014cff5b7c5413efc667d2bca5549edadde53248",
+        "14fed82e330d4110d610f5d0325deddb5541eacc",
+        "cd70da0a613768335768226657b201a456613163",
+        "b0b4a21cf17276c2be6d02280ff05d08a6d4cf48",
+        "1c00c544f4adff1c86c6845a21871b75b4cffdc9",
+        "314a2b46bddcb2bd44861fa48d4cc429a0783cb1",
+        "22a3eda67b7a13ad6298995c3bddd6cfa1ba547d",
+        "dd38f0da18fcb0f7ead4d1de56db6c0442b94883",
+        "a78f40b3541982a58d2d400dbfb761cce7bf780c",
+    ];
+
+    fn hash(hex_hash: &str) -> [u8; 20] {
+        hex::decode(hex_hash).unwrap().try_into().unwrap()
+    }
+
+    fn proof() -> Vec<[u8; 20]> {
+        PROOF.iter().map(|node| hash(node)).collect()
+    }
+
+    #[test]
+    fn check_recorded_proof() {
+        let leaf = hex::decode(LEAF).unwrap();
+        assert!(check_proof(&hash(ROOT), &leaf, &proof()));
+    }
+
+    #[test]
+    fn reject_tampered_proof() {
+        let root = hash(ROOT);
+        let mut leaf = hex::decode(LEAF).unwrap();
+        leaf[40] ^= 1;
+        assert!(!check_proof(&root, &leaf, &proof()));
+
+        let leaf = hex::decode(LEAF).unwrap();
+        let mut tampered_proof = proof();
+        tampered_proof[4][0] ^= 1;
+        assert!(!check_proof(&root, &leaf, &tampered_proof));
+        assert!(!check_proof(&root, &leaf, &proof()[..8]));
+    }
+
+    #[test]
+    fn nodes_are_sorted() {
+        let leaf_1 = hash_leaf(b"leaf 1");
+        let leaf_2 = hash_leaf(b"leaf 2");
+        let root = hash_nodes(&leaf_1, &leaf_2);
+        assert_eq!(root, hash_nodes(&leaf_2, &leaf_1));
+        assert!(check_proof(&root, b"leaf 1", &[leaf_2]));
+        assert!(check_proof(&root, b"leaf 2", &[leaf_1]));
+        // The leaf prefix keeps a leaf from passing for a node
+        let (left, right) = (leaf_1.min(leaf_2), leaf_1.max(leaf_2));
+        let node = [&[MERKLE_NODE_PREFIX][..], &left, &right].concat();
+        assert!(!check_proof(&root, &node, &[]));
+    }
+}
@@ -0,0 +1,137 @@
+pub mod hermes;
+pub mod merkle;
+pub mod pnau;
+pub mod policy;
+pub mod stream;
+
+use crate::wormhole::vaa::Vaa;
+
+use self::pnau::{Pnau, MAX_UPDATES};
+
+/// Price feed id without `0x` prefix, in lowercase, as encoded by `hex::encode`.
+pub fn normalize_price_feed_id(price_feed_id: &str) -> String {
+    price_feed_id.trim_start_matches("0x").to_lowercase()
+}
+
+/// Extract the Wormhole VAA authenticating a price update, either the VAA
+/// embedded in an accumulator update (PNAU) or a legacy price attestation VAA.
+pub fn extract_vaa(price_update: &[u8]) -> Result<Vaa, String> {
+    if Pnau::is_pnau(price_update) {
+        Pnau::parse(price_update)
+            .map(|pnau| pnau.vaa)
+            .map_err(|e| e.to_string())
+    } else {
+        Vaa::parse(price_update).map_err(|e| e.to_string())
+    }
+}
+
+const P2WH_MAGIC: [u8; 4] = *b"P2WH";
+
+/// Offset of the publish time in a price attestation, after the product and price
+/// ids, price, confidence, exponent, EMA price and confidence, status, two obsolete
+/// fields and the attestation time.
+const P2WH_PUBLISH_TIME_OFFSET: usize = 117;
+
+/// Price id and publish time of the attestations of a legacy batch price attestation VAA.
+fn p2wh_attestations(price_update: &[u8]) -> Option<Vec<([u8; 32], u64)>> {
+    // P2WH payload: magic, major and minor versions, header size, header, the number
+    // of attestations and their size, then the attestations, starting with the
+    // product id and the price id
+    let payload = Vaa::parse(price_update).ok()?.body.payload;
+    if !payload.starts_with(&P2WH_MAGIC) {
+        return None;
+    }
+    let header_size = u16::from_be_bytes(payload.get(8..10)?.try_into().ok()?) as usize;
+    let offset = 10 + header_size;
+    let count = u16::from_be_bytes(payload.get(offset..offset + 2)?.try_into().ok()?) as usize;
+    let size = u16::from_be_bytes(payload.get(offset + 2..offset + 4)?.try_into().ok()?) as usize;
+    (0..count)
+        .map(|i| {
+            let start = offset + 4 + i * size;
+            let price_id = payload.get(start + 32..start + 64)?.try_into().ok()?;
+            let publish_time = start + P2WH_PUBLISH_TIME_OFFSET;
+            let publish_time = u64::from_be_bytes(
+                payload
+                    .get(publish_time..publish_time + 8)?
+                    .try_into()
+                    .ok()?,
+            );
+            Some((price_id, publish_time))
+        })
+        .collect()
+}
+
+/// Price ids of the attestations of a legacy batch price attestation VAA.
+fn p2wh_price_ids(price_update: &[u8]) -> Option<Vec<[u8; 32]>> {
+    p2wh_attestations(price_update).map(|attestations| {
+        attestations
+            .into_iter()
+            .map(|(price_id, _)| price_id)
+            .collect()
+    })
+}
+
+/// Number of price entries a price update writes to `pyth-store-v1`: the price
+/// messages of an accumulator update, or the attestations of a legacy batch
+/// price attestation VAA.
+pub fn price_entries_count(price_update: &[u8]) -> usize {
+    if let Ok(pnau) = Pnau::parse(price_update) {
+        return pnau.updates.len();
+    }
+    p2wh_price_ids(price_update)
+        .map(|price_ids| price_ids.len())
+        .unwrap_or(1)
+}
+
+/// Normalized ids of the price feeds carried by a price update.
+pub fn price_feeds_ids(price_update: &[u8]) -> Vec<String> {
+    let price_ids = match Pnau::parse(price_update) {
+        Ok(pnau) => pnau
+            .updates
+            .iter()
+            .map(|update| update.message.price_identifier)
+            .collect(),
+        Err(_) => p2wh_price_ids(price_update).unwrap_or_default(),
+    };
+    price_ids.iter().map(hex::encode).collect()
+}
+
+/// Price id and publish time of each price entry a price update writes to `pyth-store-v1`.
+pub fn price_entries_publish_times(price_update: &[u8]) -> Vec<([u8; 32], u64)> {
+    match Pnau::parse(price_update) {
+        Ok(pnau) => pnau
+            .updates
+            .iter()
+            .map(|update| (update.message.price_identifier, update.message.publish_time))
+            .collect(),
+        Err(_) => p2wh_attestations(price_update).unwrap_or_default(),
+    }
+}
+
+/// Merge the accumulator updates carrying the same VAA, so that the VAA is only
+/// verified once on chain, up to the price messages a PNAU can carry. Other price
+/// updates are left untouched.
+pub fn coalesce_price_updates(price_updates: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, String> {
+    let mut coalesced: Vec<Pnau> = vec![];
+    let mut others = vec![];
+    for price_update in price_updates {
+        let pnau = match Pnau::parse(&price_update) {
+            Ok(pnau) => pnau,
+            Err(_) => {
+                others.push(price_update);
+                continue;
+            }
+        };
+        match coalesced.iter_mut().find(|existing| {
+            existing.vaa_bytes == pnau.vaa_bytes
+                && existing.updates.len() + pnau.updates.len() <= MAX_UPDATES
+        }) {
+            Some(existing) => existing.merge(pnau),
+            None => coalesced.push(pnau),
+        }
+    }
+    for pnau in coalesced.iter() {
+        others.push(pnau.serialize()?);
+    }
+    Ok(others)
+}
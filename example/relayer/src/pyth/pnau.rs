@@ -0,0 +1,539 @@
+use std::fmt;
+use std::io::{Cursor, Read};
+
+use crate::utils::read_bytes;
+use crate::wormhole::vaa::{Vaa, VaaError};
+
+use super::merkle;
+
+pub const PNAU_MAGIC: [u8; 4] = *b"PNAU";
+pub const AUWV_MAGIC: [u8; 4] = *b"AUWV";
+pub const PYTHNET_MAJOR_VERSION: u8 = 1;
+pub const PYTHNET_MINOR_VERSION: u8 = 0;
+pub const WORMHOLE_MERKLE_PROOF_TYPE: u8 = 0;
+pub const PRICE_FEED_MESSAGE_TYPE: u8 = 0;
+/// Price messages of a PNAU, counted on a single byte.
+pub const MAX_UPDATES: usize = u8::MAX as usize;
+
+/// Errors raised while decoding a PNAU blob. Codes follow the constants of
+/// `pyth-pnau-decoder-v1`; failures on the embedded VAA keep the code that
+/// `wormhole-core-v2` would return.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PnauError {
+    MagicBytes,
+    VersionMajor,
+    VersionMinor,
+    HeaderTrailingSize,
+    ProofType,
+    UpdateType(u8),
+    InvalidAuwv,
+    MerkleRootMismatch,
+    Vaa(VaaError),
+    /// The decoder aborts with a runtime error on truncated updates.
+    Truncated,
+}
+
+impl PnauError {
+    /// Error code that `pyth-pnau-decoder-v1` would return for the same failure.
+    pub fn code(&self) -> u64 {
+        match self {
+            PnauError::MagicBytes => 2001,
+            PnauError::VersionMajor => 2002,
+            PnauError::VersionMinor => 2003,
+            PnauError::HeaderTrailingSize => 2004,
+            PnauError::ProofType => 2005,
+            PnauError::UpdateType(_) => 2006,
+            PnauError::InvalidAuwv => 2007,
+            PnauError::MerkleRootMismatch => 2008,
+            PnauError::Vaa(e) => e.code(),
+            PnauError::Truncated => 0,
+        }
+    }
+}
+
+impl fmt::Display for PnauError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PnauError::MagicBytes => write!(f, "invalid magic bytes (u{})", self.code()),
+            PnauError::VersionMajor => write!(f, "unsupported major version (u{})", self.code()),
+            PnauError::VersionMinor => write!(f, "unsupported minor version (u{})", self.code()),
+            PnauError::HeaderTrailingSize => {
+                write!(f, "unable to parse trailing header (u{})", self.code())
+            }
+            PnauError::ProofType => write!(f, "unsupported proof type (u{})", self.code()),
+            PnauError::UpdateType(message_type) => write!(
+                f,
+                "unsupported message type {} (u{})",
+                message_type,
+                self.code()
+            ),
+            PnauError::InvalidAuwv => write!(f, "unable to parse merkle root (u{})", self.code()),
+            PnauError::MerkleRootMismatch => {
+                write!(
+                    f,
+                    "merkle proof does not lead to the root (u{})",
+                    self.code()
+                )
+            }
+            PnauError::Vaa(e) => write!(f, "embedded VAA: {}", e),
+            PnauError::Truncated => write!(f, "unexpected end of price updates (u{})", self.code()),
+        }
+    }
+}
+
+impl From<VaaError> for PnauError {
+    fn from(e: VaaError) -> Self {
+        PnauError::Vaa(e)
+    }
+}
+
+/// Root of the merkle tree committed by the Wormhole Merkle VAA ('AUWV' payload).
+#[derive(Clone, Debug)]
+pub struct MerkleRoot {
+    pub slot: u64,
+    pub ring_size: u32,
+    pub hash: [u8; 20],
+}
+
+impl MerkleRoot {
+    pub fn parse(payload: &[u8]) -> Result<MerkleRoot, PnauError> {
+        let mut cursor = Cursor::new(payload);
+
+        let magic = read_bytes::<4, _>(&mut cursor, PnauError::InvalidAuwv)?;
+        let [update_type] = read_bytes(&mut cursor, PnauError::InvalidAuwv)?;
+        let slot = u64::from_be_bytes(read_bytes(&mut cursor, PnauError::InvalidAuwv)?);
+        let ring_size = u32::from_be_bytes(read_bytes(&mut cursor, PnauError::InvalidAuwv)?);
+        let hash = read_bytes(&mut cursor, PnauError::InvalidAuwv)?;
+        if magic != AUWV_MAGIC {
+            return Err(PnauError::MagicBytes);
+        }
+        if update_type != WORMHOLE_MERKLE_PROOF_TYPE {
+            return Err(PnauError::ProofType);
+        }
+
+        Ok(MerkleRoot {
+            slot,
+            ring_size,
+            hash,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PriceFeedMessage {
+    pub price_identifier: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: u64,
+    pub prev_publish_time: u64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+impl PriceFeedMessage {
+    pub fn parse(bytes: &[u8]) -> Result<PriceFeedMessage, PnauError> {
+        let mut cursor = Cursor::new(bytes);
+
+        let [message_type] = read_bytes(&mut cursor, PnauError::Truncated)?;
+        if message_type != PRICE_FEED_MESSAGE_TYPE {
+            return Err(PnauError::UpdateType(message_type));
+        }
+        Ok(PriceFeedMessage {
+            price_identifier: read_bytes(&mut cursor, PnauError::Truncated)?,
+            price: i64::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?),
+            conf: u64::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?),
+            expo: i32::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?),
+            publish_time: u64::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?),
+            prev_publish_time: u64::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?),
+            ema_price: i64::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?),
+            ema_conf: u64::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PriceUpdate {
+    pub message: PriceFeedMessage,
+    /// Encoded message, leaf of the merkle tree.
+    pub message_bytes: Vec<u8>,
+    /// Sibling hashes from the leaf up to the merkle root.
+    pub proof: Vec<[u8; 20]>,
+}
+
+impl PriceUpdate {
+    /// Check the merkle proof of the message, as `pyth-pnau-decoder-v1` does.
+    pub fn verify(&self, merkle_root: &MerkleRoot) -> Result<(), PnauError> {
+        if merkle::check_proof(&merkle_root.hash, &self.message_bytes, &self.proof) {
+            Ok(())
+        } else {
+            Err(PnauError::MerkleRootMismatch)
+        }
+    }
+}
+
+/// Size of an encoded update: message size, message, proof size and proof.
+fn update_size(update: &PriceUpdate) -> usize {
+    2 + update.message_bytes.len() + 1 + 20 * update.proof.len()
+}
+
+/// Pyth Network Accumulator Update
+///
+/// [4]byte     magic               ('PNAU')
+/// u8          major_version
+/// u8          minor_version
+/// u8          trailing_header_size
+/// []byte      trailing_header
+/// u8          proof_type          (0: Wormhole Merkle)
+/// u16         vaa_size
+/// []byte      vaa                 (Wormhole VAA carrying the 'AUWV' merkle root)
+/// u8          num_updates
+/// []update    updates
+///
+/// Update
+/// u16         message_size
+/// []byte      message             (Price feed message, leaf of the merkle tree)
+/// u8          proof_size
+/// [][20]byte  proof
+#[derive(Clone, Debug)]
+pub struct Pnau {
+    pub trailing_header: Vec<u8>,
+    pub vaa_bytes: Vec<u8>,
+    pub vaa: Vaa,
+    pub merkle_root: MerkleRoot,
+    pub updates: Vec<PriceUpdate>,
+}
+
+impl Pnau {
+    pub fn is_pnau(bytes: &[u8]) -> bool {
+        bytes.starts_with(&PNAU_MAGIC)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Pnau, PnauError> {
+        let mut cursor = Cursor::new(bytes);
+
+        let magic = read_bytes::<4, _>(&mut cursor, PnauError::MagicBytes)?;
+        let [version_major] = read_bytes(&mut cursor, PnauError::VersionMajor)?;
+        let [version_minor] = read_bytes(&mut cursor, PnauError::VersionMinor)?;
+        let [trailing_header_size] = read_bytes(&mut cursor, PnauError::HeaderTrailingSize)?;
+        let mut trailing_header = vec![0u8; trailing_header_size as usize];
+        cursor
+            .read_exact(&mut trailing_header)
+            .map_err(|_| PnauError::ProofType)?;
+        let [proof_type] = read_bytes(&mut cursor, PnauError::ProofType)?;
+        if magic != PNAU_MAGIC {
+            return Err(PnauError::MagicBytes);
+        }
+        if version_major != PYTHNET_MAJOR_VERSION {
+            return Err(PnauError::VersionMajor);
+        }
+        if version_minor != PYTHNET_MINOR_VERSION {
+            return Err(PnauError::VersionMinor);
+        }
+        if proof_type != WORMHOLE_MERKLE_PROOF_TYPE {
+            return Err(PnauError::ProofType);
+        }
+
+        let vaa_size = u16::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?);
+        let mut vaa_bytes = vec![0u8; vaa_size as usize];
+        cursor
+            .read_exact(&mut vaa_bytes)
+            .map_err(|_| PnauError::Truncated)?;
+        let vaa = Vaa::parse(&vaa_bytes)?;
+        let merkle_root = MerkleRoot::parse(&vaa.body.payload)?;
+
+        let [num_updates] = read_bytes(&mut cursor, PnauError::Truncated)?;
+        let mut updates = Vec::with_capacity(num_updates as usize);
+        for _ in 0..num_updates {
+            let message_size = u16::from_be_bytes(read_bytes(&mut cursor, PnauError::Truncated)?);
+            let mut message_bytes = vec![0u8; message_size as usize];
+            cursor
+                .read_exact(&mut message_bytes)
+                .map_err(|_| PnauError::Truncated)?;
+            let message = PriceFeedMessage::parse(&message_bytes)?;
+            let [proof_size] = read_bytes(&mut cursor, PnauError::Truncated)?;
+            let proof = (0..proof_size)
+                .map(|_| read_bytes(&mut cursor, PnauError::Truncated))
+                .collect::<Result<Vec<_>, _>>()?;
+            updates.push(PriceUpdate {
+                message,
+                message_bytes,
+                proof,
+            });
+        }
+
+        Ok(Pnau {
+            trailing_header,
+            vaa_bytes,
+            vaa,
+            merkle_root,
+            updates,
+        })
+    }
+
+    /// Drop the updates whose merkle proof doesn't lead to the root carried by
+    /// the VAA, since a single one makes the decoder reject the whole PNAU.
+    /// The dropped updates are returned along with the error.
+    pub fn retain_proven_updates(&mut self) -> Vec<(PriceUpdate, PnauError)> {
+        let mut rejected = vec![];
+        let mut proven = vec![];
+        for update in self.updates.drain(..) {
+            match update.verify(&self.merkle_root) {
+                Ok(()) => proven.push(update),
+                Err(e) => rejected.push((update, e)),
+            }
+        }
+        self.updates = proven;
+        rejected
+    }
+
+    /// Split into one PNAU per price message, all carrying the same VAA.
+    pub fn split(&self) -> Vec<Pnau> {
+        self.updates
+            .iter()
+            .map(|update| Pnau {
+                updates: vec![update.clone()],
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// Move the price messages of `other`, carrying the same VAA, into this
+    /// PNAU, skipping duplicates.
+    pub fn merge(&mut self, other: Pnau) {
+        debug_assert!(other.vaa_bytes == self.vaa_bytes);
+        for update in other.updates {
+            if !self
+                .updates
+                .iter()
+                .any(|existing| existing.message_bytes == update.message_bytes)
+            {
+                self.updates.push(update);
+            }
+        }
+    }
+
+    /// Split into PNAUs of at most `max_size` bytes and `max_updates` price messages.
+    pub fn chunks(&self, max_size: usize, max_updates: usize) -> Result<Vec<Pnau>, String> {
+        let max_updates = max_updates.min(MAX_UPDATES);
+        let base_size = Pnau {
+            updates: vec![],
+            ..self.clone()
+        }
+        .serialize()?
+        .len();
+        let mut chunks = vec![];
+        let mut chunk = Pnau {
+            updates: vec![],
+            ..self.clone()
+        };
+        let mut chunk_size = base_size;
+        for update in self.updates.iter() {
+            let update_size = update_size(update);
+            if base_size + update_size > max_size {
+                return Err(format!(
+                    "price message 0x{} does not fit in {} bytes",
+                    hex::encode(update.message.price_identifier),
+                    max_size
+                ));
+            }
+            if chunk_size + update_size > max_size || chunk.updates.len() == max_updates {
+                let next_chunk = Pnau {
+                    updates: vec![],
+                    ..self.clone()
+                };
+                chunks.push(std::mem::replace(&mut chunk, next_chunk));
+                chunk_size = base_size;
+            }
+            chunk.updates.push(update.clone());
+            chunk_size += update_size;
+        }
+        if !chunk.updates.is_empty() {
+            chunks.push(chunk);
+        }
+        Ok(chunks)
+    }
+
+    /// Encode the PNAU, its number of price messages fitting a byte.
+    pub fn serialize(&self) -> Result<Vec<u8>, String> {
+        if self.updates.len() > MAX_UPDATES {
+            return Err(format!(
+                "{} price messages exceed the {} of a price update",
+                self.updates.len(),
+                MAX_UPDATES
+            ));
+        }
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&PNAU_MAGIC);
+        bytes.push(PYTHNET_MAJOR_VERSION);
+        bytes.push(PYTHNET_MINOR_VERSION);
+        bytes.push(self.trailing_header.len() as u8);
+        bytes.extend_from_slice(&self.trailing_header);
+        bytes.push(WORMHOLE_MERKLE_PROOF_TYPE);
+        bytes.extend_from_slice(&(self.vaa_bytes.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.vaa_bytes);
+        bytes.push(self.updates.len() as u8);
+        for update in self.updates.iter() {
+            bytes.extend_from_slice(&(update.message_bytes.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(&update.message_bytes);
+            bytes.push(update.proof.len() as u8);
+            for node in update.proof.iter() {
+                bytes.extend_from_slice(node);
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Hermes update of STX-USD at slot 98677868, signed by guardian set 3, as
+    /// recorded in `unit-tests/pyth/fixtures.ts`.
+    pub(crate) const PNAU_STX_USD: &str = "504e41550100000003b801000000030d002c9e33a703a8c86012c117474849ae41118270cf56e2db61c7af28f64ce322391d33cb738930dc3ffcfe5ae35dbd99c4e08a8ac4b390c858d94dc1f0b91e24d00101901948561c54c17b7a21a9885d3f60a85b410e18209b02ebe098b0dd422b02667565e3b5011203303a6445cf629d20cec3d4771f0b391ab03bfd54364ac049f70002a030ed0060f1ba123ddf5473bef4c1c8f4aec8737b47f11023f94d5339b72c4c3aa325515956ea702537a3950d74f86134396de437bfc97a5365fc090dbb382c0103f1f64ba806fe8ba7b7db3846d85fccbe7d803865e7b757301f4a5de6ebde40f923ee925a7df78871d39299d70fd117635263b91fe53f792bfff450d210431316000439e31501d7b661a1b075a9302b57a6fc500c1e760de7e949e30994055b061b280cefcab3eb3b5c780f56cd555e88ee3c674b798ddeb8713bd27a361b8b8f1c730008db908c61e4158d1a7a7d53b2ec14027a4a2b2f3207edf0db2fbb0f431f3cdc020ee9fc9f38e812d094dcf5dd18f3a8d1d1ee94e840f504b00e08d0fbd607ed6d0109cf5c52aed9f4a42691c3c33bc366fde4a3ad23aacdd46c14669ad321106cf8d257b9379fc45cbe91a26ee87f4800951c5ca89ff0069e4a47299f988b368c6b74000b1241dea623dd874e13135682d760ee48ac1888149bddcfac54ff3787f4b89629063d89e084350ff1168999365918f9bdf469323b817a25491e56341a0da5c498000d538baecc668df092ce3cfeebfa5968447584916cb503ce50e7f77443bbd2a3441bb1d962a7f3401bfcf30f444a3301ab72dcb87cc04b681bdada93d45001f53d010e2fd97eeac94ceb3fd87a6e4ab3b785c64db221d522e58230a6d9eb10cba3b53a086ba6989e4285ab0816a26ff64c1a3a05175d0ffa61f4f744f928d6c5b3f92600107f999fa2f5cd826520cdf36f2e3434f4619eca4c2fb944bdec79d84903f76aaa02727844ec451378ab165910140c866ebdc65aea2cc6717da4747d4ed8a7338700115ce8f97f4a39ccdf5a61c9425d8a306a35b598db9d0f936bece6f2d92e79f9b1306d4c4789fb73ba5bd29bf0d306f2e8079aae818bc70aa9db111b745f1f4ab900126933d7f1317e3e45d98687e66ae033d871e1026e92f1ad168ec197a92ad653571837b78ee8e2263f2aca1dfa112fc78de63de9e2231f62ab79db2d956704146c0165131de200000000001ae101faedac5851e32b9b23b5f9411a8c2bac4aae3ed4dd7b811dd1a72ea4aa710000000000df92280141555756000000000005e1b46c00002710dbdd79b598d499596393b24358343774fd54e63c01005500ec7a775f46379b5e943c3526b1c8d54cd49749176b0b98e02dde68d1bd335c170000000002bf687c00000000000091effffffff80000000065131de10000000065131de00000000002c073dc0000000000008d5f09014cff5b7c5413efc667d2bca5549edadde5324814fed82e330d4110d610f5d0325deddb5541eacccd70da0a613768335768226657b201a456613163b0b4a21cf17276c2be6d02280ff05d08a6d4cf481c00c544f4adff1c86c6845a21871b75b4cffdc9314a2b46bddcb2bd44861fa48d4cc429a0783cb122a3eda67b7a13ad6298995c3bddd6cfa1ba547ddd38f0da18fcb0f7ead4d1de56db6c0442b94883a78f40b3541982a58d2d400dbfb761cce7bf780c";
+
+    fn pnau_bytes() -> Vec<u8> {
+        hex::decode(PNAU_STX_USD).unwrap()
+    }
+
+    /// Copy of the update of `pnau`, with another price.
+    fn other_update(pnau: &Pnau) -> PriceUpdate {
+        let mut update = pnau.updates[0].clone();
+        update.message_bytes[33..41].copy_from_slice(&1i64.to_be_bytes());
+        update.message = PriceFeedMessage::parse(&update.message_bytes).unwrap();
+        update
+    }
+
+    #[test]
+    fn parse_hermes_update() {
+        let pnau = Pnau::parse(&pnau_bytes()).unwrap();
+        assert_eq!(pnau.vaa.header.guardian_set_index, 3);
+        assert_eq!(pnau.merkle_root.slot, 98677868);
+        assert_eq!(pnau.merkle_root.ring_size, 10000);
+        assert_eq!(pnau.updates.len(), 1);
+        let message = &pnau.updates[0].message;
+        assert_eq!(
+            hex::encode(message.price_identifier),
+            "ec7a775f46379b5e943c3526b1c8d54cd49749176b0b98e02dde68d1bd335c17"
+        );
+        assert_eq!(message.price, 46098556);
+        assert_eq!(message.expo, -8);
+        assert_eq!(message.publish_time, 1695751649);
+        assert_eq!(pnau.updates[0].verify(&pnau.merkle_root), Ok(()));
+        assert_eq!(pnau.serialize().unwrap(), pnau_bytes());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let bytes = pnau_bytes();
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert_eq!(Pnau::parse(&magic).unwrap_err(), PnauError::MagicBytes);
+        let mut major = bytes.clone();
+        major[4] = 2;
+        assert_eq!(Pnau::parse(&major).unwrap_err(), PnauError::VersionMajor);
+        let mut proof_type = bytes.clone();
+        proof_type[7] = 1;
+        assert_eq!(Pnau::parse(&proof_type).unwrap_err(), PnauError::ProofType);
+        assert_eq!(
+            Pnau::parse(&bytes[..bytes.len() - 1]).unwrap_err(),
+            PnauError::Truncated
+        );
+        // VAA size field, then the VAA cut after its header
+        assert_eq!(Pnau::parse(&bytes[..9]).unwrap_err(), PnauError::Truncated);
+        let mut vaa = bytes[..10 + 6 + 13 * 66].to_vec();
+        vaa[8..10].copy_from_slice(&((6 + 13 * 66) as u16).to_be_bytes());
+        assert_eq!(
+            Pnau::parse(&vaa).unwrap_err(),
+            PnauError::Vaa(VaaError::ParsingTimestamp)
+        );
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let mut pnau = Pnau::parse(&pnau_bytes()).unwrap();
+        pnau.updates.push(other_update(&pnau));
+
+        let parts = pnau.split();
+        assert_eq!(parts.len(), 2);
+        for (part, update) in parts.iter().zip(pnau.updates.iter()) {
+            assert_eq!(part.vaa_bytes, pnau.vaa_bytes);
+            assert_eq!(part.updates.len(), 1);
+            assert_eq!(part.updates[0].message_bytes, update.message_bytes);
+            let reparsed = Pnau::parse(&part.serialize().unwrap()).unwrap();
+            assert_eq!(reparsed.updates[0].message, update.message);
+        }
+
+        let mut merged = parts[0].clone();
+        merged.merge(parts[1].clone());
+        merged.merge(parts[0].clone());
+        assert_eq!(merged.serialize(), pnau.serialize());
+    }
+
+    #[test]
+    fn retain_proven_updates() {
+        let mut pnau = Pnau::parse(&pnau_bytes()).unwrap();
+        let forged = other_update(&pnau);
+        pnau.updates.push(forged.clone());
+        let rejected = pnau.retain_proven_updates();
+        assert_eq!(pnau.updates.len(), 1);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0.message, forged.message);
+        assert_eq!(rejected[0].1, PnauError::MerkleRootMismatch);
+    }
+
+    #[test]
+    fn serialize_at_most_255_updates() {
+        let mut pnau = Pnau::parse(&pnau_bytes()).unwrap();
+        pnau.updates = vec![pnau.updates[0].clone(); MAX_UPDATES];
+        let reparsed = Pnau::parse(&pnau.serialize().unwrap()).unwrap();
+        assert_eq!(reparsed.updates.len(), MAX_UPDATES);
+        pnau.updates.push(pnau.updates[0].clone());
+        assert_eq!(
+            pnau.serialize().unwrap_err(),
+            "256 price messages exceed the 255 of a price update"
+        );
+    }
+
+    #[test]
+    fn chunks() {
+        let mut pnau = Pnau::parse(&pnau_bytes()).unwrap();
+        let update = pnau.updates[0].clone();
+        let base_size = pnau_bytes().len() - update_size(&update);
+        pnau.updates = vec![update.clone(); 5];
+
+        let sizes = |chunks: Vec<Pnau>| -> Vec<usize> {
+            chunks.iter().map(|chunk| chunk.updates.len()).collect()
+        };
+        assert_eq!(sizes(pnau.chunks(usize::MAX, 2).unwrap()), vec![2, 2, 1]);
+        assert_eq!(
+            sizes(
+                pnau.chunks(base_size + 3 * update_size(&update), 10)
+                    .unwrap()
+            ),
+            vec![3, 2]
+        );
+        for chunk in pnau
+            .chunks(base_size + 3 * update_size(&update), 10)
+            .unwrap()
+        {
+            assert!(chunk.serialize().unwrap().len() <= base_size + 3 * update_size(&update));
+            assert_eq!(chunk.vaa_bytes, pnau.vaa_bytes);
+        }
+
+        // No more than a byte of price messages, whatever the limit
+        pnau.updates = vec![update.clone(); 300];
+        assert_eq!(sizes(pnau.chunks(usize::MAX, 1000).unwrap()), vec![255, 45]);
+
+        assert_eq!(
+            pnau.chunks(base_size + update_size(&update) - 1, 10)
+                .unwrap_err(),
+            format!(
+                "price message 0x{} does not fit in {} bytes",
+                hex::encode(update.message.price_identifier),
+                base_size + update_size(&update) - 1
+            )
+        );
+    }
+}
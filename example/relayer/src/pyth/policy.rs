@@ -0,0 +1,276 @@
+//! Deviation and heartbeat policies deciding which price feeds get pushed on chain.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::PriceFeedPolicy;
+use crate::stacks::oracle::OnChainPrice;
+
+use super::normalize_price_feed_id;
+use super::pnau::{Pnau, PriceFeedMessage};
+
+/// Price a price feed was last known to hold on chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReferencePrice {
+    pub price: i64,
+    pub expo: i32,
+    pub publish_time: u64,
+}
+
+impl From<&OnChainPrice> for ReferencePrice {
+    fn from(on_chain_price: &OnChainPrice) -> Self {
+        ReferencePrice {
+            price: on_chain_price.price,
+            expo: on_chain_price.expo,
+            publish_time: on_chain_price.publish_time,
+        }
+    }
+}
+
+/// Reason for pushing a price feed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpdateTrigger {
+    /// The policy has no bound.
+    Unconditional,
+    /// No on-chain price to compare with.
+    NoReference,
+    /// Deviation from the on-chain price, in basis points.
+    Deviation(u64),
+    /// Age of the on-chain price, in seconds.
+    Heartbeat(u64),
+}
+
+impl fmt::Display for UpdateTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateTrigger::Unconditional => write!(f, "no policy"),
+            UpdateTrigger::NoReference => write!(f, "no on-chain price"),
+            UpdateTrigger::Deviation(bps) => write!(f, "deviation of {} bps", bps),
+            UpdateTrigger::Heartbeat(secs) => write!(f, "on-chain price {}s old", secs),
+        }
+    }
+}
+
+/// Deviation in basis points between two prices, `None` if they can't be compared.
+fn deviation_bps(reference: &ReferencePrice, message: &PriceFeedMessage) -> Option<u64> {
+    if reference.expo != message.expo || reference.price == 0 {
+        return None;
+    }
+    let delta = (message.price as i128 - reference.price as i128).unsigned_abs();
+    let bps = delta * 10_000 / (reference.price as i128).unsigned_abs();
+    Some(bps.try_into().unwrap_or(u64::MAX))
+}
+
+/// Check whether the message crosses one of the bounds of the policy.
+pub fn evaluate(
+    policy: &PriceFeedPolicy,
+    reference: Option<&ReferencePrice>,
+    message: &PriceFeedMessage,
+) -> Option<UpdateTrigger> {
+    if policy.deviation_threshold_bps.is_none() && policy.heartbeat_secs.is_none() {
+        return Some(UpdateTrigger::Unconditional);
+    }
+    let reference = match reference {
+        Some(reference) => reference,
+        None => return Some(UpdateTrigger::NoReference),
+    };
+    if message.publish_time <= reference.publish_time {
+        return None;
+    }
+    if let Some(heartbeat_secs) = policy.heartbeat_secs {
+        let age = message.publish_time - reference.publish_time;
+        if age >= heartbeat_secs {
+            return Some(UpdateTrigger::Heartbeat(age));
+        }
+    }
+    if let Some(deviation_threshold_bps) = policy.deviation_threshold_bps {
+        match deviation_bps(reference, message) {
+            Some(bps) if bps >= deviation_threshold_bps => {
+                return Some(UpdateTrigger::Deviation(bps))
+            }
+            Some(_) => {}
+            // Prices not comparable, let the on-chain price be refreshed
+            None => return Some(UpdateTrigger::NoReference),
+        }
+    }
+    None
+}
+
+/// Reference prices, keyed by normalized price feed id.
+#[derive(Clone, Debug, Default)]
+pub struct ReferencePrices {
+    prices: HashMap<String, ReferencePrice>,
+}
+
+impl ReferencePrices {
+    pub fn get(&self, price_feed_id: &[u8; 32]) -> Option<&ReferencePrice> {
+        self.prices.get(&hex::encode(price_feed_id))
+    }
+
+    /// Keep the newest of the known and given references. Prices read on chain
+    /// don't override prices submitted since then.
+    pub fn insert(&mut self, price_feed_id: &str, reference: ReferencePrice) {
+        let price_feed_id = normalize_price_feed_id(price_feed_id);
+        match self.prices.get(&price_feed_id) {
+            Some(known) if known.publish_time >= reference.publish_time => {}
+            _ => {
+                self.prices.insert(price_feed_id, reference);
+            }
+        }
+    }
+
+    /// Forget the reference of a price feed, until read on chain again.
+    pub fn remove(&mut self, price_feed_id: &str) {
+        self.prices.remove(&normalize_price_feed_id(price_feed_id));
+    }
+
+    pub fn record(&mut self, message: &PriceFeedMessage) {
+        let reference = ReferencePrice {
+            price: message.price,
+            expo: message.expo,
+            publish_time: message.publish_time,
+        };
+        self.insert(&hex::encode(message.price_identifier), reference);
+    }
+
+    /// Record the price messages carried by accumulator updates.
+    pub fn record_price_updates(&mut self, price_updates: &[(String, Vec<u8>)]) {
+        for (_, price_update) in price_updates.iter() {
+            if let Ok(pnau) = Pnau::parse(price_update) {
+                for update in pnau.updates.iter() {
+                    self.record(&update.message);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(price: i64, publish_time: u64) -> ReferencePrice {
+        ReferencePrice {
+            price,
+            expo: -8,
+            publish_time,
+        }
+    }
+
+    fn message(price: i64, publish_time: u64) -> PriceFeedMessage {
+        PriceFeedMessage {
+            price_identifier: [1; 32],
+            price,
+            conf: 0,
+            expo: -8,
+            publish_time,
+            prev_publish_time: publish_time - 1,
+            ema_price: price,
+            ema_conf: 0,
+        }
+    }
+
+    fn policy(
+        deviation_threshold_bps: Option<u64>,
+        heartbeat_secs: Option<u64>,
+    ) -> PriceFeedPolicy {
+        PriceFeedPolicy {
+            deviation_threshold_bps,
+            heartbeat_secs,
+        }
+    }
+
+    #[test]
+    fn deviation() {
+        let negative = reference(-10_000, 100);
+        assert_eq!(deviation_bps(&negative, &message(-10_000, 101)), Some(0));
+        assert_eq!(deviation_bps(&negative, &message(-10_050, 101)), Some(50));
+        assert_eq!(deviation_bps(&negative, &message(-9_950, 101)), Some(50));
+        assert_eq!(
+            deviation_bps(&negative, &message(10_000, 101)),
+            Some(20_000)
+        );
+
+        let mut expo = message(10_000, 101);
+        expo.expo = -6;
+        assert_eq!(deviation_bps(&negative, &expo), None);
+        assert_eq!(deviation_bps(&reference(0, 100), &message(1, 101)), None);
+    }
+
+    #[test]
+    fn deviation_bound() {
+        let policy = policy(Some(50), None);
+        let reference = reference(10_000, 100);
+        assert_eq!(
+            evaluate(&policy, Some(&reference), &message(10_049, 101)),
+            None
+        );
+        assert_eq!(
+            evaluate(&policy, Some(&reference), &message(10_050, 101)),
+            Some(UpdateTrigger::Deviation(50))
+        );
+        assert_eq!(
+            evaluate(&policy, Some(&reference), &message(9_900, 101)),
+            Some(UpdateTrigger::Deviation(100))
+        );
+    }
+
+    #[test]
+    fn heartbeat_bound() {
+        let policy = policy(Some(50), Some(60));
+        let reference = reference(10_000, 100);
+        assert_eq!(
+            evaluate(&policy, Some(&reference), &message(10_000, 159)),
+            None
+        );
+        assert_eq!(
+            evaluate(&policy, Some(&reference), &message(10_000, 160)),
+            Some(UpdateTrigger::Heartbeat(60))
+        );
+    }
+
+    #[test]
+    fn prices_not_comparable() {
+        let policy = policy(Some(50), None);
+        let mut expo = message(10_000, 101);
+        expo.expo = -6;
+        assert_eq!(
+            evaluate(&policy, Some(&reference(10_000, 100)), &expo),
+            Some(UpdateTrigger::NoReference)
+        );
+        assert_eq!(
+            evaluate(&policy, Some(&reference(0, 100)), &message(0, 101)),
+            Some(UpdateTrigger::NoReference)
+        );
+        assert_eq!(
+            evaluate(&policy, None, &message(10_000, 101)),
+            Some(UpdateTrigger::NoReference)
+        );
+    }
+
+    #[test]
+    fn messages_not_newer_than_the_reference() {
+        let policy = policy(Some(50), Some(60));
+        let reference = reference(10_000, 100);
+        assert_eq!(
+            evaluate(&policy, Some(&reference), &message(20_000, 100)),
+            None
+        );
+        assert_eq!(
+            evaluate(&policy, Some(&reference), &message(20_000, 99)),
+            None
+        );
+    }
+
+    #[test]
+    fn no_bound() {
+        assert_eq!(
+            evaluate(
+                &policy(None, None),
+                Some(&reference(10_000, 100)),
+                &message(10_000, 50)
+            ),
+            Some(UpdateTrigger::Unconditional)
+        );
+    }
+}
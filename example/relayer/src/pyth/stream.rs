@@ -0,0 +1,233 @@
+//! Subscription to Hermes `/v2/updates/price/stream` server-sent events, keeping
+//! the newest update of each price feed at hand for the runloop.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use chainhook_sdk::utils::Context;
+
+use crate::config::BinaryEncoding;
+use crate::utils::unix_timestamp;
+
+use super::hermes::{decode_price_updates, price_updates_query, PriceFeedUpdate};
+use super::normalize_price_feed_id;
+
+const RECONNECT_MIN_BACKOFF: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// reqwest can't time out idle reads, so connections are recycled instead of
+/// hanging forever on a silent server.
+const CONNECTION_MAX_DURATION: Duration = Duration::from_secs(300);
+/// Updates older than this are not handed to the runloop.
+pub const PRICE_UPDATE_MAX_AGE_SECS: u64 = 60;
+
+pub enum StreamCommand {
+    Terminate,
+}
+
+/// Accumulate the chunks of an event stream and yield the data of complete events.
+/// Chunks are kept as bytes, a character being possibly split across two chunks.
+#[derive(Default)]
+struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer
+            .extend(chunk.iter().filter(|byte| **byte != b'\r'));
+        let mut events = vec![];
+        while let Some(end) = self.buffer.windows(2).position(|bytes| bytes == b"\n\n") {
+            let event = String::from_utf8_lossy(&self.buffer[..end]).into_owned();
+            self.buffer.drain(..end + 2);
+            let data = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect::<Vec<_>>();
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+        events
+    }
+}
+
+pub struct PriceUpdatesStream {
+    price_updates: Arc<Mutex<HashMap<String, PriceFeedUpdate>>>,
+    stream_cmd_tx: crossbeam_channel::Sender<StreamCommand>,
+}
+
+impl PriceUpdatesStream {
+    /// Open the subscription on a dedicated thread, reconnecting with an exponential
+    /// backoff. Updates received after a reconnection only replace older ones.
+    pub fn start(
+        price_service_url: &str,
+        price_feeds_ids: &[String],
+        encoding: &BinaryEncoding,
+        ctx: &Context,
+    ) -> PriceUpdatesStream {
+        let (stream_cmd_tx, stream_cmd_rx) = crossbeam_channel::unbounded();
+        let price_updates = Arc::new(Mutex::new(HashMap::new()));
+
+        let url = format!(
+            "{}/v2/updates/price/stream?{}",
+            price_service_url,
+            price_updates_query(price_feeds_ids, encoding)
+        );
+        let encoding = encoding.clone();
+        let price_updates_moved = price_updates.clone();
+        let ctx = ctx.clone();
+        let _ = std::thread::spawn(move || {
+            let mut backoff = RECONNECT_MIN_BACKOFF;
+            loop {
+                let future = consume_stream(
+                    &url,
+                    &encoding,
+                    &price_updates_moved,
+                    &stream_cmd_rx,
+                    &mut backoff,
+                    &ctx,
+                );
+                match hiro_system_kit::nestable_block_on(future) {
+                    Ok(Some(StreamCommand::Terminate)) => break,
+                    Ok(None) => debug!(ctx.expect_logger(), "Recycling price updates stream"),
+                    Err(e) => {
+                        warn!(
+                            ctx.expect_logger(),
+                            "Price updates stream interrupted: {}, reconnecting in {}s",
+                            e,
+                            backoff.as_secs()
+                        );
+                        sleep(backoff);
+                        backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+                    }
+                }
+                if let Ok(StreamCommand::Terminate) = stream_cmd_rx.try_recv() {
+                    break;
+                }
+            }
+        });
+
+        PriceUpdatesStream {
+            price_updates,
+            stream_cmd_tx,
+        }
+    }
+
+    /// Newest update received for each of the given price feeds, skipping the
+    /// feeds without recent data.
+    pub fn latest(&self, price_feeds_ids: &[String], ctx: &Context) -> Vec<(String, Vec<u8>)> {
+        let price_updates = match self.price_updates.lock() {
+            Ok(price_updates) => price_updates,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let now = unix_timestamp();
+        let mut latest = vec![];
+        for price_feed_id in price_feeds_ids.iter() {
+            match price_updates.get(&normalize_price_feed_id(price_feed_id)) {
+                Some(update) if update.publish_time + PRICE_UPDATE_MAX_AGE_SECS >= now => {
+                    latest.push((price_feed_id.clone(), update.price_update.clone()))
+                }
+                Some(update) => warn!(
+                    ctx.expect_logger(),
+                    "Latest update of price feed {} is stale (published at {})",
+                    price_feed_id,
+                    update.publish_time
+                ),
+                None => warn!(
+                    ctx.expect_logger(),
+                    "No update received yet for price feed {}", price_feed_id
+                ),
+            }
+        }
+        latest
+    }
+}
+
+impl Drop for PriceUpdatesStream {
+    fn drop(&mut self) {
+        let _ = self.stream_cmd_tx.send(StreamCommand::Terminate);
+    }
+}
+
+/// Read events until the connection ends, returning `None` once it is recycled
+/// after `CONNECTION_MAX_DURATION`.
+async fn consume_stream(
+    url: &str,
+    encoding: &BinaryEncoding,
+    price_updates: &Mutex<HashMap<String, PriceFeedUpdate>>,
+    stream_cmd_rx: &crossbeam_channel::Receiver<StreamCommand>,
+    backoff: &mut Duration,
+    ctx: &Context,
+) -> Result<Option<StreamCommand>, String> {
+    let mut response = reqwest::Client::new()
+        .get(url)
+        .header("Accept", "text/event-stream")
+        .timeout(CONNECTION_MAX_DURATION)
+        .send()
+        .await
+        .map_err(|e| format!("unable to connect: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("unable to connect: {}", response.status()));
+    }
+    info!(ctx.expect_logger(), "Subscribed to price updates stream");
+
+    let mut decoder = EventStreamDecoder::default();
+    loop {
+        if let Ok(StreamCommand::Terminate) = stream_cmd_rx.try_recv() {
+            return Ok(Some(StreamCommand::Terminate));
+        }
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => return Err("connection closed".into()),
+            Err(e) if e.is_timeout() => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        for event in decoder.push(&chunk) {
+            let new_updates = match decode_price_updates(event.as_bytes(), encoding, ctx) {
+                Ok(new_updates) => new_updates,
+                Err(e) => {
+                    warn!(ctx.expect_logger(), "Discarding price updates event: {}", e);
+                    continue;
+                }
+            };
+            *backoff = RECONNECT_MIN_BACKOFF;
+            let mut price_updates = match price_updates.lock() {
+                Ok(price_updates) => price_updates,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            for (price_feed_id, update) in new_updates {
+                match price_updates.get(&price_feed_id) {
+                    Some(known) if known.publish_time >= update.publish_time => {}
+                    _ => {
+                        price_updates.insert(price_feed_id, update);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_events_across_chunks() {
+        let mut decoder = EventStreamDecoder::default();
+        let stream =
+            "event: price\r\ndata: {\"price\": \"€1\"}\r\n\r\n: ping\n\ndata: a\ndata: b\n\n";
+        // Split within the euro sign and the event separators
+        let split_at = stream.find('€').unwrap() + 1;
+        let (head, tail) = stream.as_bytes().split_at(split_at);
+        assert!(decoder.push(head).is_empty());
+        assert_eq!(
+            decoder.push(&tail[..tail.len() - 1]),
+            vec!["{\"price\": \"€1\"}"]
+        );
+        assert_eq!(decoder.push(&tail[tail.len() - 1..]), vec!["a\nb"]);
+        assert!(decoder.buffer.is_empty());
+    }
+}
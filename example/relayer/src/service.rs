@@ -1,40 +1,62 @@
-use std::collections::HashMap;
-use std::io::{Cursor, Read};
+use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::channel;
 use std::thread;
+use std::time::Instant;
 
-use chainhook_sdk::stacks_rpc_client::clarity::codec::{
-    TransactionAnchorMode, TransactionContractCall, TransactionPayload,
-};
-use chainhook_sdk::stacks_rpc_client::clarity::vm::types::Value;
-use chainhook_sdk::stacks_rpc_client::{self, StacksRpc};
+use chainhook_sdk::stacks_rpc_client::clarity::codec::{TransactionAnchorMode, TransactionPayload};
+use chainhook_sdk::stacks_rpc_client::StacksRpc;
 use chainhook_sdk::{
     observer::{start_event_observer, ObserverEvent},
     types::{BlockIdentifier, StacksChainEvent},
     utils::Context,
 };
-use libsecp256k1::{recover, Message, PublicKey, RecoveryId, Signature};
-use sha3::Digest;
-use sha3::Keccak256;
 
-use crate::config::{PythConfig, StacksConfig};
+use crate::config::{
+    FeeEstimateTier, GuardianSetSource, OracleEntrypoint, PriceFeedPolicy, PriceServiceApi,
+    PythConfig,
+};
+use crate::pyth::pnau::Pnau;
+use crate::pyth::policy::{evaluate, ReferencePrices};
+use crate::pyth::stream::PriceUpdatesStream;
+use crate::pyth::{
+    coalesce_price_updates, extract_vaa, hermes, price_entries_count, price_feeds_ids,
+};
+use crate::stacks::accounts::AccountPool;
+use crate::stacks::balance::{BalanceMonitor, BalanceStatus};
+use crate::stacks::confirmations::{
+    ChainUpdate, ConfirmationTracker, PriceUpdateError, TransactionOutcome,
+};
+use crate::stacks::fees::FeeBudget;
+use crate::stacks::nonces::NonceRejection;
+use crate::stacks::oracle::{
+    batch_price_updates, build_price_updates_payload, fetch_on_chain_prices, ExecutionPlan,
+    OracleFee,
+};
+use crate::stacks::rpc::get_account_balance;
+use crate::stacks::simulation::ChainState;
+use crate::stacks::transactions::TrackedTransaction;
+use crate::wormhole::compute_eth_address;
+use crate::wormhole::guardians::{GuardianSet, GuardianSetRegistry};
 use crate::{
     config::Config,
-    utils::{start_new_clock, ClockCommand},
+    utils::{start_new_clock, unix_timestamp, ClockCommand},
 };
 
-const VAA_GUARDIANS_SET_EPOCH_1: &str = "010000000001007ac31b282c2aeeeb37f3385ee0de5f8e421d30b9e5ae8ba3d4375c1c77a86e77159bb697d9c456d6f8c02d22a94b1279b65b0d6a9957e7d3857423845ac758e300610ac1d2000000030001000000000000000000000000000000000000000000000000000000000000000400000000000005390000000000000000000000000000000000000000000000000000000000436f7265020000000000011358cc3ae5c097b213ce3c81979e1b9f9570746aa5ff6cb952589bde862c25ef4392132fb9d4a42157114de8460193bdf3a2fcf81f86a09765f4762fd1107a0086b32d7a0977926a205131d8731d39cbeb8c82b2fd82faed2711d59af0f2499d16e726f6b211b39756c042441be6d8650b69b54ebe715e234354ce5b4d348fb74b958e8966e2ec3dbd4958a7cdeb5f7389fa26941519f0863349c223b73a6ddee774a3bf913953d695260d88bc1aa25a4eee363ef0000ac0076727b35fbea2dac28fee5ccb0fea768eaf45ced136b9d9e24903464ae889f5c8a723fc14f93124b7c738843cbb89e864c862c38cddcccf95d2cc37a4dc036a8d232b48f62cdd4731412f4890da798f6896a3331f64b48c12d1d57fd9cbe7081171aa1be1d36cafe3867910f99c09e347899c19c38192b6e7387ccd768277c17dab1b7a5027c0b3cf178e21ad2e77ae06711549cfbb1f9c7a9d8096e85e1487f35515d02a92753504a8d75471b9f49edb6fbebc898f403e4773e95feb15e80c9a99c8348d";
-const VAA_GUARDIANS_SET_EPOCH_2: &str = "01000000010d0012e6b39c6da90c5dfd3c228edbb78c7a4c97c488ff8a346d161a91db067e51d638c17216f368aa9bdf4836b8645a98018ca67d2fec87d769cabfdf2406bf790a0002ef42b288091a670ef3556596f4f47323717882881eaf38e03345078d07a156f312b785b64dae6e9a87e3d32872f59cb1931f728cecf511762981baf48303668f0103cef2616b84c4e511ff03329e0853f1bd7ee9ac5ba71d70a4d76108bddf94f69c2a8a84e4ee94065e8003c334e899184943634e12043d0dda78d93996da073d190104e76d166b9dac98f602107cc4b44ac82868faf00b63df7d24f177aa391e050902413b71046434e67c770b19aecdf7fce1d1435ea0be7262e3e4c18f50ddc8175c0105d9450e8216d741e0206a50f93b750a47e0a258b80eb8fed1314cc300b3d905092de25cd36d366097b7103ae2d184121329ba3aa2d7c6cc53273f11af14798110010687477c8deec89d36a23e7948feb074df95362fc8dcbd8ae910ac556a1dee1e755c56b9db5d710c940938ed79bc1895a3646523a58bc55f475a23435a373ecfdd0107fb06734864f79def4e192497362513171530daea81f07fbb9f698afe7e66c6d44db21323144f2657d4a5386a954bb94eef9f64148c33aef6e477eafa2c5c984c01088769e82216310d1827d9bd48645ec23e90de4ef8a8de99e2d351d1df318608566248d80cdc83bdcac382b3c30c670352be87f9069aab5037d0b747208eae9c650109e9796497ff9106d0d1c62e184d83716282870cef61a1ee13d6fc485b521adcce255c96f7d1bca8d8e7e7d454b65783a830bddc9d94092091a268d311ecd84c26010c468c9fb6d41026841ff9f8d7368fa309d4dbea3ea4bbd2feccf94a92cc8a20a226338a8e2126cd16f70eaf15b4fc9be2c3fa19def14e071956a605e9d1ac4162010e23fcb6bd445b7c25afb722250c1acbc061ed964ba9de1326609ae012acdfb96942b2a102a2de99ab96327859a34a2b49a767dbdb62e0a1fb26af60fe44fd496a00106bb0bac77ac68b347645f2fb1ad789ea9bd76fb9b2324f25ae06f97e65246f142df717f662e73948317182c62ce87d79c73def0dba12e5242dfc038382812cfe00126da03c5e56cb15aeeceadc1e17a45753ab4dc0ec7bf6a75ca03143ed4a294f6f61bc3f478a457833e43084ecd7c985bf2f55a55f168aac0e030fc49e845e497101626e9d9a5d9e343f00010000000000000000000000000000000000000000000000000000000000000004c1759167c43f501c2000000000000000000000000000000000000000000000000000000000436f7265020000000000021358cc3ae5c097b213ce3c81979e1b9f9570746aa5ff6cb952589bde862c25ef4392132fb9d4a42157114de8460193bdf3a2fcf81f86a09765f4762fd1107a0086b32d7a0977926a205131d8731d39cbeb8c82b2fd82faed2711d59af0f2499d16e726f6b211b39756c042441be6d8650b69b54ebe715e234354ce5b4d348fb74b958e8966e2ec3dbd4958a7cd66b9590e1c41e0b226937bf9217d1d67fd4e91f574a3bf913953d695260d88bc1aa25a4eee363ef0000ac0076727b35fbea2dac28fee5ccb0fea768eaf45ced136b9d9e24903464ae889f5c8a723fc14f93124b7c738843cbb89e864c862c38cddcccf95d2cc37a4dc036a8d232b48f62cdd4731412f4890da798f6896a3331f64b48c12d1d57fd9cbe7081171aa1be1d36cafe3867910f99c09e347899c19c38192b6e7387ccd768277c17dab1b7a5027c0b3cf178e21ad2e77ae06711549cfbb1f9c7a9d8096e85e1487f35515d02a92753504a8d75471b9f49edb6fbebc898f403e4773e95feb15e80c9a99c8348d";
-const VAA_GUARDIANS_SET_EPOCH_3: &str = "01000000020d00ce45474d9e1b1e7790a2d210871e195db53a70ffd6f237cfe70e2686a32859ac43c84a332267a8ef66f59719cf91cc8df0101fd7c36aa1878d5139241660edc0010375cc906156ae530786661c0cd9aef444747bc3d8d5aa84cac6a6d2933d4e1a031cffa30383d4af8131e929d9f203f460b07309a647d6cd32ab1cc7724089392c000452305156cfc90343128f97e499311b5cae174f488ff22fbc09591991a0a73d8e6af3afb8a5968441d3ab8437836407481739e9850ad5c95e6acfcc871e951bc30105a7956eefc23e7c945a1966d5ddbe9e4be376c2f54e45e3d5da88c2f8692510c7429b1ea860ae94d929bd97e84923a18187e777aa3db419813a80deb84cc8d22b00061b2a4f3d2666608e0aa96737689e3ba5793810ff3a52ff28ad57d8efb20967735dc5537a2e43ef10f583d144c12a1606542c207f5b79af08c38656d3ac40713301086b62c8e130af3411b3c0d91b5b50dcb01ed5f293963f901fc36e7b0e50114dce203373b32eb45971cef8288e5d928d0ed51cd86e2a3006b0af6a65c396c009080009e93ab4d2c8228901a5f4525934000b2c26d1dc679a05e47fdf0ff3231d98fbc207103159ff4116df2832eea69b38275283434e6cd4a4af04d25fa7a82990b707010aa643f4cf615dfff06ffd65830f7f6cf6512dabc3690d5d9e210fdc712842dc2708b8b2c22e224c99280cd25e5e8bfb40e3d1c55b8c41774e287c1e2c352aecfc010b89c1e85faa20a30601964ccc6a79c0ae53cfd26fb10863db37783428cd91390a163346558239db3cd9d420cfe423a0df84c84399790e2e308011b4b63e6b8015010ca31dcb564ac81a053a268d8090e72097f94f366711d0c5d13815af1ec7d47e662e2d1bde22678113d15963da100b668ba26c0c325970d07114b83c5698f46097010dc9fda39c0d592d9ed92cd22b5425cc6b37430e236f02d0d1f8a2ef45a00bde26223c0a6eb363c8b25fd3bf57234a1d9364976cefb8360e755a267cbbb674b39501108db01e444ab1003dd8b6c96f8eb77958b40ba7a85fefecf32ad00b7a47c0ae7524216262495977e09c0989dd50f280c21453d3756843608eacd17f4fdfe47600001261025228ef5af837cb060bcd986fcfa84ccef75b3fa100468cfd24e7fadf99163938f3b841a33496c2706d0208faab088bd155b2e20fd74c625bb1cc8c43677a0163c53c409e0c5dfa000100000000000000000000000000000000000000000000000000000000000000046c5a054d7833d1e42000000000000000000000000000000000000000000000000000000000436f7265020000000000031358cc3ae5c097b213ce3c81979e1b9f9570746aa5ff6cb952589bde862c25ef4392132fb9d4a42157114de8460193bdf3a2fcf81f86a09765f4762fd1107a0086b32d7a0977926a205131d8731d39cbeb8c82b2fd82faed2711d59af0f2499d16e726f6b211b39756c042441be6d8650b69b54ebe715e234354ce5b4d348fb74b958e8966e2ec3dbd4958a7cd15e7caf07c4e3dc8e7c469f92c8cd88fb8005a2074a3bf913953d695260d88bc1aa25a4eee363ef0000ac0076727b35fbea2dac28fee5ccb0fea768eaf45ced136b9d9e24903464ae889f5c8a723fc14f93124b7c738843cbb89e864c862c38cddcccf95d2cc37a4dc036a8d232b48f62cdd4731412f4890da798f6896a3331f64b48c12d1d57fd9cbe7081171aa1be1d36cafe3867910f99c09e347899c19c38192b6e7387ccd768277c17dab1b7a5027c0b3cf178e21ad2e77ae06711549cfbb1f9c7a9d8096e85e1487f35515d02a92753504a8d75471b9f49edb6fbebc898f403e4773e95feb15e80c9a99c8348d";
-
 pub enum BridgeTenureCommand {
     StartPriceFeedBlockUpdates(BlockIdentifier),
     PriceFeedBlockUpdatesTick,
     PerformPriceFeedMicroblockUpdate(BlockIdentifier),
+    /// Txids of the relayer transactions rolled back with their microblocks.
+    RollbackPriceFeedMicroblockUpdates(Vec<String>),
+    ReconcilePriceFeedUpdates(ChainUpdate),
     StopPriceFeedBlockUpdates,
 }
 
 pub fn start_bridge_service(config: &Config, ctx: &Context) -> Result<(), String> {
+    // Signers may prompt for their secrets, before the observer starts logging
+    let accounts = AccountPool::load(&config.stacks, &config.bridge.balance_policy)?;
+    let relayer_addresses = accounts.addresses().into_iter().collect::<HashSet<_>>();
+
     let (tenure_cmd_tx, tenure_cmd_rx) = crossbeam_channel::unbounded();
     let (observer_cmd_tx, observer_cmd_rx) = channel();
     let (observer_event_tx, observer_event_rx) = crossbeam_channel::unbounded();
@@ -67,11 +89,12 @@ pub fn start_bridge_service(config: &Config, ctx: &Context) -> Result<(), String
     let _ = std::thread::spawn(move || {
         let future = start_bridge_runloop(
             &config_cloned,
+            accounts,
             tenure_cmd_tx_cloned,
             tenure_cmd_rx,
             &context_cloned,
         );
-        let _ = hiro_system_kit::nestable_block_on(future);
+        hiro_system_kit::nestable_block_on(future);
     });
 
     loop {
@@ -101,13 +124,37 @@ pub fn start_bridge_service(config: &Config, ctx: &Context) -> Result<(), String
                 if new_block_identifier.index < config.stacks.start_block {
                     continue;
                 }
-                let (_reorg, microblocks_in_progress) = match chain_event {
+                let (reorg, microblocks_in_progress) = match &chain_event {
                     StacksChainEvent::ChainUpdatedWithBlocks(_) => (false, false),
                     StacksChainEvent::ChainUpdatedWithReorg(_) => (true, false),
                     StacksChainEvent::ChainUpdatedWithMicroblocks(_) => (false, true),
-                    StacksChainEvent::ChainUpdatedWithMicroblocksReorg(_) => (true, true),
+                    StacksChainEvent::ChainUpdatedWithMicroblocksReorg(data) => {
+                        let rolled_back_txids = data
+                            .microblocks_to_rollback
+                            .iter()
+                            .flat_map(|microblock| microblock.transactions.iter())
+                            .filter(|transaction| {
+                                relayer_addresses.contains(&transaction.metadata.sender)
+                            })
+                            .map(|transaction| transaction.transaction_identifier.hash.clone())
+                            .collect::<Vec<_>>();
+                        if !rolled_back_txids.is_empty() {
+                            let _ = tenure_cmd_tx.send(
+                                BridgeTenureCommand::RollbackPriceFeedMicroblockUpdates(
+                                    rolled_back_txids,
+                                ),
+                            );
+                        }
+                        (true, true)
+                    }
                 };
 
+                if let Some(chain_update) =
+                    ChainUpdate::from_chain_event(&chain_event, &relayer_addresses)
+                {
+                    let _ = tenure_cmd_tx
+                        .send(BridgeTenureCommand::ReconcilePriceFeedUpdates(chain_update));
+                }
                 if microblocks_in_progress {
                     // Submit microblock anchored transaction
                     let _ =
@@ -115,11 +162,19 @@ pub fn start_bridge_service(config: &Config, ctx: &Context) -> Result<(), String
                             new_block_identifier.clone(),
                         ));
                 } else {
-                    info!(
-                        ctx.expect_logger(),
-                        "Stacks blockchain updated with block #{}", new_block_identifier.index
-                    );
-                    // Submit RBF transaction if new
+                    if reorg {
+                        warn!(
+                            ctx.expect_logger(),
+                            "Stacks blockchain reorganized, new tip block #{}",
+                            new_block_identifier.index
+                        );
+                    } else {
+                        info!(
+                            ctx.expect_logger(),
+                            "Stacks blockchain updated with block #{}", new_block_identifier.index
+                        );
+                    }
+                    // Submit RBF transaction if new, or update the price feeds on the new canonical tip
                     let _ = tenure_cmd_tx.send(BridgeTenureCommand::StartPriceFeedBlockUpdates(
                         new_block_identifier.clone(),
                     ));
@@ -135,70 +190,167 @@ pub fn start_bridge_service(config: &Config, ctx: &Context) -> Result<(), String
 }
 
 pub async fn ping_bridge_service(config: &Config, ctx: &Context) -> Result<(), String> {
-    // Test the price feeds
-    let _price_feeds = fetch_pyth_price_feeds(&config.pyth, &ctx).await?;
-
-    // for (price_feed_id, price_feed) in price_feeds.iter() {
-    //     info!(ctx.expect_logger(), "{}: {:?}", price_feed_id, price_feed);
-    // }
-
-    info!(ctx.expect_logger(), "Epoch 1");
-    let vaa_bytes = hex::decode(VAA_GUARDIANS_SET_EPOCH_1).unwrap();
-    let public_keys_epoch_1 = compute_secp256k1_recovery_from_vaa(&vaa_bytes, &ctx).unwrap();
-    for (guardian_id, public_key) in public_keys_epoch_1.iter() {
-        let public_key_bytes = public_key.serialize()[1..].to_vec();
-        let eth_address = {
-            let public_key_hash: [u8; 32] =
-                Keccak256::digest(&public_key_bytes).try_into().unwrap();
-            public_key_hash[12..32].to_vec()
-        };
+    let registry = load_guardian_set_registry(config).await?;
+    for guardian_set in registry.iter() {
         info!(
             ctx.expect_logger(),
-            "{}:\t0x{} ({})",
-            guardian_id,
-            hex::encode(&eth_address),
-            hex::encode(&public_key_bytes)
+            "Guardian set #{} ({} guardians, quorum: {}, expiration: {})",
+            guardian_set.index,
+            guardian_set.addresses.len(),
+            guardian_set.quorum(),
+            guardian_set
+                .expiration_time
+                .map(|expiration_time| expiration_time.to_string())
+                .unwrap_or("none".into())
         );
+        for (guardian_id, eth_address) in guardian_set.addresses.iter().enumerate() {
+            info!(
+                ctx.expect_logger(),
+                "{}:\t0x{}",
+                guardian_id,
+                hex::encode(eth_address)
+            );
+        }
     }
 
-    info!(ctx.expect_logger(), "Epoch 2");
-    let vaa_bytes = hex::decode(VAA_GUARDIANS_SET_EPOCH_2).unwrap();
-    let public_keys_epoch_2 = compute_secp256k1_recovery_from_vaa(&vaa_bytes, &ctx).unwrap();
-    for (guardian_id, public_key) in public_keys_epoch_2.iter() {
-        let public_key_bytes = public_key.serialize()[1..].to_vec();
-        let eth_address = {
-            let public_key_hash: [u8; 32] =
-                Keccak256::digest(&public_key_bytes).try_into().unwrap();
-            public_key_hash[12..32].to_vec()
+    log_on_chain_prices(config, ctx).await?;
+    log_balance(config, ctx).await?;
+
+    // Test the price feeds
+    let price_feeds = fetch_pyth_price_feeds(&config.pyth, ctx).await?;
+    let now = unix_timestamp();
+    for (price_feed_id, price_update) in price_feeds.iter() {
+        let result = extract_vaa(price_update).and_then(|vaa| {
+            registry
+                .verify(&vaa, now)
+                .map(|guardian_set| guardian_set.index)
+                .map_err(|e| e.to_string())
+        });
+        match result {
+            Ok(guardian_set_index) => info!(
+                ctx.expect_logger(),
+                "{}: verified by guardian set #{}", price_feed_id, guardian_set_index
+            ),
+            Err(e) => warn!(ctx.expect_logger(), "{}: {}", price_feed_id, e),
+        }
+        if Pnau::is_pnau(price_update) {
+            log_accumulator_update(price_feed_id, price_update, ctx);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn log_on_chain_prices(config: &Config, ctx: &Context) -> Result<(), String> {
+    let now = unix_timestamp();
+    for (price_feed_id, on_chain_price) in
+        fetch_on_chain_prices(&config.stacks, &config.pyth.price_feeds_ids).await?
+    {
+        match on_chain_price {
+            Some(price) => info!(
+                ctx.expect_logger(),
+                "{}: on-chain price {} ± {} (expo: {}), ema {} ± {}, published at {} ({}s ago, previous: {})",
+                price_feed_id,
+                price.price,
+                price.conf,
+                price.expo,
+                price.ema_price,
+                price.ema_conf,
+                price.publish_time,
+                now.saturating_sub(price.publish_time),
+                price.prev_publish_time
+            ),
+            None => info!(
+                ctx.expect_logger(),
+                "{}: no on-chain price", price_feed_id
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Log the balance of the hot wallets, and the number of updates of all the price
+/// feeds they can pay the oracle fees of.
+async fn log_balance(config: &Config, ctx: &Context) -> Result<(), String> {
+    let accounts = AccountPool::load(&config.stacks, &config.bridge.balance_policy)?;
+    let balance_monitor = BalanceMonitor::new(&config.bridge.balance_policy);
+    let mut available_balance = 0u128;
+    for address in accounts.addresses() {
+        let balance = get_account_balance(&config.stacks.stacks_node_rpc_url, &address).await?;
+        match balance_monitor.status(balance) {
+            BalanceStatus::Funded => info!(
+                ctx.expect_logger(),
+                "Balance of {}: {} µSTX", address, balance
+            ),
+            BalanceStatus::Low => warn!(
+                ctx.expect_logger(),
+                "Balance of {}: {} µSTX, below the warning threshold", address, balance
+            ),
+            BalanceStatus::Depleted => error!(
+                ctx.expect_logger(),
+                "Balance of {}: {} µSTX, below the floor of {} µSTX",
+                address,
+                balance,
+                config.bridge.balance_policy.floor
+            ),
+        }
+        available_balance += balance.saturating_sub(config.bridge.balance_policy.floor as u128);
+    }
+
+    if config.stacks.pyth_oracle_entrypoint == OracleEntrypoint::VerifyAndUpdatePriceFeeds {
+        let oracle_fee = OracleFee::fetch(&config.stacks).await?.per_price_update();
+        let update_fee = oracle_fee.saturating_mul(config.pyth.price_feeds_ids.len() as u64);
+        let updates = match update_fee {
+            0 => "unlimited".to_string(),
+            update_fee => (available_balance / update_fee as u128).to_string(),
         };
         info!(
             ctx.expect_logger(),
-            "{}:\t0x{} ({})",
-            guardian_id,
-            hex::encode(&eth_address),
-            hex::encode(&public_key_bytes)
+            "Oracle fee: {} µSTX per price feed, {} µSTX per update of the {} price feeds ({} updates left, transaction fees excluded)",
+            oracle_fee,
+            update_fee,
+            config.pyth.price_feeds_ids.len(),
+            updates
         );
     }
+    Ok(())
+}
 
-    info!(ctx.expect_logger(), "Epoch 3");
-    let vaa_bytes = hex::decode(VAA_GUARDIANS_SET_EPOCH_3).unwrap();
-    let public_keys_epoch_3 = compute_secp256k1_recovery_from_vaa(&vaa_bytes, &ctx).unwrap();
-    for (guardian_id, public_key) in public_keys_epoch_3.iter() {
-        let public_key_bytes = public_key.serialize()[1..].to_vec();
-        let eth_address = {
-            let public_key_hash: [u8; 32] =
-                Keccak256::digest(&public_key_bytes).try_into().unwrap();
-            public_key_hash[12..32].to_vec()
-        };
+fn log_accumulator_update(price_feed_id: &str, price_update: &[u8], ctx: &Context) {
+    let pnau = match Pnau::parse(price_update) {
+        Ok(pnau) => pnau,
+        Err(e) => {
+            warn!(ctx.expect_logger(), "{}: {}", price_feed_id, e);
+            return;
+        }
+    };
+    info!(
+        ctx.expect_logger(),
+        "{}: merkle root 0x{} (slot: {}, ring size: {})",
+        price_feed_id,
+        hex::encode(pnau.merkle_root.hash),
+        pnau.merkle_root.slot,
+        pnau.merkle_root.ring_size
+    );
+    for update in pnau.updates.iter() {
+        let message = &update.message;
         info!(
             ctx.expect_logger(),
-            "{}:\t0x{}",
-            guardian_id,
-            hex::encode(&eth_address)
+            "0x{}: price {} ± {} (expo: {}), ema {} ± {}, published at {} (previous: {}), merkle proof: {}",
+            hex::encode(message.price_identifier),
+            message.price,
+            message.conf,
+            message.expo,
+            message.ema_price,
+            message.ema_conf,
+            message.publish_time,
+            message.prev_publish_time,
+            match update.verify(&pnau.merkle_root) {
+                Ok(()) => format!("{} nodes, ok", update.proof.len()),
+                Err(e) => e.to_string(),
+            }
         );
     }
-
-    Ok(())
 }
 
 pub async fn collect_guardians_public_keys(config: &Config, ctx: &Context) {
@@ -207,38 +359,61 @@ pub async fn collect_guardians_public_keys(config: &Config, ctx: &Context) {
         "Collecting VAAs and computing guardians public keys"
     );
 
+    let guardian_set = match load_guardian_set_registry(config)
+        .await
+        .and_then(|registry| registry.active().cloned().ok_or("no guardian set".into()))
+    {
+        Ok(guardian_set) => guardian_set,
+        Err(e) => {
+            error!(ctx.expect_logger(), "Unable to load guardian set: {}", e);
+            return;
+        }
+    };
+
     let mut public_keys = HashMap::new();
     loop {
-        let vaas = match fetch_pyth_price_feeds(&config.pyth, &ctx).await {
+        let vaas = match fetch_pyth_price_feeds(&config.pyth, ctx).await {
             Ok(vaas) => vaas,
             Err(e) => {
-                error!(
-                    ctx.expect_logger(),
-                    "unable to fetch price feed: {}",
-                    e.to_string()
-                );
+                error!(ctx.expect_logger(), "unable to fetch price feed: {}", e);
+                thread::sleep(config.bridge.price_update_interval());
                 continue;
             }
         };
-        for (_, vaa) in vaas.iter() {
-            let new_public_keys = compute_secp256k1_recovery_from_vaa(vaa, ctx).unwrap();
+        for (price_feed_id, vaa_bytes) in vaas.iter() {
+            let new_public_keys = match extract_vaa(vaa_bytes)
+                .and_then(|vaa| vaa.recover_public_keys().map_err(|e| e.to_string()))
+            {
+                Ok(new_public_keys) => new_public_keys,
+                Err(e) => {
+                    warn!(
+                        ctx.expect_logger(),
+                        "unable to decode VAA for price feed {}: {}", price_feed_id, e
+                    );
+                    continue;
+                }
+            };
             for (guardian_id, public_key) in new_public_keys.into_iter() {
-                match public_keys.get(&guardian_id) {
-                    None => {
-                        let key = public_key.serialize();
-                        info!(
-                            ctx.expect_logger(),
-                            "Found new public key: {}\t{}",
-                            guardian_id,
-                            hex::encode(&key)
-                        );
-                        public_keys.insert(guardian_id, public_key.serialize());
-                    }
-                    Some(_) => {}
+                if guardian_set.addresses.get(guardian_id as usize)
+                    != Some(&compute_eth_address(&public_key))
+                {
+                    continue;
+                }
+                if let std::collections::hash_map::Entry::Vacant(entry) =
+                    public_keys.entry(guardian_id)
+                {
+                    let key = public_key.serialize();
+                    info!(
+                        ctx.expect_logger(),
+                        "Found new public key: {}\t{}",
+                        guardian_id,
+                        hex::encode(key)
+                    );
+                    entry.insert(key);
                 }
             }
         }
-        if public_keys.len() == 19 {
+        if public_keys.len() == guardian_set.addresses.len() {
             break;
         }
         thread::sleep(std::time::Duration::from_secs(1));
@@ -247,6 +422,7 @@ pub async fn collect_guardians_public_keys(config: &Config, ctx: &Context) {
 
 pub async fn start_bridge_runloop(
     config: &Config,
+    accounts: AccountPool,
     tenure_cmd_tx: crossbeam_channel::Sender<BridgeTenureCommand>,
     tenure_cmd_rx: crossbeam_channel::Receiver<BridgeTenureCommand>,
     ctx: &Context,
@@ -257,65 +433,948 @@ pub async fn start_bridge_runloop(
         "Listening for Stacks blockchain events on port {}", config.event_observer.ingestion_port
     );
 
-    let mut rbf_tracking = HashMap::new();
-    let rbf_enabled = false;
+    // wormhole-core-v2 only accepts VAAs signed by its active guardian set
+    let guardian_set = match load_guardian_set_registry(config)
+        .await
+        .and_then(|registry| registry.active().cloned().ok_or("no guardian set".into()))
+    {
+        Ok(guardian_set) => guardian_set,
+        Err(e) => {
+            error!(ctx.expect_logger(), "Unable to load guardian set: {}", e);
+            return;
+        }
+    };
+
+    let price_updates_stream = match &config.pyth.price_service_api {
+        PriceServiceApi::HermesV2Stream(encoding) => Some(PriceUpdatesStream::start(
+            &config.pyth.price_service_url,
+            &config.pyth.price_feeds_ids,
+            encoding,
+            ctx,
+        )),
+        _ => None,
+    };
+
+    let mut runloop = BridgeRunloop {
+        config,
+        accounts,
+        guardian_set,
+        price_updates_stream,
+        reference_prices: ReferencePrices::default(),
+        confirmation_tracker: ConfirmationTracker::default(),
+        fee_budget: FeeBudget::new(&config.bridge.fee_strategy),
+        fee_budget_exhausted: false,
+        requeued_price_feeds: HashSet::new(),
+        block_height: 0,
+        ctx,
+    };
     let mut price_updated = false;
+    let mut last_microblock_update: Option<Instant> = None;
     let mut clock_stop: Option<crossbeam_channel::Sender<ClockCommand>> = None;
     loop {
         let event = match tenure_cmd_rx.recv() {
             Ok(event) => event,
             Err(e) => {
                 // display error
-                error!(
-                    ctx.expect_logger(),
-                    "Tenure aborted: {}", e.to_string()
-                );            
+                error!(ctx.expect_logger(), "Tenure aborted: {}", e.to_string());
                 break;
             }
         };
 
         match event {
-            BridgeTenureCommand::StartPriceFeedBlockUpdates(_block) => {
+            BridgeTenureCommand::StartPriceFeedBlockUpdates(block) => {
+                runloop.block_height = block.index;
+                runloop.refresh_reference_prices().await;
                 // Stop previous clock
                 if let Some(previous_clock) = clock_stop {
                     let _ = previous_clock.send(ClockCommand::Terminate);
                 }
                 // Start new clock
                 let new_clock = start_new_clock(
-                    config.bridge.price_updates_per_minute,
+                    config.bridge.price_update_interval(),
                     tenure_cmd_tx.clone(),
-                    &ctx,
+                    ctx,
                 );
                 clock_stop = Some(new_clock);
                 price_updated = false;
             }
             BridgeTenureCommand::PriceFeedBlockUpdatesTick => {
-                if price_updated && !rbf_enabled {
+                if price_updated {
                     continue;
                 }
-                // Fetch Pyth Price Feeds
-                let vaas = match fetch_pyth_price_feeds(&config.pyth, &ctx).await {
-                    Ok(vaas) => vaas,
+                match runloop
+                    .update_price_feeds(TransactionAnchorMode::OnChainOnly)
+                    .await
+                {
+                    Ok(submitted) => price_updated = submitted,
+                    Err(e) => error!(ctx.expect_logger(), "Unable to update price feeds: {}", e),
+                }
+            }
+            BridgeTenureCommand::PerformPriceFeedMicroblockUpdate(_microblock) => {
+                let microblocks = match &config.bridge.microblocks {
+                    Some(microblocks) => microblocks,
+                    None => continue,
+                };
+                // Rate limited independently from the block updates
+                if let Some(last_update) = last_microblock_update {
+                    if last_update.elapsed() < microblocks.interval() {
+                        continue;
+                    }
+                }
+                match runloop.update_price_feeds(microblocks.anchor_mode).await {
+                    Ok(true) => last_microblock_update = Some(Instant::now()),
+                    Ok(false) => {}
+                    Err(e) => error!(
+                        ctx.expect_logger(),
+                        "Unable to update price feeds in microblocks: {}", e
+                    ),
+                }
+            }
+            BridgeTenureCommand::RollbackPriceFeedMicroblockUpdates(txids) => {
+                runloop.reconcile_microblocks_reorg(&txids).await;
+            }
+            BridgeTenureCommand::ReconcilePriceFeedUpdates(chain_update) => {
+                runloop.reconcile_chain_update(chain_update).await;
+            }
+            BridgeTenureCommand::StopPriceFeedBlockUpdates => {
+                // Termination
+                break;
+            }
+        }
+    }
+}
+
+/// Attempts at submitting a transaction rejected because of its nonce.
+const MAX_NONCE_ATTEMPTS: usize = 3;
+
+/// State of the bridge runloop, carried across tenure commands.
+struct BridgeRunloop<'a> {
+    config: &'a Config,
+    accounts: AccountPool,
+    guardian_set: GuardianSet,
+    price_updates_stream: Option<PriceUpdatesStream>,
+    reference_prices: ReferencePrices,
+    confirmation_tracker: ConfirmationTracker,
+    fee_budget: FeeBudget,
+    /// Price updates are paused until fees can be spent again.
+    fee_budget_exhausted: bool,
+    /// Price feeds to push regardless of their policy, as their last update didn't make it.
+    requeued_price_feeds: HashSet<String>,
+    /// Height of the last block received.
+    block_height: u64,
+    ctx: &'a Context,
+}
+
+impl<'a> BridgeRunloop<'a> {
+    /// Refresh the reference prices used by the price feeds policies.
+    async fn refresh_reference_prices(&mut self) {
+        if !self
+            .config
+            .pyth
+            .price_feeds_policies
+            .values()
+            .any(|policy| *policy != PriceFeedPolicy::default())
+        {
+            return;
+        }
+        match fetch_on_chain_prices(&self.config.stacks, &self.config.pyth.price_feeds_ids).await {
+            Ok(on_chain_prices) => {
+                for (price_feed_id, on_chain_price) in on_chain_prices.iter() {
+                    if let Some(on_chain_price) = on_chain_price {
+                        self.reference_prices
+                            .insert(price_feed_id, on_chain_price.into());
+                    }
+                }
+            }
+            Err(e) => warn!(
+                self.ctx.expect_logger(),
+                "Unable to read on-chain prices: {}", e
+            ),
+        }
+    }
+
+    /// Fetch, check and submit the price updates due, anchored according to
+    /// `anchor_mode`. Returns whether a transaction was submitted.
+    async fn update_price_feeds(
+        &mut self,
+        anchor_mode: TransactionAnchorMode,
+    ) -> Result<bool, String> {
+        let ctx = self.ctx;
+        // Pause instead of draining the wallet once the fee budget is spent
+        let fee_floor = self.config.bridge.fee_strategy.floor.max(1);
+        let exhausted = matches!(
+            self.fee_budget.remaining(unix_timestamp()),
+            Some(remaining) if remaining < fee_floor
+        );
+        match (self.fee_budget_exhausted, exhausted) {
+            (false, true) => warn!(
+                ctx.expect_logger(),
+                "Fee budget exhausted, pausing price updates"
+            ),
+            (true, false) => info!(
+                ctx.expect_logger(),
+                "Fee budget available, resuming price updates"
+            ),
+            _ => {}
+        }
+        self.fee_budget_exhausted = exhausted;
+        if exhausted {
+            return Ok(false);
+        }
+        // Price updates go on as long as one of the accounts can pay for them
+        let mut funded_accounts = 0;
+        for index in self.accounts.indices() {
+            // An account keeps its last status when its balance can't be read
+            let status = match self.check_balance(index).await {
+                Ok(status) => status,
+                Err(e) => {
+                    let account = self.accounts.get(index);
+                    warn!(
+                        ctx.expect_logger(),
+                        "Balance of {} unknown, keeping its status ({:?}): {}",
+                        account.address,
+                        account.balance_status,
+                        e
+                    );
+                    account.balance_status
+                }
+            };
+            if status != BalanceStatus::Depleted {
+                funded_accounts += 1;
+            }
+        }
+        if funded_accounts == 0 {
+            return Ok(false);
+        }
+
+        // Fetch Pyth Price Feeds, or take the freshest ones from the stream
+        let vaas = match &self.price_updates_stream {
+            Some(stream) => stream.latest(&self.config.pyth.price_feeds_ids, ctx),
+            None => fetch_pyth_price_feeds(&self.config.pyth, ctx)
+                .await
+                .map_err(|e| format!("unable to fetch price feed: {}", e))?,
+        };
+
+        self.sync_nonces()
+            .await
+            .map_err(|e| format!("unable to sync nonces: {}", e))?;
+        // Pending transactions due for a replacement, carrying fresh prices of their feeds.
+        // Replacements are scheduled on block arrivals, not on microblocks, and left
+        // to the accounts able to pay for them.
+        let stale_transactions = match (&self.config.bridge.rbf, anchor_mode) {
+            (Some(rbf), TransactionAnchorMode::OnChainOnly) => self
+                .accounts
+                .iter()
+                .filter(|account| account.balance_status != BalanceStatus::Depleted)
+                .flat_map(|account| {
+                    account
+                        .nonce_manager
+                        .stale_transactions(self.block_height, rbf.rebroadcast_after_blocks)
+                })
+                .collect(),
+            _ => vec![],
+        };
+        let forced_price_feeds_ids = stale_transactions
+            .iter()
+            .flat_map(|transaction| transaction.price_feeds_ids.iter().cloned())
+            .chain(self.requeued_price_feeds.iter().cloned())
+            .collect::<HashSet<_>>();
+
+        // Drop the VAAs that would be rejected by wormhole-core
+        let vaas = filter_verified_vaas(vaas, &self.guardian_set, ctx);
+        // Only keep the price feeds crossing their deviation or heartbeat bounds
+        let vaas = filter_due_price_updates(
+            vaas,
+            &self.config.pyth,
+            &self.reference_prices,
+            &forced_price_feeds_ids,
+            ctx,
+        );
+        if vaas.is_empty() {
+            return Ok(false);
+        }
+
+        // Submit Stacks transactions
+        let submitted_price_feeds_ids = self
+            .submit_stacks_transaction(&vaas, stale_transactions, anchor_mode)
+            .await
+            .map_err(|e| format!("unable to submit price feeds: {}", e))?;
+        // Nothing changed on chain, simulate again on the next block
+        if self.config.bridge.dry_run {
+            return Ok(true);
+        }
+        if submitted_price_feeds_ids.is_empty() {
+            return Ok(false);
+        }
+        let vaas = vaas
+            .into_iter()
+            .filter(|(_, vaa)| {
+                price_feeds_ids(vaa)
+                    .iter()
+                    .all(|price_feed_id| submitted_price_feeds_ids.contains(price_feed_id))
+            })
+            .collect::<Vec<_>>();
+        self.reference_prices.record_price_updates(&vaas);
+        for (_, vaa) in vaas.iter() {
+            for price_feed_id in price_feeds_ids(vaa) {
+                self.requeued_price_feeds.remove(&price_feed_id);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Check the balance of a relayer account against the balance policy,
+    /// logging its changes of status along with the estimated runway.
+    async fn check_balance(&mut self, index: usize) -> Result<BalanceStatus, String> {
+        let (config, ctx) = (self.config, self.ctx);
+        let account = self.accounts.get_mut(index);
+        let address = &account.address;
+        let balance = get_account_balance(&config.stacks.stacks_node_rpc_url, address)
+            .await
+            .map_err(|e| format!("unable to check balance: {}", e))?;
+        let runway = match account.balance_monitor.runway(balance, unix_timestamp()) {
+            Some(runway) => format!("{}h{:02}m", runway / 3600, runway % 3600 / 60),
+            None => "unknown".into(),
+        };
+        let status = account.balance_monitor.status(balance);
+        debug!(
+            ctx.expect_logger(),
+            "Balance of {}: {} µSTX (runway: {})", address, balance, runway
+        );
+        match (account.balance_status, status) {
+            (previous, status) if previous == status => {}
+            (_, BalanceStatus::Depleted) => error!(
+                ctx.expect_logger(),
+                "Balance of {} down to {} µSTX, below the floor of {} µSTX, pausing its price updates",
+                address,
+                balance,
+                config.bridge.balance_policy.floor
+            ),
+            (BalanceStatus::Depleted, BalanceStatus::Low) => warn!(
+                ctx.expect_logger(),
+                "Balance of {} up to {} µSTX, resuming its price updates (runway: {})",
+                address,
+                balance,
+                runway
+            ),
+            (_, BalanceStatus::Low) => warn!(
+                ctx.expect_logger(),
+                "Balance of {} running low: {} µSTX (runway: {})", address, balance, runway
+            ),
+            (_, BalanceStatus::Funded) => info!(
+                ctx.expect_logger(),
+                "Balance of {} back to {} µSTX", address, balance
+            ),
+        }
+        account.balance_status = status;
+        Ok(status)
+    }
+
+    /// Transactions of the relayer were rolled back along with their microblocks:
+    /// the prices they carried may no longer be on chain, and their nonces may be
+    /// available again.
+    async fn reconcile_microblocks_reorg(&mut self, txids: &[String]) {
+        let ctx = self.ctx;
+        let mut rolled_back = vec![];
+        for txid in txids.iter() {
+            warn!(
+                ctx.expect_logger(),
+                "Transaction {} rolled back with its microblock", txid
+            );
+            // Still watched, microblock transactions being reported with their block
+            if let Some(transaction) = self.confirmation_tracker.forget(txid) {
+                rolled_back.push(transaction);
+            }
+        }
+        self.reference_prices = ReferencePrices::default();
+        for transaction in rolled_back.iter() {
+            self.requeue_price_feeds(transaction);
+            if let Some(index) = self.accounts.position(&transaction.sender) {
+                self.accounts
+                    .get_mut(index)
+                    .nonce_manager
+                    .restore(transaction.clone());
+            }
+        }
+        self.refresh_reference_prices().await;
+        // The rolled back transactions may be back in the mempool, or dropped
+        if let Err(e) = self.sync_nonces().await {
+            warn!(ctx.expect_logger(), "Unable to sync nonces: {}", e);
+        }
+        // The ones still pending may get mined in a block again
+        for transaction in rolled_back.iter() {
+            let pending = self
+                .accounts
+                .position(&transaction.sender)
+                .and_then(|index| {
+                    self.accounts
+                        .get(index)
+                        .nonce_manager
+                        .get(transaction.nonce)
+                })
+                .map_or(false, |pending| pending.txid == transaction.txid);
+            if pending {
+                self.confirmation_tracker.watch(transaction);
+            }
+        }
+    }
+
+    /// Reconcile the nonces of the accounts with the node, logging the transactions
+    /// no longer pending.
+    async fn sync_nonces(&mut self) -> Result<(), String> {
+        let config = self.config;
+        for index in self.accounts.indices() {
+            let (settled, evicted) = self
+                .accounts
+                .get_mut(index)
+                .nonce_manager
+                .sync(&config.stacks.stacks_node_rpc_url)
+                .await?;
+            for transaction in settled.iter() {
+                debug!(
+                    self.ctx.expect_logger(),
+                    "Transaction {} (nonce {}) no longer pending",
+                    transaction.txid,
+                    transaction.nonce
+                );
+            }
+            for transaction in evicted.iter() {
+                warn!(
+                    self.ctx.expect_logger(),
+                    "Transaction {} (nonce {} of {}) dropped from the mempool, reusing its nonce",
+                    transaction.txid,
+                    transaction.nonce,
+                    transaction.sender
+                );
+                self.confirmation_tracker.forget(&transaction.txid);
+                self.requeue_price_feeds(transaction);
+            }
+        }
+        Ok(())
+    }
+
+    /// Push the price feeds of a transaction that didn't make it again, at the next update.
+    fn requeue_price_feeds(&mut self, transaction: &TrackedTransaction) {
+        self.forget_reference_prices(transaction);
+        self.requeued_price_feeds
+            .extend(transaction.price_feeds_ids.iter().cloned());
+    }
+
+    /// Match the relayer transactions against the blocks received.
+    async fn reconcile_chain_update(&mut self, chain_update: ChainUpdate) {
+        let ctx = self.ctx;
+        if !chain_update.rolled_back_blocks.is_empty() {
+            warn!(
+                ctx.expect_logger(),
+                "Chain reorg of depth {}, orphaned blocks: {}",
+                chain_update.rolled_back_blocks.len(),
+                chain_update
+                    .rolled_back_blocks
+                    .iter()
+                    .map(|block| format!("#{} ({})", block.index, block.hash))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            // The references may come from the orphaned blocks, read them again on the new tip
+            self.reference_prices = ReferencePrices::default();
+        }
+
+        let orphaned = self
+            .confirmation_tracker
+            .rollback(&chain_update.rolled_back_blocks);
+        if !orphaned.is_empty() {
+            warn!(
+                ctx.expect_logger(),
+                "Transactions orphaned by the reorg: {}",
+                orphaned
+                    .iter()
+                    .map(|transaction| transaction.txid.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            for transaction in orphaned.iter() {
+                self.requeue_price_feeds(transaction);
+                if let Some(index) = self.accounts.position(&transaction.sender) {
+                    self.accounts
+                        .get_mut(index)
+                        .nonce_manager
+                        .restore(transaction.clone());
+                }
+            }
+            // The orphaned transactions may be back in the mempool, or dropped
+            if let Err(e) = self.sync_nonces().await {
+                warn!(ctx.expect_logger(), "Unable to sync nonces: {}", e);
+            }
+        }
+
+        for (transaction, mined) in self
+            .confirmation_tracker
+            .apply(chain_update.mined_transactions)
+        {
+            match &mined.outcome {
+                TransactionOutcome::Success => info!(
+                    ctx.expect_logger(),
+                    "Transaction {} (nonce {}) confirmed in block #{}, {} price entries updated",
+                    mined.txid,
+                    transaction.nonce,
+                    mined.block.index,
+                    transaction.price_entries
+                ),
+                TransactionOutcome::Failure(e) => {
+                    error!(
+                        ctx.expect_logger(),
+                        "Transaction {} (nonce {}) failed in block #{}: {}",
+                        mined.txid,
+                        transaction.nonce,
+                        mined.block.index,
+                        e
+                    );
+                    // Fresher prices may succeed, while newer prices are already on chain
+                    match e {
+                        PriceUpdateError::StalePrice => self.requeue_price_feeds(&transaction),
+                        _ => self.forget_reference_prices(&transaction),
+                    }
+                }
+                TransactionOutcome::Aborted(result) => {
+                    error!(
+                        ctx.expect_logger(),
+                        "Transaction {} (nonce {}) aborted in block #{}: {}",
+                        mined.txid,
+                        transaction.nonce,
+                        mined.block.index,
+                        result
+                    );
+                    self.forget_reference_prices(&transaction);
+                }
+            }
+        }
+
+        self.confirmation_tracker
+            .finalize(&chain_update.confirmed_blocks);
+    }
+
+    /// The prices recorded on submission didn't make it on chain.
+    fn forget_reference_prices(&mut self, transaction: &TrackedTransaction) {
+        for price_feed_id in transaction.price_feeds_ids.iter() {
+            self.reference_prices.remove(price_feed_id);
+        }
+    }
+
+    /// Submit the price updates, replacing the `stale_transactions` first when
+    /// replace-by-fee is enabled, and rotating across the accounts otherwise.
+    /// Expects the nonces to be synced. Transactions failing the pre-flight simulation
+    /// are not broadcast, and nothing is in dry-run mode. Returns the ids of the price
+    /// feeds submitted.
+    async fn submit_stacks_transaction(
+        &mut self,
+        vaas: &[(String, Vec<u8>)],
+        stale_transactions: Vec<TrackedTransaction>,
+        anchor_mode: TransactionAnchorMode,
+    ) -> Result<Vec<String>, String> {
+        let (config, ctx) = (self.config, self.ctx);
+        let (stacks_config, rbf) = (&config.stacks, config.bridge.rbf.as_ref());
+        let fee_strategy = &config.bridge.fee_strategy;
+        let execution_plan = match stacks_config.pyth_oracle_entrypoint {
+            OracleEntrypoint::VerifyAndUpdatePriceFeeds => Some(
+                ExecutionPlan::fetch(stacks_config)
+                    .await
+                    .map_err(|e| format!("unable to retrieve execution plan: {}", e))?,
+            ),
+            OracleEntrypoint::LegacyUpdatePricesFeeds => None,
+        };
+        let price_updates =
+            coalesce_price_updates(vaas.iter().map(|(_, vaa)| vaa.clone()).collect())?;
+        let (batches, rejected) =
+            batch_price_updates(&stacks_config.pyth_oracle_entrypoint, price_updates);
+        for e in rejected.iter() {
+            warn!(ctx.expect_logger(), "Discarding price update: {}", e);
+        }
+        let transaction_payloads = batches
+            .iter()
+            .map(|batch| build_price_updates_payload(stacks_config, execution_plan.as_ref(), batch))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut replaceable_transactions = stale_transactions
+            .into_iter()
+            .filter_map(|transaction| {
+                let rbf = rbf?;
+                if rbf
+                    .replacement_fee(transaction.fee, 0)
+                    .filter(|fee| *fee <= fee_strategy.ceiling)
+                    .is_none()
+                {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Transaction {} (nonce {}, fee: {}) pending, fee ceiling {} reached",
+                        transaction.txid,
+                        transaction.nonce,
+                        transaction.fee,
+                        rbf.max_fee.min(fee_strategy.ceiling)
+                    );
+                    return None;
+                }
+                Some((transaction, rbf))
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        let mut chain_state = ChainState::fetch(
+            stacks_config,
+            &self.accounts.addresses(),
+            &batches
+                .iter()
+                .flat_map(|batch| batch.iter().flat_map(|vaa| price_feeds_ids(vaa)))
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .map_err(|e| format!("unable to simulate price feed updates: {}", e))?;
+        let mut submitted_price_feeds_ids = vec![];
+
+        let stacks_rpc = StacksRpc::new(&stacks_config.stacks_node_rpc_url);
+        // A failed submission would leave a gap in the nonces, stop there
+        'batches: for (transaction_payload, batch) in
+            transaction_payloads.into_iter().zip(batches.iter())
+        {
+            let price_entries = batch.iter().map(|vaa| price_entries_count(vaa)).sum();
+            let price_feeds_ids = batch
+                .iter()
+                .flat_map(|vaa| price_feeds_ids(vaa))
+                .collect::<Vec<_>>();
+            let mut replacing = replaceable_transactions.next();
+            // Replacements are signed by the account of the transaction they replace
+            let index = match &replacing {
+                Some((replaced, _)) => self.accounts.position(&replaced.sender),
+                None => self
+                    .accounts
+                    .select(self.block_height, config.bridge.account_stuck_after_blocks),
+            }
+            .ok_or("no account available")?;
+            let sender = self.accounts.get(index).address.clone();
+            // Fees escalate with the pending transactions of the account
+            let pending_blocks = self
+                .accounts
+                .get(index)
+                .nonce_manager
+                .pending_since()
+                .map(|block_height| self.block_height.saturating_sub(block_height))
+                .unwrap_or(0);
+            let mut attempts = 0;
+            loop {
+                attempts += 1;
+                let estimated_fee = estimate_transaction_fee(
+                    &stacks_rpc,
+                    &transaction_payload,
+                    fee_strategy.estimate_tier,
+                    ctx,
+                );
+                let fee = fee_strategy.fee(estimated_fee, pending_blocks);
+                let (nonce, tx_fee, spent_fee) = match &replacing {
+                    Some((replaced, rbf)) => {
+                        let tx_fee = rbf
+                            .replacement_fee(replaced.fee, fee)
+                            .unwrap_or(rbf.max_fee)
+                            .min(fee_strategy.ceiling);
+                        (replaced.nonce, tx_fee, tx_fee.saturating_sub(replaced.fee))
+                    }
+                    None => (
+                        self.accounts.get(index).nonce_manager.next_nonce()?,
+                        fee,
+                        fee,
+                    ),
+                };
+                if let Some(remaining) = self.fee_budget.remaining(unix_timestamp()) {
+                    if spent_fee > remaining {
+                        self.fee_budget_exhausted = true;
+                        return Err(format!(
+                            "fee budget exhausted ({} µSTX left, {} µSTX needed)",
+                            remaining, spent_fee
+                        ));
+                    }
+                }
+                // Kept once the transaction is accepted by the node
+                let mut simulated_chain_state = chain_state.clone();
+                let simulation = match simulated_chain_state.simulate(
+                    &sender,
+                    batch,
+                    tx_fee,
+                    unix_timestamp(),
+                ) {
+                    Ok(simulation) => simulation,
                     Err(e) => {
-                        error!(
+                        warn!(
                             ctx.expect_logger(),
-                            "unable to fetch price feed: {}",
-                            e.to_string()
+                            "Price feed update would fail with {}, not broadcasting (account: {}, nonce: {}, fee: {}, {} price entries)",
+                            e,
+                            sender,
+                            nonce,
+                            tx_fee,
+                            price_entries
                         );
-                        continue;
+                        continue 'batches;
+                    }
+                };
+                for (price_feed_id, e) in simulation.discarded.iter() {
+                    info!(
+                        ctx.expect_logger(),
+                        "Price entry {} would be discarded: {}", price_feed_id, e
+                    );
+                }
+                if config.bridge.dry_run {
+                    info!(
+                        ctx.expect_logger(),
+                        "Dry run: price feed update would succeed (account: {}, nonce: {}, fee: {}, oracle fees: {}, {} of {} price entries written)",
+                        sender,
+                        nonce,
+                        tx_fee,
+                        simulation.oracle_fees,
+                        simulation.written,
+                        price_entries
+                    );
+                    chain_state = simulated_chain_state;
+                    continue 'batches;
+                }
+                let transaction = self.accounts.get(index).signer.sign_transaction_payload(
+                    transaction_payload.clone(),
+                    nonce,
+                    tx_fee,
+                    anchor_mode,
+                    stacks_config.network.is_mainnet(),
+                )?;
+                let e = match stacks_rpc.post_transaction(&transaction) {
+                    Ok(res) => {
+                        info!(
+                        ctx.expect_logger(),
+                        "Price feed update transaction submitted ({}, account: {}, nonce: {}, fee: {}, {} price entries)",
+                        res.txid,
+                        sender,
+                        nonce,
+                        tx_fee,
+                        price_entries
+                    );
+                        chain_state = simulated_chain_state;
+                        submitted_price_feeds_ids.extend(price_feeds_ids.iter().cloned());
+                        let tracked_transaction = TrackedTransaction {
+                            txid: res.txid.clone(),
+                            sender: sender.clone(),
+                            nonce,
+                            fee: tx_fee,
+                            price_entries,
+                            price_feeds_ids,
+                            block_height: self.block_height,
+                            replaced: vec![],
+                        };
+                        self.confirmation_tracker.watch(&tracked_transaction);
+                        self.fee_budget.record(unix_timestamp(), spent_fee);
+                        // Only one transaction per nonce gets mined, and charged the oracle fee
+                        let oracle_fees = match replacing {
+                            Some(_) => 0,
+                            None => simulation.oracle_fees,
+                        };
+                        let account = self.accounts.get_mut(index);
+                        account
+                            .balance_monitor
+                            .record(unix_timestamp(), spent_fee.saturating_add(oracle_fees));
+                        let replaced = account.nonce_manager.track(tracked_transaction);
+                        if let Some(replaced) = replaced {
+                            info!(
+                                ctx.expect_logger(),
+                                "Transaction {} (fee: {}) replaced by {} (fee: {})",
+                                replaced.txid,
+                                replaced.fee,
+                                res.txid,
+                                tx_fee
+                            );
+                        }
+                        break;
                     }
+                    Err(e) => e.to_string(),
                 };
+                match (NonceRejection::parse(&e), replacing.take()) {
+                    // The node requires a higher fee, or the transaction got mined meanwhile:
+                    // submit with a new nonce instead
+                    (Some(rejection), Some((replaced, _))) if attempts < MAX_NONCE_ATTEMPTS => {
+                        warn!(
+                            ctx.expect_logger(),
+                            "Replacement of transaction {} rejected (nonce: {}, {:?})",
+                            replaced.txid,
+                            nonce,
+                            rejection
+                        );
+                        if let NonceRejection::BadNonce { .. } = rejection {
+                            self.accounts
+                                .get_mut(index)
+                                .nonce_manager
+                                .recover(nonce, &rejection);
+                        }
+                    }
+                    (Some(rejection), None) if attempts < MAX_NONCE_ATTEMPTS => {
+                        warn!(
+                            ctx.expect_logger(),
+                            "Price feed update rejected (nonce: {}, {:?}), retrying",
+                            nonce,
+                            rejection
+                        );
+                        self.accounts
+                            .get_mut(index)
+                            .nonce_manager
+                            .recover(nonce, &rejection);
+                    }
+                    _ => {
+                        return Err(format!(
+                            "unable to submit price feed update (nonce: {}): {}",
+                            nonce, e
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(submitted_price_feeds_ids)
+    }
+}
+
+/// Keep the price updates passing the checks performed by `wormhole-core-v2.parse-and-verify-vaa`
+/// and, for accumulator updates, the merkle proofs checked by `pyth-pnau-decoder-v1`,
+/// logging the ones discarded.
+pub fn filter_verified_vaas(
+    vaas: Vec<(String, Vec<u8>)>,
+    guardian_set: &GuardianSet,
+    ctx: &Context,
+) -> Vec<(String, Vec<u8>)> {
+    vaas.into_iter()
+        .filter_map(|(price_feed_id, vaa_bytes)| {
+            let vaa = match extract_vaa(&vaa_bytes) {
+                Ok(vaa) => vaa,
+                Err(e) => {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Discarding price feed {}: {}", price_feed_id, e
+                    );
+                    return None;
+                }
+            };
+            if let Err(e) = vaa.verify(guardian_set) {
+                warn!(
+                    ctx.expect_logger(),
+                    "Discarding price feed {} (sequence {}): {}",
+                    price_feed_id,
+                    vaa.body.sequence,
+                    e
+                );
+                return None;
+            }
+            debug!(
+                ctx.expect_logger(),
+                "{} VAA verified (emitter chain: {}, sequence: {}, timestamp: {})",
+                price_feed_id,
+                vaa.body.emitter_chain,
+                vaa.body.sequence,
+                vaa.body.timestamp
+            );
+            if !Pnau::is_pnau(&vaa_bytes) {
+                return Some((price_feed_id, vaa_bytes));
+            }
 
-                // Submit Stacks transactions
-                if submit_stacks_transaction(&config.stacks, vaas, &mut rbf_tracking, &ctx).await.is_ok() {
-                    price_updated = true;
+            // Already decoded by extract_vaa
+            let mut pnau = Pnau::parse(&vaa_bytes).ok()?;
+            for (update, e) in pnau.retain_proven_updates() {
+                warn!(
+                    ctx.expect_logger(),
+                    "Discarding price message 0x{} from price feed {}: {}",
+                    hex::encode(update.message.price_identifier),
+                    price_feed_id,
+                    e
+                );
+            }
+            if pnau.updates.is_empty() {
+                return None;
+            }
+            match pnau.serialize() {
+                Ok(price_update) => Some((price_feed_id, price_update)),
+                Err(e) => {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Discarding price feed {}: {}", price_feed_id, e
+                    );
+                    None
                 }
             }
-            BridgeTenureCommand::PerformPriceFeedMicroblockUpdate(_microblock) => {}
-            BridgeTenureCommand::StopPriceFeedBlockUpdates => {
-                // Termination
-                break;
+        })
+        .collect()
+}
+
+/// Keep the price messages crossing one of the bounds of their price feed policy,
+/// using the prices last pushed as reference, and the ones of the `forced` price feeds.
+/// Legacy VAAs are kept as is.
+pub fn filter_due_price_updates(
+    vaas: Vec<(String, Vec<u8>)>,
+    pyth_config: &PythConfig,
+    reference_prices: &ReferencePrices,
+    forced: &HashSet<String>,
+    ctx: &Context,
+) -> Vec<(String, Vec<u8>)> {
+    vaas.into_iter()
+        .filter_map(|(price_feed_id, vaa_bytes)| {
+            let mut pnau = match Pnau::parse(&vaa_bytes) {
+                Ok(pnau) => pnau,
+                Err(_) => return Some((price_feed_id, vaa_bytes)),
+            };
+            pnau.updates.retain(|update| {
+                let message = &update.message;
+                let price_feed_id = hex::encode(message.price_identifier);
+                if forced.contains(&price_feed_id) {
+                    debug!(
+                        ctx.expect_logger(),
+                        "0x{}: update due (replacing a pending transaction)", price_feed_id
+                    );
+                    return true;
+                }
+                let policy = pyth_config.price_feed_policy(&price_feed_id);
+                let reference = reference_prices.get(&message.price_identifier);
+                match evaluate(&policy, reference, message) {
+                    Some(trigger) => {
+                        debug!(
+                            ctx.expect_logger(),
+                            "0x{}: update due ({})", price_feed_id, trigger
+                        );
+                        true
+                    }
+                    None => {
+                        debug!(
+                            ctx.expect_logger(),
+                            "0x{}: within deviation and heartbeat bounds, skipping", price_feed_id
+                        );
+                        false
+                    }
+                }
+            });
+            if pnau.updates.is_empty() {
+                return None;
+            }
+            match pnau.serialize() {
+                Ok(price_update) => Some((price_feed_id, price_update)),
+                Err(e) => {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Discarding price feed {}: {}", price_feed_id, e
+                    );
+                    None
+                }
             }
+        })
+        .collect()
+}
+
+pub async fn load_guardian_set_registry(config: &Config) -> Result<GuardianSetRegistry, String> {
+    match &config.wormhole.guardian_set_source {
+        GuardianSetSource::Builtin => GuardianSetRegistry::from_builtin(),
+        GuardianSetSource::File(path) => GuardianSetRegistry::from_file(path),
+        GuardianSetSource::OnChain => {
+            GuardianSetRegistry::from_chain(
+                &config.stacks.stacks_node_rpc_url,
+                &config.wormhole.core_contract_address,
+            )
+            .await
         }
     }
 }
@@ -323,6 +1382,30 @@ pub async fn start_bridge_runloop(
 pub async fn fetch_pyth_price_feeds(
     pyth_config: &PythConfig,
     ctx: &Context,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    match &pyth_config.price_service_api {
+        PriceServiceApi::LegacyLatestVaas => fetch_latest_vaas(pyth_config, ctx).await,
+        // One-off requests don't need the stream
+        PriceServiceApi::HermesV2(encoding) | PriceServiceApi::HermesV2Stream(encoding) => {
+            info!(
+                ctx.expect_logger(),
+                "Fetching prices feeds {}",
+                pyth_config.price_feeds_ids.join(", ")
+            );
+            hermes::fetch_latest_price_updates(
+                &pyth_config.price_service_url,
+                &pyth_config.price_feeds_ids,
+                encoding,
+                ctx,
+            )
+            .await
+        }
+    }
+}
+
+async fn fetch_latest_vaas(
+    pyth_config: &PythConfig,
+    ctx: &Context,
 ) -> Result<Vec<(String, Vec<u8>)>, String> {
     use base64::{engine::general_purpose, Engine as _};
 
@@ -333,7 +1416,8 @@ pub async fn fetch_pyth_price_feeds(
         .collect::<Vec<_>>();
     info!(
         ctx.expect_logger(),
-        "Fetching prices feeds {}", price_feed_ids.join(", ")
+        "Fetching prices feeds {}",
+        price_feed_ids.join(", ")
     );
 
     let response = reqwest::get(format!(
@@ -342,16 +1426,16 @@ pub async fn fetch_pyth_price_feeds(
         price_feed_ids.join("&")
     ))
     .await
-    .map_err(|e| format!("{}", e.to_string()))?
+    .map_err(|e| e.to_string())?
     .json::<Vec<String>>()
     .await
-    .map_err(|e| format!("{}", e.to_string()))?;
+    .map_err(|e| e.to_string())?;
 
     let mut vaas = vec![];
     for (price_feed, price_feed_id) in response.into_iter().zip(&pyth_config.price_feeds_ids) {
         let vaa = general_purpose::STANDARD
             .decode(price_feed)
-            .map_err(|e| format!("unable to decode price feed: {}", e.to_string()))?;
+            .map_err(|e| format!("unable to decode price feed: {}", e))?;
         debug!(
             ctx.expect_logger(),
             "{} price feed retrieved {}",
@@ -364,138 +1448,125 @@ pub async fn fetch_pyth_price_feeds(
     Ok(vaas)
 }
 
-pub async fn submit_stacks_transaction(
-    stacks_config: &StacksConfig,
-    vaas: Vec<(String, Vec<u8>)>,
-    rbf_tracking: &mut HashMap<u64, u64>,
+fn estimate_transaction_fee(
+    stacks_rpc: &StacksRpc,
+    transaction_payload: &TransactionPayload,
+    tier: FeeEstimateTier,
     ctx: &Context,
-) -> Result<Vec<u8>, String> {
-    let encoded_vaas = vaas
-        .iter()
-        .map(|(_, vaa)| Value::buff_from(vaa.to_vec()))
-        .collect::<Result<Vec<Value>, _>>()
-        .map_err(|e| format!("{}", e.to_string()))?;
-
-    let encoded_vaas_list =
-        Value::list_from(encoded_vaas).map_err(|e| format!("{}", e.to_string()))?;
-
-    let stacks_rpc = StacksRpc::new(&stacks_config.stacks_node_rpc_url);
-    let address = stacks_config.wallet.compute_stacks_address();
-    let nonce = stacks_rpc
-        .get_nonce(&address.to_string())
-        .expect("Unable to retrieve nonce");
-
-    let transaction_payload = TransactionPayload::ContractCall(TransactionContractCall {
-        contract_name: stacks_config.pyth_oracle_contract_address.name.clone(),
-        address: stacks_config
-            .pyth_oracle_contract_address
-            .issuer
-            .clone()
-            .into(),
-        function_name: "update-prices-feeds".into(),
-        function_args: vec![encoded_vaas_list],
-    });
-
-    let tx_fee = match rbf_tracking.get(&nonce) {
-        Some(submitted_tx_fee) => submitted_tx_fee + 10,
-        None => {
-            // Assuming we moved on to the next transaction
-            rbf_tracking.clear();
-
-            match stacks_rpc.estimate_transaction_fee(
-                &transaction_payload,
-                1, /* low = 0, medium = 1, high = 2 */
-            ) {
-                Ok(fee) => fee,
-                Err(_e) => {
-                    10_000 // default fee value
-                }
-            }
-        }
-    };
-
-    let transaction = stacks_rpc_client::crypto::sign_transaction_payload(
-        &stacks_config.wallet,
-        transaction_payload,
-        nonce,
-        tx_fee,
-        TransactionAnchorMode::OnChainOnly,
-    )?;
-
-    match stacks_rpc.post_transaction(&transaction) {
-        Ok(res) => {
-            info!(
-                ctx.expect_logger(),
-                "Price feed update transaction submitted ({})", res.txid
-            );
-        }
+) -> Option<u64> {
+    match stacks_rpc.estimate_transaction_fee(transaction_payload, tier.index()) {
+        Ok(fee) => Some(fee),
         Err(e) => {
             warn!(
                 ctx.expect_logger(),
-                "Unable to submit price feed update: {}",
+                "Unable to estimate transaction fee: {}",
                 e.to_string()
             );
+            None
         }
-    };
-
-    Ok(vec![])
+    }
 }
 
-pub fn compute_secp256k1_recovery_from_vaa(
-    vaa: &Vec<u8>,
-    ctx: &Context,
-) -> Result<Vec<(u8, PublicKey)>, String> {
-    let mut cursor = Cursor::new(&vaa);
-    // Version
-    let mut version = [0u8; 1];
-    let _ = cursor.read(&mut version);
-    // Magic bytes
-    let mut magic = [0u8; 4];
-    let _ = cursor.read(&mut magic);
-    // Signatures len
-    let mut sig_len_bytes = [0u8; 1];
-    let _ = cursor.read(&mut sig_len_bytes);
-    let sig_len = u8::from_be(sig_len_bytes[0]);
-    let mut sigs = vec![];
-    // Signatures
-    for _i in 0..sig_len {
-        let mut guardian_id_bytes = [0u8; 1];
-        let _ = cursor.read(&mut guardian_id_bytes);
-        let guardian_id = u8::from_be(guardian_id_bytes[0]);
-
-        let mut signature = [0u8; 64];
-        let _ = cursor.read(&mut signature);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::generator::generate_config;
+    use crate::stacks::nonces::tests::start_node;
 
-        debug!(
-            ctx.expect_logger(),
-            "{}:\t{}",
-            guardian_id,
-            hex::encode(&signature)
-        );
-        let mut recovery_id = [0u8; 1];
-        let _ = cursor.read(&mut recovery_id);
+    const PRIVATE_KEY: &str = "753b7cc01a1a2e86221266a154af739463fce51219d97e4f856cd7200c3bd2a601";
 
-        sigs.push((guardian_id, signature, recovery_id));
+    fn config(stacks_node_rpc_url: &str) -> Config {
+        let source = generate_config(&chainhook_sdk::types::StacksNetwork::Devnet)
+            .replacen("http://localhost:20443", stacks_node_rpc_url, 1)
+            .replacen(
+                "mnemonic_env = \"PYTH_RELAYER_MNEMONIC\"",
+                &format!(
+                    "signer = \"private-key\"\nprivate_key = \"{}\"",
+                    PRIVATE_KEY
+                ),
+                1,
+            );
+        Config::from_config_file(toml::from_str(&source).unwrap()).unwrap()
     }
-    // Payload
-    let mut payload = vec![];
-    let _ = cursor.read_to_end(&mut payload);
-    // Compute Keccak256 on payload
-
-    let payload_hash: [u8; 32] = {
-        let pass_1: [u8; 32] = Keccak256::digest(payload).try_into().unwrap();
-        Keccak256::digest(pass_1).try_into().unwrap()
-    };
 
-    let message = Message::parse(&payload_hash);
-    let mut public_keys = vec![];
-    for (guardian_id, signature_bytes, recovery_id_byte) in sigs.into_iter() {
-        let signature = Signature::parse_standard(&signature_bytes).expect("invalid signature");
-        let recovery_id = RecoveryId::parse(recovery_id_byte[0]).expect("invalid recovery id");
-        let public_key =
-            recover(&message, &signature, &recovery_id).expect("unable to recover public key");
-        public_keys.push((guardian_id, public_key));
+    fn runloop<'a>(config: &'a Config, ctx: &'a Context) -> BridgeRunloop<'a> {
+        BridgeRunloop {
+            config,
+            accounts: AccountPool::load(&config.stacks, &config.bridge.balance_policy).unwrap(),
+            guardian_set: GuardianSetRegistry::from_builtin()
+                .unwrap()
+                .active()
+                .cloned()
+                .unwrap(),
+            price_updates_stream: None,
+            reference_prices: ReferencePrices::default(),
+            confirmation_tracker: ConfirmationTracker::default(),
+            fee_budget: FeeBudget::new(&config.bridge.fee_strategy),
+            fee_budget_exhausted: false,
+            requeued_price_feeds: HashSet::new(),
+            block_height: 0,
+            ctx,
+        }
     }
 
-    Ok(public_keys)
+    fn transaction(sender: &str, nonce: u64, price_feed_id: &str) -> TrackedTransaction {
+        TrackedTransaction {
+            txid: format!("{:064x}", nonce),
+            sender: sender.into(),
+            nonce,
+            fee: 1_000,
+            price_entries: 1,
+            price_feeds_ids: vec![price_feed_id.into()],
+            block_height: 100,
+            replaced: vec![],
+        }
+    }
+
+    #[test]
+    fn reconcile_microblocks_reorg() {
+        let ctx = Context {
+            logger: Some(hiro_system_kit::log::setup_logger()),
+            tracer: false,
+        };
+        // 4 and 5 were mined in a microblock, then rolled back: 4 got dropped while
+        // 5 is back in the mempool
+        let txid_5 = format!("{:064x}", 5);
+        let url = start_node(&[6, 4], &[&txid_5]);
+        let config = config(&url);
+        let mut runloop = runloop(&config, &ctx);
+        let sender = runloop.accounts.get(0).address.clone();
+        let (transaction_4, transaction_5) = (
+            transaction(&sender, 4, &"aa".repeat(32)),
+            transaction(&sender, 5, &"bb".repeat(32)),
+        );
+        runloop.confirmation_tracker.watch(&transaction_4);
+        runloop.confirmation_tracker.watch(&transaction_5);
+        hiro_system_kit::nestable_block_on(runloop.sync_nonces()).unwrap();
+        assert_eq!(runloop.accounts.get(0).nonce_manager.next_nonce(), Ok(6));
+
+        hiro_system_kit::nestable_block_on(runloop.reconcile_microblocks_reorg(&[
+            format!("0x{}", transaction_4.txid),
+            format!("0x{}", transaction_5.txid),
+            format!("0x{:064x}", 9),
+        ]));
+        // Both pushed again at the next update, the nonce of the dropped one reused
+        assert_eq!(
+            runloop.requeued_price_feeds,
+            HashSet::from(["aa".repeat(32), "bb".repeat(32)])
+        );
+        let nonce_manager = &runloop.accounts.get(0).nonce_manager;
+        assert_eq!(nonce_manager.next_nonce(), Ok(4));
+        assert_eq!(
+            nonce_manager.get(5).map(|pending| pending.txid.clone()),
+            Some(transaction_5.txid.clone())
+        );
+        assert!(runloop
+            .confirmation_tracker
+            .forget(&transaction_4.txid)
+            .is_none());
+        assert!(runloop
+            .confirmation_tracker
+            .forget(&transaction_5.txid)
+            .is_some());
+    }
 }
@@ -0,0 +1,246 @@
+//! Private keys encrypted in a keystore file, following the Web3 Secret Storage
+//! Definition (version 3) used by most secp256k1 wallets.
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use chainhook_sdk::stacks_rpc_client::clarity::codec::StacksTransaction;
+use chainhook_sdk::stacks_rpc_client::clarity::stacks_common::util::secp256k1::{
+    MessageSignature, Secp256k1PrivateKey, Secp256k1PublicKey,
+};
+use sha3::{Digest, Keccak256};
+
+use super::{PrivateKeySigner, TransactionSigner};
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+#[derive(Deserialize, Debug)]
+struct Keystore {
+    version: u64,
+    #[serde(alias = "Crypto")]
+    crypto: KeystoreCrypto,
+}
+
+#[derive(Deserialize, Debug)]
+struct KeystoreCrypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    ciphertext: String,
+    #[serde(flatten)]
+    kdf: Kdf,
+    mac: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "kdf", content = "kdfparams", rename_all = "lowercase")]
+enum Kdf {
+    Scrypt {
+        dklen: usize,
+        n: u64,
+        r: u32,
+        p: u32,
+        salt: String,
+    },
+    Pbkdf2 {
+        dklen: usize,
+        c: u32,
+        prf: String,
+        salt: String,
+    },
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| format!("invalid keystore {}: {}", field, e))
+}
+
+impl Kdf {
+    fn derive_key(&self, password: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Kdf::Scrypt {
+                dklen,
+                n,
+                r,
+                p,
+                salt,
+            } => {
+                if !n.is_power_of_two() || *dklen < 32 {
+                    return Err("invalid keystore scrypt parameters".into());
+                }
+                let params = scrypt::Params::new(n.trailing_zeros() as u8, *r, *p, *dklen)
+                    .map_err(|e| format!("invalid keystore scrypt parameters: {}", e))?;
+                let mut key = vec![0u8; *dklen];
+                scrypt::scrypt(
+                    password.as_bytes(),
+                    &decode_hex("salt", salt)?,
+                    &params,
+                    &mut key,
+                )
+                .map_err(|e| format!("unable to derive keystore key: {}", e))?;
+                Ok(key)
+            }
+            Kdf::Pbkdf2 {
+                dklen,
+                c,
+                prf,
+                salt,
+            } => {
+                if prf != "hmac-sha256" || *dklen < 32 {
+                    return Err("invalid keystore pbkdf2 parameters".into());
+                }
+                let mut key = vec![0u8; *dklen];
+                pbkdf2::pbkdf2_hmac::<sha2::Sha256>(
+                    password.as_bytes(),
+                    &decode_hex("salt", salt)?,
+                    *c,
+                    &mut key,
+                );
+                Ok(key)
+            }
+        }
+    }
+}
+
+/// Signer holding the private key decrypted from a keystore file.
+pub struct KeystoreSigner(PrivateKeySigner);
+
+impl KeystoreSigner {
+    pub fn open(path: &str, password: &str) -> Result<KeystoreSigner, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("unable to read keystore {}: {}", path, e))?;
+        let secret_key = decrypt_keystore(&content, password)
+            .map_err(|e| format!("unable to open keystore {}: {}", path, e))?;
+        Ok(KeystoreSigner(PrivateKeySigner::new(secret_key)))
+    }
+}
+
+/// Decrypt the private key of a keystore, checking the password against its MAC.
+fn decrypt_keystore(content: &str, password: &str) -> Result<Secp256k1PrivateKey, String> {
+    let keystore = serde_json::from_str::<Keystore>(content)
+        .map_err(|e| format!("invalid keystore: {}", e))?;
+    if keystore.version != 3 {
+        return Err(format!(
+            "keystore version {} unsupported (3)",
+            keystore.version
+        ));
+    }
+    let crypto = keystore.crypto;
+    if crypto.cipher != "aes-128-ctr" {
+        return Err(format!(
+            "keystore cipher '{}' unsupported (aes-128-ctr)",
+            crypto.cipher
+        ));
+    }
+    let derived_key = crypto.kdf.derive_key(password)?;
+    let mut ciphertext = decode_hex("ciphertext", &crypto.ciphertext)?;
+
+    let mac = Keccak256::new()
+        .chain_update(&derived_key[16..32])
+        .chain_update(&ciphertext)
+        .finalize();
+    if mac.as_slice() != decode_hex("mac", &crypto.mac)?.as_slice() {
+        return Err("wrong password".into());
+    }
+
+    let iv = decode_hex("iv", &crypto.cipherparams.iv)?;
+    let mut cipher = Aes128Ctr::new_from_slices(&derived_key[..16], &iv)
+        .map_err(|_| "invalid keystore iv".to_string())?;
+    cipher.apply_keystream(&mut ciphertext);
+    Secp256k1PrivateKey::from_slice(&ciphertext).map_err(|e| format!("invalid private key: {}", e))
+}
+
+impl TransactionSigner for KeystoreSigner {
+    fn public_key(&self) -> &Secp256k1PublicKey {
+        self.0.public_key()
+    }
+
+    fn sign(&self, unsigned_transaction: &StacksTransaction) -> Result<MessageSignature, String> {
+        self.0.sign(unsigned_transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test vector of the Web3 Secret Storage Definition, encrypting the private key
+    /// below with the password "testpassword".
+    const PRIVATE_KEY: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+    const PBKDF2_KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": {
+                "iv": "6087dab2f9fdbbfaddc31a909735c1e6"
+            },
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+    /// Same key and password, with the light scrypt parameters of geth: the scrypt
+    /// vector of the definition (n = 2^18, r = 1) is out of the bounds of RFC 7914.
+    const SCRYPT_KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": {
+                "iv": "83dbcc02d8ccb40e466191a123791e0e"
+            },
+            "ciphertext": "584f4eb2783472ec149b334c22d77ddbf118a0783ea24e66ca8a7883cd2b4bf5",
+            "kdf": "scrypt",
+            "kdfparams": {
+                "dklen": 32,
+                "n": 4096,
+                "p": 6,
+                "r": 8,
+                "salt": "ab0c7876052600dd703518d6fc3fe8984592145b591fc8fb5c6d43190334ba19"
+            },
+            "mac": "860ad3ce9642f99d709abc696e25ede7cd35daf6a6041e975d14c53dae118886"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    #[test]
+    fn decrypt_test_vectors() {
+        for keystore in [PBKDF2_KEYSTORE, SCRYPT_KEYSTORE] {
+            let secret_key = decrypt_keystore(keystore, "testpassword").unwrap();
+            assert_eq!(secret_key.to_hex(), PRIVATE_KEY);
+        }
+    }
+
+    #[test]
+    fn wrong_password() {
+        assert_eq!(
+            decrypt_keystore(PBKDF2_KEYSTORE, "wrongpassword").unwrap_err(),
+            "wrong password"
+        );
+    }
+
+    #[test]
+    fn unsupported_keystores() {
+        assert_eq!(
+            decrypt_keystore(&PBKDF2_KEYSTORE.replace("aes-128-ctr", "aes-128-cbc"), "")
+                .unwrap_err(),
+            "keystore cipher 'aes-128-cbc' unsupported (aes-128-ctr)"
+        );
+        assert_eq!(
+            decrypt_keystore(
+                &PBKDF2_KEYSTORE.replace("\"version\": 3", "\"version\": 1"),
+                ""
+            )
+            .unwrap_err(),
+            "keystore version 1 unsupported (3)"
+        );
+    }
+}
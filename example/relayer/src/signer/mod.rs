@@ -0,0 +1,183 @@
+//! Keys signing the relayer transactions, held in-process or by a remote signer.
+
+pub mod keystore;
+pub mod remote;
+
+use chainhook_sdk::stacks_rpc_client::clarity::codec::{
+    SinglesigHashMode, SinglesigSpendingCondition, StacksTransaction, StacksTransactionSigner,
+    TransactionAnchorMode, TransactionAuth, TransactionAuthFlags, TransactionPayload,
+    TransactionPostConditionMode, TransactionPublicKeyEncoding, TransactionSpendingCondition,
+    TransactionVersion, Txid,
+};
+use chainhook_sdk::stacks_rpc_client::clarity::stacks_common::types::chainstate::StacksAddress;
+use chainhook_sdk::stacks_rpc_client::clarity::stacks_common::types::PrivateKey;
+use chainhook_sdk::stacks_rpc_client::clarity::stacks_common::util::secp256k1::{
+    MessageSignature, Secp256k1PrivateKey, Secp256k1PublicKey,
+};
+use chainhook_sdk::stacks_rpc_client::crypto::{compute_keypair, compute_stacks_address, Wallet};
+
+use crate::config::SignerConfig;
+
+use self::keystore::KeystoreSigner;
+use self::remote::RemoteSigner;
+
+/// Signs the transactions of a single-sig account.
+pub trait TransactionSigner: Send {
+    /// Compressed public key of the account.
+    fn public_key(&self) -> &Secp256k1PublicKey;
+
+    /// Recoverable signature of the origin of an unsigned transaction, over the
+    /// sighash computed by [`presign_sighash`].
+    fn sign(&self, unsigned_transaction: &StacksTransaction) -> Result<MessageSignature, String>;
+
+    fn stacks_address(&self, mainnet: bool) -> Result<StacksAddress, String> {
+        let public_key =
+            libsecp256k1::PublicKey::parse_slice(&self.public_key().to_bytes_compressed(), None)
+                .map_err(|e| format!("invalid public key {}: {}", self.public_key().to_hex(), e))?;
+        Ok(compute_stacks_address(&public_key, mainnet))
+    }
+
+    /// Build and sign a transaction, checking that the signature matches the public key.
+    fn sign_transaction_payload(
+        &self,
+        payload: TransactionPayload,
+        nonce: u64,
+        tx_fee: u64,
+        anchor_mode: TransactionAnchorMode,
+        mainnet: bool,
+    ) -> Result<StacksTransaction, String> {
+        let spending_condition =
+            TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
+                signer: self.stacks_address(mainnet)?.bytes,
+                nonce,
+                tx_fee,
+                hash_mode: SinglesigHashMode::P2PKH,
+                key_encoding: TransactionPublicKeyEncoding::Compressed,
+                signature: MessageSignature::empty(),
+            });
+        let mut transaction = StacksTransaction {
+            version: match mainnet {
+                true => TransactionVersion::Mainnet,
+                false => TransactionVersion::Testnet,
+            },
+            chain_id: match mainnet {
+                true => 0x00000001,
+                false => 0x80000000,
+            },
+            auth: TransactionAuth::Standard(spending_condition),
+            anchor_mode,
+            post_condition_mode: TransactionPostConditionMode::Allow,
+            post_conditions: vec![],
+            payload,
+        };
+
+        let signature = self.sign(&transaction)?;
+        let sighash = presign_sighash(&transaction)?;
+        match Secp256k1PublicKey::recover_to_pubkey(sighash.as_bytes(), &signature) {
+            Ok(public_key) if public_key == *self.public_key() => {}
+            _ => return Err("transaction signature doesn't match the public key".into()),
+        }
+        if let TransactionAuth::Standard(TransactionSpendingCondition::Singlesig(
+            ref mut condition,
+        )) = transaction.auth
+        {
+            condition.set_signature(signature);
+        }
+        Ok(transaction)
+    }
+}
+
+/// Sighash signed by the origin of an unsigned single-sig transaction.
+pub fn presign_sighash(unsigned_transaction: &StacksTransaction) -> Result<Txid, String> {
+    let (tx_fee, nonce) = match &unsigned_transaction.auth {
+        TransactionAuth::Standard(condition) => (condition.tx_fee(), condition.nonce()),
+        TransactionAuth::Sponsored(_, _) => {
+            return Err("sponsored transactions are not supported".into())
+        }
+    };
+    let initial_sighash = StacksTransactionSigner::new(unsigned_transaction).sighash;
+    Ok(TransactionSpendingCondition::make_sighash_presign(
+        &initial_sighash,
+        &TransactionAuthFlags::AuthStandard,
+        tx_fee,
+        nonce,
+    ))
+}
+
+/// Signer holding a raw secp256k1 private key.
+pub struct PrivateKeySigner {
+    secret_key: Secp256k1PrivateKey,
+    public_key: Secp256k1PublicKey,
+}
+
+impl PrivateKeySigner {
+    pub fn new(mut secret_key: Secp256k1PrivateKey) -> PrivateKeySigner {
+        secret_key.set_compress_public(true);
+        PrivateKeySigner {
+            public_key: Secp256k1PublicKey::from_private(&secret_key),
+            secret_key,
+        }
+    }
+
+    /// Load a hex encoded private key, with or without the trailing `01` compression flag.
+    pub fn from_hex(private_key: &str) -> Result<PrivateKeySigner, String> {
+        Secp256k1PrivateKey::from_hex(private_key.trim_start_matches("0x"))
+            .map(PrivateKeySigner::new)
+            .map_err(|e| format!("unable to load private key: {}", e))
+    }
+}
+
+impl TransactionSigner for PrivateKeySigner {
+    fn public_key(&self) -> &Secp256k1PublicKey {
+        &self.public_key
+    }
+
+    fn sign(&self, unsigned_transaction: &StacksTransaction) -> Result<MessageSignature, String> {
+        let sighash = presign_sighash(unsigned_transaction)?;
+        self.secret_key
+            .sign(sighash.as_bytes())
+            .map_err(|e| format!("unable to sign transaction: {}", e))
+    }
+}
+
+/// Signer deriving its key from a BIP39 mnemonic.
+pub struct MnemonicSigner(PrivateKeySigner);
+
+impl MnemonicSigner {
+    pub fn new(wallet: &Wallet) -> MnemonicSigner {
+        MnemonicSigner(PrivateKeySigner::new(compute_keypair(wallet).secret_key))
+    }
+}
+
+impl TransactionSigner for MnemonicSigner {
+    fn public_key(&self) -> &Secp256k1PublicKey {
+        self.0.public_key()
+    }
+
+    fn sign(&self, unsigned_transaction: &StacksTransaction) -> Result<MessageSignature, String> {
+        self.0.sign(unsigned_transaction)
+    }
+}
+
+/// Load the signer configured for a relayer account.
+pub fn load_signer(signer_config: &SignerConfig) -> Result<Box<dyn TransactionSigner>, String> {
+    let signer: Box<dyn TransactionSigner> = match signer_config {
+        SignerConfig::Mnemonic {
+            mnemonic,
+            derivation,
+            mainnet,
+        } => Box::new(MnemonicSigner::new(&Wallet {
+            mnemonic: mnemonic.load("mnemonic")?.expose().to_string(),
+            derivation: derivation.clone(),
+            mainnet: *mainnet,
+        })),
+        SignerConfig::PrivateKey(private_key) => {
+            Box::new(PrivateKeySigner::from_hex(private_key.expose())?)
+        }
+        SignerConfig::Keystore { path, password } => {
+            Box::new(KeystoreSigner::open(path, password.expose())?)
+        }
+        SignerConfig::Remote { url } => Box::new(RemoteSigner::connect(url)?),
+    };
+    Ok(signer)
+}
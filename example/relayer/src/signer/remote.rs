@@ -0,0 +1,353 @@
+//! Signer keeping the key out of the relayer process, reached over a JSON-over-HTTP
+//! protocol:
+//!
+//! - `GET /v1/public-key` returns `{"public_key": "<hex>"}`, the compressed public key
+//!   of the account.
+//! - `POST /v1/sign` with `{"transaction": "<hex>"}`, an unsigned single-sig transaction,
+//!   returns `{"signature": "<hex>"}`, the recoverable signature of its origin. The
+//!   signer computes the sighash itself, so that it never signs blindly.
+//!
+//! Failed requests return a non-2xx status with `{"error": "<reason>"}`.
+
+use std::io::{BufRead, BufReader, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use chainhook_sdk::stacks_rpc_client::clarity::codec::StacksTransaction;
+use chainhook_sdk::stacks_rpc_client::clarity::stacks_common::codec::StacksMessageCodec;
+use chainhook_sdk::stacks_rpc_client::clarity::stacks_common::util::secp256k1::{
+    MessageSignature, Secp256k1PublicKey,
+};
+use chainhook_sdk::utils::Context;
+
+use super::TransactionSigner;
+
+/// Largest request body served, a sign request being a few hundred bytes.
+const MAX_REQUEST_BODY_SIZE: usize = 16 * 1024;
+
+/// Requests are served one at a time, a client stalling longer is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PublicKeyResponse {
+    public_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SignRequest {
+    transaction: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SignResponse {
+    signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ErrorResponse {
+    error: String,
+}
+
+pub struct RemoteSigner {
+    url: String,
+    public_key: Secp256k1PublicKey,
+    client: reqwest::blocking::Client,
+}
+
+impl RemoteSigner {
+    /// Retrieve the public key of the account held by the signer at `url`.
+    pub fn connect(url: &str) -> Result<RemoteSigner, String> {
+        let url = url.trim_end_matches('/').to_string();
+        let client = reqwest::blocking::Client::new();
+        let response = client
+            .get(format!("{}/v1/public-key", url))
+            .send()
+            .map_err(|e| format!("unable to reach signer {}: {}", url, e))?;
+        let response = decode_response::<PublicKeyResponse>(&url, response)?;
+        let public_key = Secp256k1PublicKey::from_hex(response.public_key.trim_start_matches("0x"))
+            .map_err(|e| format!("invalid public key from signer {}: {}", url, e))?;
+        let signer = RemoteSigner {
+            url,
+            public_key,
+            client,
+        };
+        signer
+            .stacks_address(true)
+            .map_err(|e| format!("signer {} unusable: {}", signer.url, e))?;
+        Ok(signer)
+    }
+}
+
+fn decode_response<T: serde::de::DeserializeOwned>(
+    url: &str,
+    response: reqwest::blocking::Response,
+) -> Result<T, String> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response
+            .json::<ErrorResponse>()
+            .map(|response| response.error)
+            .unwrap_or_default();
+        return Err(format!("signer {} failed: {} {}", url, status, message));
+    }
+    response
+        .json::<T>()
+        .map_err(|e| format!("unable to decode response of signer {}: {}", url, e))
+}
+
+impl TransactionSigner for RemoteSigner {
+    fn public_key(&self) -> &Secp256k1PublicKey {
+        &self.public_key
+    }
+
+    fn sign(&self, unsigned_transaction: &StacksTransaction) -> Result<MessageSignature, String> {
+        let request = SignRequest {
+            transaction: hex::encode(unsigned_transaction.serialize_to_vec()),
+        };
+        let response = self
+            .client
+            .post(format!("{}/v1/sign", self.url))
+            .json(&request)
+            .send()
+            .map_err(|e| format!("unable to reach signer {}: {}", self.url, e))?;
+        let response = decode_response::<SignResponse>(&self.url, response)?;
+        let signature = hex::decode(response.signature.trim_start_matches("0x"))
+            .ok()
+            .and_then(|bytes| <[u8; 65]>::try_from(bytes).ok())
+            .ok_or(format!("invalid signature from signer {}", self.url))?;
+        Ok(MessageSignature(signature))
+    }
+}
+
+/// Serve the remote signer protocol on `port` with a local signer, standing in for
+/// a remote signer in development and tests. Requests are handled one at a time,
+/// with a bounded body and a timeout.
+pub fn serve(signer: &dyn TransactionSigner, port: u16, ctx: &Context) -> Result<(), String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| format!("unable to listen on port {}: {}", port, e))?;
+    info!(
+        ctx.expect_logger(),
+        "Signer listening on 127.0.0.1:{} (public key: {})",
+        port,
+        signer.public_key().to_hex()
+    );
+    serve_requests(signer, listener, ctx);
+    Ok(())
+}
+
+fn serve_requests(signer: &dyn TransactionSigner, listener: TcpListener, ctx: &Context) {
+    for stream in listener.incoming() {
+        let result = stream
+            .map_err(|e| e.to_string())
+            .and_then(|stream| handle_request(signer, stream));
+        if let Err(e) = result {
+            warn!(
+                ctx.expect_logger(),
+                "Unable to handle signer request: {}", e
+            );
+        }
+    }
+}
+
+fn handle_request(signer: &dyn TransactionSigner, stream: TcpStream) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(REQUEST_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(REQUEST_TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|e| e.to_string())?;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| e.to_string())?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| "invalid content-length")?;
+            }
+        }
+    }
+    if content_length > MAX_REQUEST_BODY_SIZE {
+        let error = format!("body larger than {} bytes", MAX_REQUEST_BODY_SIZE);
+        write_response(
+            &stream,
+            "413 Payload Too Large",
+            serde_json::json!(ErrorResponse {
+                error: error.clone()
+            }),
+        )?;
+        return Err(error);
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+
+    let mut request_line = request_line.split_whitespace();
+    let response = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/v1/public-key")) => Ok(serde_json::json!(PublicKeyResponse {
+            public_key: signer.public_key().to_hex(),
+        })),
+        (Some("POST"), Some("/v1/sign")) => sign_request(signer, &body)
+            .map(|signature| serde_json::json!(SignResponse { signature }))
+            .map_err(|e| ("400 Bad Request", e)),
+        _ => Err(("404 Not Found", "unknown endpoint".to_string())),
+    };
+    match response {
+        Ok(body) => write_response(&stream, "200 OK", body),
+        Err((status, error)) => {
+            write_response(&stream, status, serde_json::json!(ErrorResponse { error }))
+        }
+    }
+}
+
+fn write_response(
+    mut stream: &TcpStream,
+    status: &str,
+    body: serde_json::Value,
+) -> Result<(), String> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn sign_request(signer: &dyn TransactionSigner, body: &[u8]) -> Result<String, String> {
+    let request = serde_json::from_slice::<SignRequest>(body)
+        .map_err(|e| format!("invalid request: {}", e))?;
+    let bytes = hex::decode(request.transaction.trim_start_matches("0x"))
+        .map_err(|e| format!("invalid transaction: {}", e))?;
+    let transaction = StacksTransaction::consensus_deserialize(&mut Cursor::new(&bytes))
+        .map_err(|e| format!("invalid transaction: {}", e))?;
+    signer
+        .sign(&transaction)
+        .map(|signature| hex::encode(signature.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use chainhook_sdk::stacks_rpc_client::clarity::codec::{
+        TransactionAnchorMode, TransactionContractCall, TransactionPayload,
+    };
+    use chainhook_sdk::stacks_rpc_client::clarity::vm::types::{
+        QualifiedContractIdentifier, Value,
+    };
+
+    use super::*;
+    use crate::signer::PrivateKeySigner;
+
+    const PRIVATE_KEY: &str = "753b7cc01a1a2e86221266a154af739463fce51219d97e4f856cd7200c3bd2a601";
+
+    /// Serve a local signer on an ephemeral port, returning its url.
+    fn start_signer() -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let ctx = Context {
+                logger: Some(hiro_system_kit::log::setup_logger()),
+                tracer: false,
+            };
+            let signer = PrivateKeySigner::from_hex(PRIVATE_KEY).unwrap();
+            serve_requests(&signer, listener, &ctx);
+        });
+        url
+    }
+
+    /// Send a raw HTTP request, returning the status line of the response.
+    fn send_request(url: &str, request: &str) -> String {
+        let mut stream = TcpStream::connect(url.trim_start_matches("http://")).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        BufReader::new(&stream).read_line(&mut response).unwrap();
+        response.trim_end().to_string()
+    }
+
+    fn payload() -> TransactionPayload {
+        let contract = QualifiedContractIdentifier::parse(
+            "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.pyth-oracle-v2",
+        )
+        .unwrap();
+        TransactionPayload::ContractCall(TransactionContractCall {
+            address: contract.issuer.into(),
+            contract_name: contract.name,
+            function_name: "update-prices-feeds".into(),
+            function_args: vec![Value::UInt(1)],
+        })
+    }
+
+    #[test]
+    fn sign_remotely() {
+        let url = start_signer();
+        let remote_signer = RemoteSigner::connect(&url).unwrap();
+        let local_signer = PrivateKeySigner::from_hex(PRIVATE_KEY).unwrap();
+        assert_eq!(remote_signer.public_key(), local_signer.public_key());
+        assert_eq!(
+            remote_signer.stacks_address(false).unwrap(),
+            local_signer.stacks_address(false).unwrap()
+        );
+
+        for nonce in [0, 7] {
+            let sign = |signer: &dyn TransactionSigner| {
+                signer
+                    .sign_transaction_payload(
+                        payload(),
+                        nonce,
+                        3_000,
+                        TransactionAnchorMode::Any,
+                        false,
+                    )
+                    .unwrap()
+                    .serialize_to_vec()
+            };
+            assert_eq!(sign(&remote_signer), sign(&local_signer));
+        }
+    }
+
+    #[test]
+    fn reject_malformed_requests() {
+        let url = start_signer();
+        let sign_request = |body: &str| {
+            format!(
+                "POST /v1/sign HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        };
+        for body in [
+            "{",
+            r#"{"tx": "00"}"#,
+            r#"{"transaction": "zz"}"#,
+            r#"{"transaction": "8080000000"}"#,
+        ] {
+            assert_eq!(
+                send_request(&url, &sign_request(body)),
+                "HTTP/1.1 400 Bad Request",
+                "{}",
+                body
+            );
+        }
+        assert_eq!(
+            send_request(&url, "GET /v1/private-key HTTP/1.1\r\n\r\n"),
+            "HTTP/1.1 404 Not Found"
+        );
+        // Rejected before reading the body
+        assert_eq!(
+            send_request(
+                &url,
+                "POST /v1/sign HTTP/1.1\r\nContent-Length: 100000000\r\n\r\n"
+            ),
+            "HTTP/1.1 413 Payload Too Large"
+        );
+        // Still serving once the requests above failed
+        assert!(RemoteSigner::connect(&url).is_ok());
+    }
+}
@@ -0,0 +1,209 @@
+//! Accounts of the relayer, each signing its own chain of price update transactions.
+
+use std::ops::Range;
+
+use crate::config::{BalancePolicy, StacksConfig};
+use crate::signer::{load_signer, TransactionSigner};
+
+use super::balance::{BalanceMonitor, BalanceStatus};
+use super::nonces::NonceManager;
+
+/// Account of the relayer, with its own nonces and spendings.
+pub struct RelayerAccount {
+    pub address: String,
+    pub signer: Box<dyn TransactionSigner>,
+    pub nonce_manager: NonceManager,
+    pub balance_monitor: BalanceMonitor,
+    /// Status of the account at the last balance check.
+    pub balance_status: BalanceStatus,
+}
+
+impl RelayerAccount {
+    /// Whether a transaction of the account has been pending for at least
+    /// `blocks` blocks at `block_height`.
+    pub fn is_stuck(&self, block_height: u64, blocks: u64) -> bool {
+        matches!(
+            self.nonce_manager.pending_since(),
+            Some(since) if since + blocks <= block_height
+        )
+    }
+}
+
+/// Accounts submitting the price updates in rotation.
+pub struct AccountPool {
+    accounts: Vec<RelayerAccount>,
+    /// Index of the account next in rotation.
+    next: usize,
+}
+
+impl AccountPool {
+    /// Load the signers of the accounts configured.
+    pub fn load(
+        stacks_config: &StacksConfig,
+        balance_policy: &BalancePolicy,
+    ) -> Result<AccountPool, String> {
+        let mut accounts: Vec<RelayerAccount> = vec![];
+        for signer_config in stacks_config.signers.iter() {
+            let signer = load_signer(signer_config)?;
+            let address = signer
+                .stacks_address(stacks_config.network.is_mainnet())?
+                .to_string();
+            // Both would hand out the same nonces
+            if accounts.iter().any(|account| account.address == address) {
+                return Err(format!("account {} configured twice", address));
+            }
+            accounts.push(RelayerAccount {
+                nonce_manager: NonceManager::new(address.clone()),
+                balance_monitor: BalanceMonitor::new(balance_policy),
+                balance_status: BalanceStatus::Funded,
+                address,
+                signer,
+            });
+        }
+        if accounts.is_empty() {
+            return Err("no account configured".into());
+        }
+        Ok(AccountPool { accounts, next: 0 })
+    }
+
+    pub fn indices(&self) -> Range<usize> {
+        0..self.accounts.len()
+    }
+
+    pub fn get(&self, index: usize) -> &RelayerAccount {
+        &self.accounts[index]
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> &mut RelayerAccount {
+        &mut self.accounts[index]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RelayerAccount> {
+        self.accounts.iter()
+    }
+
+    pub fn addresses(&self) -> Vec<String> {
+        self.accounts
+            .iter()
+            .map(|account| account.address.clone())
+            .collect()
+    }
+
+    pub fn position(&self, address: &str) -> Option<usize> {
+        self.accounts
+            .iter()
+            .position(|account| account.address == address)
+    }
+
+    /// Index of the account submitting the next price update, rotating across the
+    /// accounts above the balance floor. Accounts with a transaction pending for
+    /// `stuck_after_blocks` blocks are only picked when no other account is left,
+    /// the new transaction then waiting behind the stuck one.
+    pub fn select(&mut self, block_height: u64, stuck_after_blocks: u64) -> Option<usize> {
+        let candidates = (0..self.accounts.len())
+            .map(|offset| (self.next + offset) % self.accounts.len())
+            .filter(|index| self.accounts[*index].balance_status != BalanceStatus::Depleted)
+            .collect::<Vec<_>>();
+        let index = candidates
+            .iter()
+            .find(|index| !self.accounts[**index].is_stuck(block_height, stuck_after_blocks))
+            .or(candidates.first())
+            .cloned()?;
+        self.next = (index + 1) % self.accounts.len();
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::PrivateKeySigner;
+    use crate::stacks::transactions::TrackedTransaction;
+
+    const PRIVATE_KEY: &str = "753b7cc01a1a2e86221266a154af739463fce51219d97e4f856cd7200c3bd2a601";
+    const STUCK_AFTER_BLOCKS: u64 = 10;
+
+    /// Pool of accounts in the given status, stuck if pending since block 0.
+    fn pool(accounts: &[(BalanceStatus, bool)]) -> AccountPool {
+        let balance_policy = BalancePolicy {
+            warning_threshold: None,
+            floor: 0,
+        };
+        let accounts = accounts
+            .iter()
+            .enumerate()
+            .map(|(index, (balance_status, stuck))| {
+                let address = format!("account-{}", index);
+                let mut nonce_manager = NonceManager::new(address.clone());
+                nonce_manager.track(TrackedTransaction {
+                    txid: format!("{:064x}", index),
+                    sender: address.clone(),
+                    nonce: 0,
+                    fee: 1_000,
+                    price_entries: 1,
+                    price_feeds_ids: vec![],
+                    block_height: if *stuck { 0 } else { 100 },
+                    replaced: vec![],
+                });
+                RelayerAccount {
+                    address,
+                    signer: Box::new(PrivateKeySigner::from_hex(PRIVATE_KEY).unwrap()),
+                    nonce_manager,
+                    balance_monitor: BalanceMonitor::new(&balance_policy),
+                    balance_status: *balance_status,
+                }
+            })
+            .collect();
+        AccountPool { accounts, next: 0 }
+    }
+
+    fn select(pool: &mut AccountPool) -> Option<usize> {
+        pool.select(100, STUCK_AFTER_BLOCKS)
+    }
+
+    #[test]
+    fn rotate_skipping_depleted_accounts() {
+        let mut pool = pool(&[
+            (BalanceStatus::Funded, false),
+            (BalanceStatus::Depleted, false),
+            (BalanceStatus::Low, false),
+        ]);
+        assert_eq!(select(&mut pool), Some(0));
+        assert_eq!(select(&mut pool), Some(2));
+        assert_eq!(select(&mut pool), Some(0));
+    }
+
+    #[test]
+    fn prefer_accounts_not_stuck() {
+        let mut pool = pool(&[
+            (BalanceStatus::Funded, true),
+            (BalanceStatus::Funded, false),
+            (BalanceStatus::Funded, true),
+        ]);
+        assert_eq!(select(&mut pool), Some(1));
+        assert_eq!(select(&mut pool), Some(1));
+        assert!(!pool.get(1).is_stuck(100, STUCK_AFTER_BLOCKS));
+        assert!(pool.get(0).is_stuck(100, STUCK_AFTER_BLOCKS));
+    }
+
+    #[test]
+    fn fall_back_to_stuck_accounts() {
+        let mut pool = pool(&[
+            (BalanceStatus::Funded, true),
+            (BalanceStatus::Depleted, false),
+            (BalanceStatus::Funded, true),
+        ]);
+        assert_eq!(select(&mut pool), Some(0));
+        assert_eq!(select(&mut pool), Some(2));
+        assert_eq!(select(&mut pool), Some(0));
+    }
+
+    #[test]
+    fn none_when_all_depleted() {
+        let mut pool = pool(&[
+            (BalanceStatus::Depleted, false),
+            (BalanceStatus::Depleted, true),
+        ]);
+        assert_eq!(select(&mut pool), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! Balance of a relayer account, checked against the thresholds of the balance policy.
+
+use std::collections::VecDeque;
+
+use crate::config::BalancePolicy;
+
+/// Spendings older than this are left out of the runway estimate.
+const SPENDINGS_WINDOW_SECS: u64 = 24 * 3600;
+/// Shortest period the spendings are averaged over, so that a single
+/// transaction doesn't make for an alarming runway.
+const MIN_RUNWAY_PERIOD_SECS: u64 = 3600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BalanceStatus {
+    Funded,
+    /// Below the warning threshold.
+    Low,
+    /// Below the floor, price updates stop.
+    Depleted,
+}
+
+#[derive(Clone, Debug)]
+pub struct BalanceMonitor {
+    warning_threshold: Option<u64>,
+    floor: u64,
+    /// Transaction and oracle fees spent over the last 24 hours, with the unix
+    /// timestamp of their submission.
+    spendings: VecDeque<(u64, u64)>,
+}
+
+impl BalanceMonitor {
+    pub fn new(balance_policy: &BalancePolicy) -> BalanceMonitor {
+        BalanceMonitor {
+            warning_threshold: balance_policy.warning_threshold,
+            floor: balance_policy.floor,
+            spendings: VecDeque::new(),
+        }
+    }
+
+    /// Record an amount spent at `timestamp`, transaction fee and oracle fees included.
+    pub fn record(&mut self, timestamp: u64, amount: u64) {
+        self.spendings.push_back((timestamp, amount));
+    }
+
+    pub fn status(&self, balance: u128) -> BalanceStatus {
+        if balance < self.floor as u128 {
+            BalanceStatus::Depleted
+        } else if matches!(self.warning_threshold, Some(threshold) if balance < threshold as u128) {
+            BalanceStatus::Low
+        } else {
+            BalanceStatus::Funded
+        }
+    }
+
+    /// Seconds until the balance reaches the floor at the recent spending rate,
+    /// or None without recent spendings.
+    pub fn runway(&mut self, balance: u128, timestamp: u64) -> Option<u64> {
+        while let Some((spent_at, _)) = self.spendings.front() {
+            if spent_at + SPENDINGS_WINDOW_SECS > timestamp {
+                break;
+            }
+            self.spendings.pop_front();
+        }
+        let spent = self
+            .spendings
+            .iter()
+            .map(|(_, amount)| *amount as u128)
+            .sum::<u128>();
+        if spent == 0 {
+            return None;
+        }
+        let period = self
+            .spendings
+            .front()
+            .map(|(spent_at, _)| timestamp.saturating_sub(*spent_at))
+            .unwrap_or(0)
+            .max(MIN_RUNWAY_PERIOD_SECS);
+        let runway = balance.saturating_sub(self.floor as u128) * period as u128 / spent;
+        Some(u64::try_from(runway).unwrap_or(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance_monitor(warning_threshold: Option<u64>, floor: u64) -> BalanceMonitor {
+        BalanceMonitor::new(&BalancePolicy {
+            warning_threshold,
+            floor,
+        })
+    }
+
+    #[test]
+    fn status() {
+        let monitor = balance_monitor(Some(100_000), 10_000);
+        assert_eq!(monitor.status(100_000), BalanceStatus::Funded);
+        assert_eq!(monitor.status(99_999), BalanceStatus::Low);
+        assert_eq!(monitor.status(10_000), BalanceStatus::Low);
+        assert_eq!(monitor.status(9_999), BalanceStatus::Depleted);
+        assert_eq!(monitor.status(0), BalanceStatus::Depleted);
+
+        let monitor = balance_monitor(None, 10_000);
+        assert_eq!(monitor.status(10_000), BalanceStatus::Funded);
+        assert_eq!(monitor.status(9_999), BalanceStatus::Depleted);
+
+        let monitor = balance_monitor(None, 0);
+        assert_eq!(monitor.status(0), BalanceStatus::Funded);
+    }
+
+    #[test]
+    fn runway_over_spending_window() {
+        let start = 1_700_000_000;
+        let mut monitor = balance_monitor(None, 10_000);
+        assert_eq!(monitor.runway(1_000_000, start), None);
+
+        // 20_000 spent over 4 hours, 990_000 left above the floor
+        monitor.record(start, 10_000);
+        monitor.record(start + 3600, 10_000);
+        assert_eq!(
+            monitor.runway(1_000_000, start + 4 * 3600),
+            Some(990_000 * 4 * 3600 / 20_000)
+        );
+        // The first spending leaves the window after 24 hours
+        assert_eq!(
+            monitor.runway(1_000_000, start + SPENDINGS_WINDOW_SECS),
+            Some(990_000 * (SPENDINGS_WINDOW_SECS - 3600) / 10_000)
+        );
+        assert_eq!(
+            monitor.runway(1_000_000, start + SPENDINGS_WINDOW_SECS + 3600),
+            None
+        );
+        assert!(monitor.spendings.is_empty());
+    }
+
+    #[test]
+    fn runway_averaged_over_an_hour_at_least() {
+        let start = 1_700_000_000;
+        let mut monitor = balance_monitor(None, 0);
+        monitor.record(start, 1_000);
+        // A single transaction a minute ago spends 1_000 per hour, not per minute
+        assert_eq!(monitor.runway(60_000, start + 60), Some(60 * 3600));
+        assert_eq!(
+            monitor.runway(60_000, start),
+            Some(60 * MIN_RUNWAY_PERIOD_SECS)
+        );
+        assert_eq!(
+            monitor.runway(60_000, start + 2 * 3600),
+            Some(60 * 2 * 3600)
+        );
+    }
+
+    #[test]
+    fn runway_below_floor() {
+        let start = 1_700_000_000;
+        let mut monitor = balance_monitor(Some(100_000), 10_000);
+        monitor.record(start, 1_000);
+        assert_eq!(monitor.runway(10_000, start + 3600), Some(0));
+        assert_eq!(monitor.runway(5_000, start + 3600), Some(0));
+    }
+}
@@ -0,0 +1,63 @@
+//! Fallible accessors over Clarity values returned by read-only calls.
+//! `Value::expect_*` panics on unexpected shapes, which is not an option for
+//! data coming from a remote node.
+
+use chainhook_sdk::stacks_rpc_client::clarity::vm::types::{
+    PrincipalData, QualifiedContractIdentifier, SequenceData, TupleData, Value,
+};
+
+pub fn unwrap_response(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Response(response) if response.committed => Ok(*response.data),
+        Value::Response(response) => Err(format!("call returned an error: {}", response.data)),
+        value => Err(format!("expected a response, got {}", value)),
+    }
+}
+
+pub fn as_tuple(value: &Value) -> Result<&TupleData, String> {
+    match value {
+        Value::Tuple(tuple) => Ok(tuple),
+        value => Err(format!("expected a tuple, got {}", value)),
+    }
+}
+
+pub fn tuple_get<'a>(tuple: &'a TupleData, field: &str) -> Result<&'a Value, String> {
+    tuple
+        .get(field)
+        .map_err(|_| format!("tuple field '{}' missing", field))
+}
+
+pub fn as_int(value: &Value) -> Result<i128, String> {
+    match value {
+        Value::Int(value) => Ok(*value),
+        value => Err(format!("expected an int, got {}", value)),
+    }
+}
+
+pub fn as_uint(value: &Value) -> Result<u128, String> {
+    match value {
+        Value::UInt(value) => Ok(*value),
+        value => Err(format!("expected a uint, got {}", value)),
+    }
+}
+
+pub fn as_buff(value: &Value) -> Result<&[u8], String> {
+    match value {
+        Value::Sequence(SequenceData::Buffer(buff)) => Ok(&buff.data),
+        value => Err(format!("expected a buffer, got {}", value)),
+    }
+}
+
+pub fn as_list(value: &Value) -> Result<&[Value], String> {
+    match value {
+        Value::Sequence(SequenceData::List(list)) => Ok(&list.data),
+        value => Err(format!("expected a list, got {}", value)),
+    }
+}
+
+pub fn as_contract_principal(value: &Value) -> Result<QualifiedContractIdentifier, String> {
+    match value {
+        Value::Principal(PrincipalData::Contract(contract_id)) => Ok(contract_id.clone()),
+        value => Err(format!("expected a contract principal, got {}", value)),
+    }
+}
@@ -0,0 +1,538 @@
+//! Outcome of the relayer transactions, matched against the blocks received from chainhook.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use chainhook_sdk::types::{
+    BlockIdentifier, StacksChainEvent, StacksTransactionData, StacksTransactionMetadata,
+};
+
+use super::transactions::TrackedTransaction;
+
+/// Errors returned by the price update transactions, following the constants of
+/// `pyth-oracle-v2` and `pyth-store-v1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PriceUpdateError {
+    BalanceInsufficient,
+    UnauthorizedPriceUpdate,
+    NewerPriceAvailable,
+    StalePrice,
+    InvalidUpdates,
+    Other(u64),
+}
+
+impl PriceUpdateError {
+    pub fn from_code(code: u64) -> PriceUpdateError {
+        match code {
+            402 => PriceUpdateError::BalanceInsufficient,
+            2401 => PriceUpdateError::UnauthorizedPriceUpdate,
+            5000 => PriceUpdateError::NewerPriceAvailable,
+            5001 => PriceUpdateError::StalePrice,
+            5003 => PriceUpdateError::InvalidUpdates,
+            code => PriceUpdateError::Other(code),
+        }
+    }
+
+    pub fn code(&self) -> u64 {
+        match self {
+            PriceUpdateError::BalanceInsufficient => 402,
+            PriceUpdateError::UnauthorizedPriceUpdate => 2401,
+            PriceUpdateError::NewerPriceAvailable => 5000,
+            PriceUpdateError::StalePrice => 5001,
+            PriceUpdateError::InvalidUpdates => 5003,
+            PriceUpdateError::Other(code) => *code,
+        }
+    }
+}
+
+impl fmt::Display for PriceUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            PriceUpdateError::BalanceInsufficient => "balance insufficient for the update fee",
+            PriceUpdateError::UnauthorizedPriceUpdate => "price update not authorized",
+            PriceUpdateError::NewerPriceAvailable => "newer price available on chain",
+            PriceUpdateError::StalePrice => "stale price",
+            PriceUpdateError::InvalidUpdates => "invalid price updates",
+            PriceUpdateError::Other(_) => "contract error",
+        };
+        write!(f, "{} (u{})", reason, self.code())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    Success,
+    Failure(PriceUpdateError),
+    /// Aborted without a Clarity error code, e.g. a runtime error.
+    Aborted(String),
+}
+
+impl TransactionOutcome {
+    fn from_metadata(metadata: &StacksTransactionMetadata) -> TransactionOutcome {
+        if metadata.success {
+            return TransactionOutcome::Success;
+        }
+        match metadata
+            .result
+            .strip_prefix("(err u")
+            .and_then(|code| code.strip_suffix(')'))
+            .and_then(|code| code.parse::<u64>().ok())
+        {
+            Some(code) => TransactionOutcome::Failure(PriceUpdateError::from_code(code)),
+            None => TransactionOutcome::Aborted(metadata.result.clone()),
+        }
+    }
+}
+
+/// Transaction of the relayer included in a block, or in a microblock confirmed by the block.
+#[derive(Clone, Debug)]
+pub struct MinedTransaction {
+    pub txid: String,
+    pub block: BlockIdentifier,
+    pub outcome: TransactionOutcome,
+}
+
+/// Relayer transactions affected by a chain event received from chainhook.
+#[derive(Clone, Debug, Default)]
+pub struct ChainUpdate {
+    pub rolled_back_blocks: Vec<BlockIdentifier>,
+    pub mined_transactions: Vec<MinedTransaction>,
+    /// Blocks deep enough for chainhook to consider them final.
+    pub confirmed_blocks: Vec<BlockIdentifier>,
+}
+
+impl ChainUpdate {
+    /// Extract the transactions sent by one of the `senders` from a block event. Microblock
+    /// events are left out, their transactions get reported with the block confirming them.
+    pub fn from_chain_event(
+        chain_event: &StacksChainEvent,
+        senders: &HashSet<String>,
+    ) -> Option<ChainUpdate> {
+        let (rolled_back, applied, confirmed) = match chain_event {
+            StacksChainEvent::ChainUpdatedWithBlocks(data) => {
+                (vec![], &data.new_blocks, &data.confirmed_blocks)
+            }
+            StacksChainEvent::ChainUpdatedWithReorg(data) => (
+                data.blocks_to_rollback.iter().collect(),
+                &data.blocks_to_apply,
+                &data.confirmed_blocks,
+            ),
+            StacksChainEvent::ChainUpdatedWithMicroblocks(_)
+            | StacksChainEvent::ChainUpdatedWithMicroblocksReorg(_) => return None,
+        };
+
+        let mut mined_transactions = vec![];
+        for update in applied.iter() {
+            let transactions = update
+                .parent_microblocks_to_apply
+                .iter()
+                .flat_map(|microblock| microblock.transactions.iter())
+                .chain(update.block.transactions.iter());
+            mined_transactions.extend(
+                transactions
+                    .filter(|transaction| senders.contains(&transaction.metadata.sender))
+                    .map(|transaction| {
+                        MinedTransaction::new(transaction, &update.block.block_identifier)
+                    }),
+            );
+        }
+        Some(ChainUpdate {
+            rolled_back_blocks: rolled_back
+                .iter()
+                .map(|update| update.block.block_identifier.clone())
+                .collect(),
+            mined_transactions,
+            confirmed_blocks: confirmed
+                .iter()
+                .map(|block| block.block_identifier.clone())
+                .collect(),
+        })
+    }
+}
+
+impl MinedTransaction {
+    fn new(transaction: &StacksTransactionData, block: &BlockIdentifier) -> MinedTransaction {
+        MinedTransaction {
+            txid: transaction.transaction_identifier.hash.clone(),
+            block: block.clone(),
+            outcome: TransactionOutcome::from_metadata(&transaction.metadata),
+        }
+    }
+}
+
+fn normalize_txid(txid: &str) -> String {
+    txid.trim_start_matches("0x").to_lowercase()
+}
+
+/// Follows the submitted transactions until their block is final. Every txid
+/// submitted for a nonce is watched, since any of them may get mined.
+#[derive(Clone, Debug, Default)]
+pub struct ConfirmationTracker {
+    /// Submitted transactions not mined yet, by txid.
+    pending: HashMap<String, TrackedTransaction>,
+    /// Mined transactions, by txid, until their block gets confirmed.
+    mined: HashMap<String, (TrackedTransaction, MinedTransaction)>,
+}
+
+impl ConfirmationTracker {
+    pub fn watch(&mut self, transaction: &TrackedTransaction) {
+        self.pending
+            .insert(normalize_txid(&transaction.txid), transaction.clone());
+    }
+
+    /// Stop watching a transaction dropped from the mempool.
+    pub fn forget(&mut self, txid: &str) -> Option<TrackedTransaction> {
+        self.pending.remove(&normalize_txid(txid))
+    }
+
+    /// Record the mined transactions, returning the ones submitted by the relayer.
+    /// The other transactions submitted by the same accounts with the same nonces
+    /// are no longer watched.
+    pub fn apply(
+        &mut self,
+        mined_transactions: Vec<MinedTransaction>,
+    ) -> Vec<(TrackedTransaction, MinedTransaction)> {
+        let mut applied = vec![];
+        for mined in mined_transactions {
+            let txid = normalize_txid(&mined.txid);
+            let transaction = match self.pending.remove(&txid) {
+                Some(transaction) => transaction,
+                None => continue,
+            };
+            self.pending.retain(|_, pending| {
+                pending.sender != transaction.sender || pending.nonce != transaction.nonce
+            });
+            self.mined
+                .insert(txid, (transaction.clone(), mined.clone()));
+            applied.push((transaction, mined));
+        }
+        applied
+    }
+
+    /// Put the transactions mined in the rolled back blocks back to pending,
+    /// returning them.
+    pub fn rollback(&mut self, blocks: &[BlockIdentifier]) -> Vec<TrackedTransaction> {
+        let rolled_back_txids = self
+            .mined
+            .iter()
+            .filter(|(_, (_, mined))| blocks.iter().any(|block| block.hash == mined.block.hash))
+            .map(|(txid, _)| txid.clone())
+            .collect::<Vec<_>>();
+        let mut rolled_back = vec![];
+        for txid in rolled_back_txids {
+            if let Some((transaction, _)) = self.mined.remove(&txid) {
+                self.pending.insert(txid, transaction.clone());
+                rolled_back.push(transaction);
+            }
+        }
+        rolled_back
+    }
+
+    /// Stop following the transactions mined in blocks now final.
+    pub fn finalize(&mut self, blocks: &[BlockIdentifier]) {
+        self.mined
+            .retain(|_, (_, mined)| !blocks.iter().any(|block| block.hash == mined.block.hash));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chainhook_sdk::types::{
+        StacksBlockData, StacksBlockUpdate, StacksChainUpdatedWithBlocksData,
+        StacksChainUpdatedWithMicroblocksData, StacksChainUpdatedWithReorgData,
+        StacksMicroblockData,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    const SENDER: &str = "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM";
+    const OTHER_SENDER: &str = "ST2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY1JKX06E";
+
+    fn txid(n: u64) -> String {
+        format!("0x{:064x}", n)
+    }
+
+    fn block(index: u64) -> BlockIdentifier {
+        BlockIdentifier {
+            index,
+            hash: format!("0x{:064x}", index),
+        }
+    }
+
+    fn tracked(n: u64, sender: &str, nonce: u64) -> TrackedTransaction {
+        TrackedTransaction {
+            txid: txid(n),
+            sender: sender.into(),
+            nonce,
+            fee: 1_000,
+            price_entries: 1,
+            price_feeds_ids: vec![],
+            block_height: 100,
+            replaced: vec![],
+        }
+    }
+
+    fn mined(n: u64, index: u64) -> MinedTransaction {
+        MinedTransaction {
+            txid: txid(n),
+            block: block(index),
+            outcome: TransactionOutcome::Success,
+        }
+    }
+
+    fn metadata(success: bool, result: &str) -> StacksTransactionMetadata {
+        transaction_data(0, SENDER, success, result).metadata
+    }
+
+    fn transaction_data(
+        n: u64,
+        sender: &str,
+        success: bool,
+        result: &str,
+    ) -> StacksTransactionData {
+        serde_json::from_value(json!({
+            "transaction_identifier": { "hash": txid(n) },
+            "operations": [],
+            "metadata": {
+                "success": success,
+                "raw_tx": "0x",
+                "result": result,
+                "sender": sender,
+                "nonce": n,
+                "fee": 1_000,
+                "kind": { "type": "Unsupported" },
+                "receipt": {
+                    "mutated_contracts_radius": [],
+                    "mutated_assets_radius": [],
+                    "contract_calls_stack": [],
+                    "events": []
+                },
+                "description": "",
+                "position": { "index": 0 },
+                "proof": null
+            }
+        }))
+        .unwrap()
+    }
+
+    fn block_data(index: u64, transactions: Vec<StacksTransactionData>) -> StacksBlockData {
+        serde_json::from_value(json!({
+            "block_identifier": block(index),
+            "parent_block_identifier": block(index - 1),
+            "timestamp": 0,
+            "transactions": transactions,
+            "metadata": {
+                "bitcoin_anchor_block_identifier": block(index),
+                "pox_cycle_index": 0,
+                "pox_cycle_position": 0,
+                "pox_cycle_length": 0,
+                "confirm_microblock_identifier": null,
+                "stacks_block_hash": block(index).hash
+            }
+        }))
+        .unwrap()
+    }
+
+    fn microblock_data(
+        index: u64,
+        anchor: u64,
+        transactions: Vec<StacksTransactionData>,
+    ) -> StacksMicroblockData {
+        serde_json::from_value(json!({
+            "block_identifier": block(index),
+            "parent_block_identifier": block(index - 1),
+            "timestamp": 0,
+            "transactions": transactions,
+            "metadata": { "anchor_block_identifier": block(anchor) }
+        }))
+        .unwrap()
+    }
+
+    /// Code of the error constant `name` defined in `contract`.
+    fn contract_error_code(contract: &str, name: &str) -> u64 {
+        let definition = format!("(define-constant {} (err u", name);
+        contract
+            .lines()
+            .find_map(|line| line.strip_prefix(&definition))
+            .and_then(|code| code.strip_suffix("))"))
+            .and_then(|code| code.parse().ok())
+            .unwrap_or_else(|| panic!("{} not defined", name))
+    }
+
+    #[test]
+    fn error_codes_match_the_contracts() {
+        let oracle = include_str!("../../../../contracts/pyth-oracle-v2.clar");
+        let decoder = include_str!("../../../../contracts/pyth-pnau-decoder-v1.clar");
+        let store = include_str!("../../../../contracts/pyth-store-v1.clar");
+        for (error, code) in [
+            (
+                PriceUpdateError::BalanceInsufficient,
+                contract_error_code(oracle, "ERR_BALANCE_INSUFFICIENT"),
+            ),
+            (
+                PriceUpdateError::UnauthorizedPriceUpdate,
+                contract_error_code(decoder, "ERR_UNAUTHORIZED_PRICE_UPDATE"),
+            ),
+            (
+                PriceUpdateError::NewerPriceAvailable,
+                contract_error_code(store, "ERR_NEWER_PRICE_AVAILABLE"),
+            ),
+            (
+                PriceUpdateError::StalePrice,
+                contract_error_code(store, "ERR_STALE_PRICE"),
+            ),
+            (
+                PriceUpdateError::InvalidUpdates,
+                contract_error_code(store, "ERR_INVALID_UPDATES"),
+            ),
+            (PriceUpdateError::Other(42), 42),
+        ] {
+            assert_eq!(error.code(), code);
+            assert_eq!(PriceUpdateError::from_code(code), error);
+        }
+    }
+
+    #[test]
+    fn outcome_from_metadata() {
+        assert_eq!(
+            TransactionOutcome::from_metadata(&metadata(true, "(ok true)")),
+            TransactionOutcome::Success
+        );
+        assert_eq!(
+            TransactionOutcome::from_metadata(&metadata(false, "(err u5001)")),
+            TransactionOutcome::Failure(PriceUpdateError::StalePrice)
+        );
+        assert_eq!(
+            TransactionOutcome::from_metadata(&metadata(false, "(err u42)")),
+            TransactionOutcome::Failure(PriceUpdateError::Other(42))
+        );
+        for result in ["(err none)", "(err u-1)", "runtime error: divide by zero"] {
+            assert_eq!(
+                TransactionOutcome::from_metadata(&metadata(false, result)),
+                TransactionOutcome::Aborted(result.into())
+            );
+        }
+    }
+
+    #[test]
+    fn apply_drops_transactions_with_the_same_nonce() {
+        let mut tracker = ConfirmationTracker::default();
+        tracker.watch(&tracked(1, SENDER, 5));
+        tracker.watch(&tracked(2, SENDER, 5));
+        tracker.watch(&tracked(3, SENDER, 6));
+        tracker.watch(&tracked(4, OTHER_SENDER, 5));
+
+        let mut replacement = mined(2, 10);
+        replacement.txid = replacement.txid.to_uppercase().replace("0X", "");
+        let applied = tracker.apply(vec![mined(9, 10), replacement]);
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].0.txid, txid(2));
+
+        assert!(tracker.forget(&txid(1)).is_none());
+        assert!(tracker.forget(&txid(2)).is_none());
+        assert!(tracker.forget(&txid(3)).is_some());
+        assert!(tracker.forget(&txid(4)).is_some());
+    }
+
+    #[test]
+    fn rollback_to_pending() {
+        let mut tracker = ConfirmationTracker::default();
+        tracker.watch(&tracked(1, SENDER, 5));
+        tracker.watch(&tracked(2, SENDER, 6));
+        tracker.apply(vec![mined(1, 10), mined(2, 11)]);
+
+        assert!(tracker.rollback(&[block(12)]).is_empty());
+        let rolled_back = tracker.rollback(&[block(11)]);
+        assert_eq!(rolled_back.len(), 1);
+        assert_eq!(rolled_back[0].txid, txid(2));
+
+        // Mined again by the new fork
+        let applied = tracker.apply(vec![mined(2, 12)]);
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].1.block, block(12));
+        assert!(tracker.forget(&txid(1)).is_none());
+    }
+
+    #[test]
+    fn finalize_prunes_final_blocks() {
+        let mut tracker = ConfirmationTracker::default();
+        tracker.watch(&tracked(1, SENDER, 5));
+        tracker.watch(&tracked(2, SENDER, 6));
+        tracker.apply(vec![mined(1, 10), mined(2, 11)]);
+
+        tracker.finalize(&[block(10)]);
+        let rolled_back = tracker.rollback(&[block(10), block(11)]);
+        assert_eq!(rolled_back.len(), 1);
+        assert_eq!(rolled_back[0].txid, txid(2));
+    }
+
+    #[test]
+    fn chain_update_from_chain_event() {
+        let senders = HashSet::from([SENDER.to_string()]);
+        let mut update = StacksBlockUpdate::new(block_data(
+            11,
+            vec![
+                transaction_data(2, SENDER, false, "(err u5000)"),
+                transaction_data(3, OTHER_SENDER, true, "(ok true)"),
+            ],
+        ));
+        update.parent_microblocks_to_apply = vec![microblock_data(
+            101,
+            10,
+            vec![transaction_data(1, SENDER, true, "(ok true)")],
+        )];
+
+        let chain_event =
+            StacksChainEvent::ChainUpdatedWithBlocks(StacksChainUpdatedWithBlocksData {
+                new_blocks: vec![update.clone()],
+                confirmed_blocks: vec![block_data(5, vec![])],
+            });
+        let chain_update = ChainUpdate::from_chain_event(&chain_event, &senders).unwrap();
+        assert!(chain_update.rolled_back_blocks.is_empty());
+        assert_eq!(chain_update.confirmed_blocks, vec![block(5)]);
+        let mined = chain_update
+            .mined_transactions
+            .iter()
+            .map(|mined| {
+                (
+                    mined.txid.clone(),
+                    mined.block.clone(),
+                    mined.outcome.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        // Microblock transactions are reported with the block confirming them
+        assert_eq!(
+            mined,
+            vec![
+                (txid(1), block(11), TransactionOutcome::Success),
+                (
+                    txid(2),
+                    block(11),
+                    TransactionOutcome::Failure(PriceUpdateError::NewerPriceAvailable)
+                ),
+            ]
+        );
+
+        let chain_event =
+            StacksChainEvent::ChainUpdatedWithReorg(StacksChainUpdatedWithReorgData {
+                blocks_to_rollback: vec![StacksBlockUpdate::new(block_data(11, vec![]))],
+                blocks_to_apply: vec![update],
+                confirmed_blocks: vec![],
+            });
+        let chain_update = ChainUpdate::from_chain_event(&chain_event, &senders).unwrap();
+        assert_eq!(chain_update.rolled_back_blocks, vec![block(11)]);
+        assert_eq!(chain_update.mined_transactions.len(), 2);
+
+        let chain_event =
+            StacksChainEvent::ChainUpdatedWithMicroblocks(StacksChainUpdatedWithMicroblocksData {
+                new_microblocks: vec![microblock_data(
+                    102,
+                    11,
+                    vec![transaction_data(4, SENDER, true, "(ok true)")],
+                )],
+            });
+        assert!(ChainUpdate::from_chain_event(&chain_event, &senders).is_none());
+    }
+}
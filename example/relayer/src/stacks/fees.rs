@@ -0,0 +1,127 @@
+//! Fees spent by the relayer, checked against the budgets of the fee strategy.
+
+use std::collections::VecDeque;
+
+use crate::config::FeeStrategy;
+
+const HOUR_SECS: u64 = 3600;
+const DAY_SECS: u64 = 24 * HOUR_SECS;
+
+#[derive(Clone, Debug)]
+pub struct FeeBudget {
+    hourly_budget: Option<u64>,
+    daily_budget: Option<u64>,
+    /// Fees spent over the last 24 hours, with the unix timestamp of their submission.
+    spendings: VecDeque<(u64, u64)>,
+}
+
+impl FeeBudget {
+    pub fn new(fee_strategy: &FeeStrategy) -> FeeBudget {
+        FeeBudget {
+            hourly_budget: fee_strategy.hourly_budget,
+            daily_budget: fee_strategy.daily_budget,
+            spendings: VecDeque::new(),
+        }
+    }
+
+    /// Record a fee spent at `timestamp`. A replacement only spends the
+    /// difference with the fee of the transaction it replaces.
+    pub fn record(&mut self, timestamp: u64, fee: u64) {
+        self.spendings.push_back((timestamp, fee));
+    }
+
+    /// Fees left to spend at `timestamp` within both budgets, or None without budget.
+    pub fn remaining(&mut self, timestamp: u64) -> Option<u64> {
+        while let Some((spent_at, _)) = self.spendings.front() {
+            if spent_at + DAY_SECS > timestamp {
+                break;
+            }
+            self.spendings.pop_front();
+        }
+        let spent_since = |since: u64| -> u64 {
+            self.spendings
+                .iter()
+                .filter(|(spent_at, _)| *spent_at + since > timestamp)
+                .map(|(_, fee)| fee)
+                .sum()
+        };
+        let hourly_remaining = self
+            .hourly_budget
+            .map(|budget| budget.saturating_sub(spent_since(HOUR_SECS)));
+        let daily_remaining = self
+            .daily_budget
+            .map(|budget| budget.saturating_sub(spent_since(DAY_SECS)));
+        match (hourly_remaining, daily_remaining) {
+            (Some(hourly), Some(daily)) => Some(hourly.min(daily)),
+            (hourly, daily) => hourly.or(daily),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FeeEstimateTier;
+
+    fn fee_budget(hourly_budget: Option<u64>, daily_budget: Option<u64>) -> FeeBudget {
+        FeeBudget::new(&FeeStrategy {
+            estimate_tier: FeeEstimateTier::Medium,
+            floor: 0,
+            ceiling: 1_000_000,
+            hourly_budget,
+            daily_budget,
+            escalation: None,
+        })
+    }
+
+    #[test]
+    fn no_budget() {
+        let mut budget = fee_budget(None, None);
+        budget.record(0, u64::MAX);
+        assert_eq!(budget.remaining(0), None);
+    }
+
+    #[test]
+    fn hourly_window_rolls_over() {
+        let start = 1_700_000_000;
+        let mut budget = fee_budget(Some(10_000), None);
+        budget.record(start, 4_000);
+        budget.record(start + 1_800, 5_000);
+        assert_eq!(budget.remaining(start + 1_800), Some(1_000));
+        // The first spending leaves the window after an hour, the second one after
+        assert_eq!(budget.remaining(start + HOUR_SECS - 1), Some(1_000));
+        assert_eq!(budget.remaining(start + HOUR_SECS), Some(5_000));
+        assert_eq!(budget.remaining(start + HOUR_SECS + 1_800), Some(10_000));
+    }
+
+    #[test]
+    fn daily_window_rolls_over() {
+        let start = 1_700_000_000;
+        let mut budget = fee_budget(Some(10_000), Some(25_000));
+        for hour in 0..3 {
+            budget.record(start + hour * HOUR_SECS, 9_000);
+        }
+        // The hourly budget is back, not the daily one
+        assert_eq!(budget.remaining(start + 3 * HOUR_SECS), Some(0));
+        assert_eq!(budget.remaining(start + DAY_SECS), Some(7_000));
+        assert_eq!(
+            budget.remaining(start + DAY_SECS + 2 * HOUR_SECS),
+            Some(10_000)
+        );
+        assert!(budget.spendings.is_empty());
+    }
+
+    #[test]
+    fn spend_above_budget_refused() {
+        let start = 1_700_000_000;
+        let mut budget = fee_budget(Some(10_000), Some(15_000));
+        budget.record(start, 8_000);
+        // Price updates pause while the fee needed exceeds the remaining budget
+        assert_eq!(budget.remaining(start + 60), Some(2_000));
+        // A replacement may still overspend, the budgets never go below 0
+        budget.record(start + 60, 12_000);
+        assert_eq!(budget.remaining(start + 120), Some(0));
+        assert_eq!(budget.remaining(start + HOUR_SECS + 60), Some(0));
+        assert_eq!(budget.remaining(start + DAY_SECS), Some(3_000));
+    }
+}
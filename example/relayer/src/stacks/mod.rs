@@ -0,0 +1,10 @@
+pub mod accounts;
+pub mod balance;
+pub mod clarity;
+pub mod confirmations;
+pub mod fees;
+pub mod nonces;
+pub mod oracle;
+pub mod rpc;
+pub mod simulation;
+pub mod transactions;
@@ -0,0 +1,329 @@
+//! Nonces of a relayer account, handed out locally and reconciled with the node.
+
+use std::collections::BTreeSet;
+
+use super::rpc::{get_account_nonce, is_transaction_unconfirmed};
+use super::transactions::{TrackedTransaction, TransactionTracker};
+
+/// Rejections of `/v2/transactions` caused by the nonce of the transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NonceRejection {
+    /// Another transaction with the same nonce is waiting in the mempool.
+    ConflictingNonceInMempool,
+    /// The nonce was already used by a mined transaction.
+    BadNonce { expected: Option<u64> },
+}
+
+#[derive(Deserialize, Debug)]
+struct TransactionRejection {
+    reason: String,
+    reason_data: Option<serde_json::Value>,
+}
+
+impl NonceRejection {
+    /// Decode the body of a rejected `/v2/transactions` request, if the nonce was at fault.
+    pub fn parse(message: &str) -> Option<NonceRejection> {
+        let rejection = serde_json::from_str::<TransactionRejection>(message).ok()?;
+        match rejection.reason.as_str() {
+            "ConflictingNonceInMempool" => Some(NonceRejection::ConflictingNonceInMempool),
+            "BadNonce" => Some(NonceRejection::BadNonce {
+                expected: rejection
+                    .reason_data
+                    .as_ref()
+                    .and_then(|data| data.get("expected"))
+                    .and_then(|expected| expected.as_u64()),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Hands out the nonces of the relayer transactions without asking the node for
+/// every submission, keeping track of the transactions still pending.
+#[derive(Clone, Debug)]
+pub struct NonceManager {
+    address: String,
+    /// Next nonce of the account as reported by the node, once synced.
+    account_nonce: Option<u64>,
+    /// Nonces used by transactions unknown to the relayer, e.g. submitted before a restart.
+    foreign_nonces: BTreeSet<u64>,
+    transactions: TransactionTracker,
+}
+
+impl NonceManager {
+    pub fn new(address: String) -> NonceManager {
+        NonceManager {
+            address,
+            account_nonce: None,
+            foreign_nonces: BTreeSet::new(),
+            transactions: TransactionTracker::default(),
+        }
+    }
+
+    /// Reconcile with the account nonce and the mempool of the node. Returns the
+    /// transactions no longer pending: the ones below the account nonce, mined or
+    /// replaced, and the ones evicted from the mempool, whose nonces get handed out again.
+    pub async fn sync(
+        &mut self,
+        stacks_node_rpc_url: &str,
+    ) -> Result<(Vec<TrackedTransaction>, Vec<TrackedTransaction>), String> {
+        let account_nonce = get_account_nonce(stacks_node_rpc_url, &self.address).await?;
+        let mut settled = self.settle(account_nonce);
+
+        let mut missing_nonces = vec![];
+        for transaction in self.transactions.iter() {
+            if !is_transaction_unconfirmed(stacks_node_rpc_url, &transaction.txid).await? {
+                missing_nonces.push(transaction.nonce);
+            }
+        }
+        if missing_nonces.is_empty() {
+            return Ok((settled, vec![]));
+        }
+        // Transactions mined since the account nonce was read left the mempool as
+        // well, only the ones at or above the new account nonce were evicted
+        let account_nonce = get_account_nonce(stacks_node_rpc_url, &self.address).await?;
+        settled.extend(self.settle(account_nonce));
+        let evicted = missing_nonces
+            .into_iter()
+            .filter(|nonce| *nonce >= account_nonce)
+            .filter_map(|nonce| self.transactions.remove(nonce))
+            .collect();
+        Ok((settled, evicted))
+    }
+
+    /// Move to the account nonce read from the node, returning the transactions below it.
+    fn settle(&mut self, account_nonce: u64) -> Vec<TrackedTransaction> {
+        self.account_nonce = Some(account_nonce);
+        self.foreign_nonces = self.foreign_nonces.split_off(&account_nonce);
+        self.transactions.prune(account_nonce)
+    }
+
+    /// Lowest nonce not used by a pending transaction, filling the gaps first.
+    pub fn next_nonce(&self) -> Result<u64, String> {
+        let mut nonce = self
+            .account_nonce
+            .ok_or(format!("nonce of {} not synced", self.address))?;
+        while self.transactions.get(nonce).is_some() || self.foreign_nonces.contains(&nonce) {
+            nonce += 1;
+        }
+        Ok(nonce)
+    }
+
+    /// Track a submitted transaction, returning the one it replaces if any.
+    pub fn track(&mut self, transaction: TrackedTransaction) -> Option<TrackedTransaction> {
+        self.transactions.track(transaction)
+    }
+
+    pub fn get(&self, nonce: u64) -> Option<&TrackedTransaction> {
+        self.transactions.get(nonce)
+    }
+
+    /// Track again a transaction mined in a block since orphaned, or in a microblock
+    /// since rolled back: its nonce is no longer used on chain.
+    pub fn restore(&mut self, transaction: TrackedTransaction) {
+        if let Some(account_nonce) = self.account_nonce {
+            self.account_nonce = Some(account_nonce.min(transaction.nonce));
+        }
+        self.transactions.track(transaction);
+    }
+
+    /// Height of the chain tip when the oldest pending transaction was submitted.
+    pub fn pending_since(&self) -> Option<u64> {
+        self.transactions
+            .iter()
+            .map(|transaction| transaction.block_height)
+            .min()
+    }
+
+    /// Pending transactions submitted at least `blocks` blocks before `block_height`.
+    pub fn stale_transactions(&self, block_height: u64, blocks: u64) -> Vec<TrackedTransaction> {
+        self.transactions
+            .iter()
+            .filter(|transaction| transaction.block_height + blocks <= block_height)
+            .cloned()
+            .collect()
+    }
+
+    /// Adjust the nonces after the node rejected a transaction submitted with `nonce`.
+    pub fn recover(&mut self, nonce: u64, rejection: &NonceRejection) {
+        match rejection {
+            NonceRejection::ConflictingNonceInMempool => {
+                self.foreign_nonces.insert(nonce);
+            }
+            NonceRejection::BadNonce { expected } => {
+                let account_nonce = expected.unwrap_or(nonce + 1);
+                self.account_nonce = Some(account_nonce);
+                self.foreign_nonces = self.foreign_nonces.split_off(&account_nonce);
+                self.transactions.prune(account_nonce);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::VecDeque;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    const ADDRESS: &str = "ST2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY1JKX06E";
+
+    /// Node answering `/v2/accounts` with `account_nonces` in turn, the last one
+    /// repeated, and knowing the `unconfirmed` transactions in its mempool.
+    pub(crate) fn start_node(account_nonces: &[u64], unconfirmed: &[&str]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let account_nonces = Arc::new(Mutex::new(
+            account_nonces.iter().copied().collect::<VecDeque<_>>(),
+        ));
+        let unconfirmed = unconfirmed
+            .iter()
+            .map(|txid| txid.to_string())
+            .collect::<Vec<_>>();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = vec![];
+                let mut buffer = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let request = String::from_utf8(request).unwrap();
+                let path = request.split(' ').nth(1).unwrap();
+                let (status, body) = if path.starts_with("/v2/accounts/") {
+                    let mut account_nonces = account_nonces.lock().unwrap();
+                    let nonce = match account_nonces.len() {
+                        1 => account_nonces[0],
+                        _ => account_nonces.pop_front().unwrap(),
+                    };
+                    (
+                        "200 OK",
+                        format!(r#"{{"balance":"0x0","nonce":{}}}"#, nonce),
+                    )
+                } else if unconfirmed
+                    .iter()
+                    .any(|txid| path == format!("/v2/transactions/unconfirmed/{}", txid))
+                {
+                    ("200 OK", "{}".to_string())
+                } else {
+                    ("404 Not Found", String::new())
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        url
+    }
+
+    fn transaction(nonce: u64) -> TrackedTransaction {
+        TrackedTransaction {
+            txid: format!("{:064x}", nonce),
+            sender: ADDRESS.into(),
+            nonce,
+            fee: 1_000,
+            price_entries: 1,
+            price_feeds_ids: vec![],
+            block_height: 100 + nonce,
+            replaced: vec![],
+        }
+    }
+
+    fn nonces(transactions: &[TrackedTransaction]) -> Vec<u64> {
+        transactions
+            .iter()
+            .map(|transaction| transaction.nonce)
+            .collect()
+    }
+
+    #[test]
+    fn sync_settles_transactions_mined_during_sync() {
+        // 5 gets mined between the two reads of the account nonce, 6 is evicted
+        let url = start_node(&[5, 6], &[]);
+        let mut nonce_manager = NonceManager::new(ADDRESS.into());
+        nonce_manager.track(transaction(5));
+        nonce_manager.track(transaction(6));
+        let (settled, evicted) =
+            hiro_system_kit::nestable_block_on(nonce_manager.sync(&url)).unwrap();
+        assert_eq!(nonces(&settled), vec![5]);
+        assert_eq!(nonces(&evicted), vec![6]);
+        assert_eq!(nonce_manager.next_nonce(), Ok(6));
+    }
+
+    #[test]
+    fn next_nonce_fills_gaps() {
+        let url = start_node(&[5], &[]);
+        let mut nonce_manager = NonceManager::new(ADDRESS.into());
+        assert!(nonce_manager.next_nonce().is_err());
+        hiro_system_kit::nestable_block_on(nonce_manager.sync(&url)).unwrap();
+        assert_eq!(nonce_manager.next_nonce(), Ok(5));
+
+        nonce_manager.track(transaction(5));
+        nonce_manager.track(transaction(7));
+        assert_eq!(nonce_manager.next_nonce(), Ok(6));
+        nonce_manager.recover(6, &NonceRejection::ConflictingNonceInMempool);
+        assert_eq!(nonce_manager.next_nonce(), Ok(8));
+        // A replacement keeps its nonce
+        assert!(nonce_manager.track(transaction(7)).is_some());
+        assert_eq!(nonce_manager.next_nonce(), Ok(8));
+    }
+
+    #[test]
+    fn sync_evicts_transactions_left_the_mempool() {
+        let txid_6 = transaction(6).txid;
+        let url = start_node(&[5], &[&txid_6]);
+        let mut nonce_manager = NonceManager::new(ADDRESS.into());
+        nonce_manager.track(transaction(4));
+        nonce_manager.track(transaction(5));
+        nonce_manager.track(transaction(6));
+        nonce_manager.recover(7, &NonceRejection::ConflictingNonceInMempool);
+        nonce_manager.recover(3, &NonceRejection::ConflictingNonceInMempool);
+        let (settled, evicted) =
+            hiro_system_kit::nestable_block_on(nonce_manager.sync(&url)).unwrap();
+        assert_eq!(nonces(&settled), vec![4]);
+        assert_eq!(nonces(&evicted), vec![5]);
+        // The nonce of the evicted transaction is handed out again first
+        assert_eq!(nonce_manager.next_nonce(), Ok(5));
+        nonce_manager.track(transaction(5));
+        assert_eq!(nonce_manager.next_nonce(), Ok(8));
+        assert_eq!(nonce_manager.pending_since(), Some(105));
+    }
+
+    #[test]
+    fn restore_orphaned_transaction() {
+        let url = start_node(&[7], &[]);
+        let mut nonce_manager = NonceManager::new(ADDRESS.into());
+        hiro_system_kit::nestable_block_on(nonce_manager.sync(&url)).unwrap();
+        nonce_manager.track(transaction(7));
+        // 5 and 6 were mined in an orphaned block, only 5 is known to the relayer
+        nonce_manager.restore(transaction(5));
+        assert_eq!(nonce_manager.next_nonce(), Ok(6));
+        nonce_manager.track(transaction(6));
+        assert_eq!(nonce_manager.next_nonce(), Ok(8));
+        assert_eq!(nonces(&nonce_manager.stale_transactions(107, 2)), vec![5]);
+    }
+
+    #[test]
+    fn recover_bad_nonce() {
+        let url = start_node(&[5], &[]);
+        let mut nonce_manager = NonceManager::new(ADDRESS.into());
+        hiro_system_kit::nestable_block_on(nonce_manager.sync(&url)).unwrap();
+        nonce_manager.track(transaction(5));
+        nonce_manager.track(transaction(6));
+        nonce_manager.recover(5, &NonceRejection::BadNonce { expected: Some(6) });
+        assert_eq!(nonce_manager.next_nonce(), Ok(7));
+        assert_eq!(nonce_manager.pending_since(), Some(106));
+        assert_eq!(
+            NonceRejection::parse(r#"{"reason":"BadNonce","reason_data":{"expected":6}}"#),
+            Some(NonceRejection::BadNonce { expected: Some(6) })
+        );
+    }
+}
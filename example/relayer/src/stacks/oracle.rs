@@ -0,0 +1,655 @@
+//! Price update transactions of `pyth-oracle-v2`, and the prices it holds on chain.
+//!
+//! The on-chain prices are read from the `prices` map of the storage contract through
+//! `/v2/map_entry`: `read-price-feed` of `pyth-oracle-v2` and `read` of `pyth-store-v1`
+//! are `define-public`, and nodes refuse to evaluate public functions through
+//! `/v2/contracts/call-read`.
+
+use chainhook_sdk::stacks_rpc_client::clarity::codec::{
+    TransactionContractCall, TransactionPayload,
+};
+use chainhook_sdk::stacks_rpc_client::clarity::vm::types::{
+    PrincipalData, QualifiedContractIdentifier, TupleData, Value,
+};
+
+use crate::config::{OracleEntrypoint, StacksConfig};
+use crate::pyth::pnau::Pnau;
+use crate::pyth::{normalize_price_feed_id, price_entries_count};
+
+use super::clarity::{as_contract_principal, as_int, as_tuple, as_uint, tuple_get};
+use super::rpc::{call_read_only_fn, get_map_entry};
+
+/// Contracts that `pyth-governance-v1` expects `pyth-oracle-v2` to be called with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionPlan {
+    pub pyth_oracle_contract: QualifiedContractIdentifier,
+    pub pyth_decoder_contract: QualifiedContractIdentifier,
+    pub pyth_storage_contract: QualifiedContractIdentifier,
+    pub wormhole_core_contract: QualifiedContractIdentifier,
+}
+
+impl ExecutionPlan {
+    /// Fetch the plan through `pyth-governance-v1.get-current-execution-plan`.
+    pub async fn fetch(stacks_config: &StacksConfig) -> Result<ExecutionPlan, String> {
+        let governance_contract = &stacks_config.pyth_governance_contract_address;
+        let value = call_read_only_fn(
+            &stacks_config.stacks_node_rpc_url,
+            governance_contract,
+            "get-current-execution-plan",
+            &[],
+            &governance_contract.issuer.to_string(),
+        )
+        .await?;
+        let execution_plan = as_tuple(&value)?;
+        Ok(ExecutionPlan {
+            pyth_oracle_contract: as_contract_principal(tuple_get(
+                execution_plan,
+                "pyth-oracle-contract",
+            )?)?,
+            pyth_decoder_contract: as_contract_principal(tuple_get(
+                execution_plan,
+                "pyth-decoder-contract",
+            )?)?,
+            pyth_storage_contract: as_contract_principal(tuple_get(
+                execution_plan,
+                "pyth-storage-contract",
+            )?)?,
+            wormhole_core_contract: as_contract_principal(tuple_get(
+                execution_plan,
+                "wormhole-core-contract",
+            )?)?,
+        })
+    }
+
+    /// Tuple expected by the `execution-plan` argument of `pyth-oracle-v2`.
+    pub fn to_clarity_value(&self) -> Result<Value, String> {
+        let contract = |contract_id: &QualifiedContractIdentifier| {
+            Value::Principal(PrincipalData::Contract(contract_id.clone()))
+        };
+        let tuple = TupleData::from_data(vec![
+            (
+                "pyth-storage-contract".into(),
+                contract(&self.pyth_storage_contract),
+            ),
+            (
+                "pyth-decoder-contract".into(),
+                contract(&self.pyth_decoder_contract),
+            ),
+            (
+                "wormhole-core-contract".into(),
+                contract(&self.wormhole_core_contract),
+            ),
+        ])
+        .map_err(|e| e.to_string())?;
+        Ok(Value::Tuple(tuple))
+    }
+}
+
+/// Fee charged by `pyth-oracle-v2` for each price updated, set through `pyth-governance-v1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OracleFee {
+    pub mantissa: u128,
+    pub exponent: u128,
+}
+
+impl OracleFee {
+    /// Fetch the fee through `pyth-governance-v1.get-fee-info`.
+    pub async fn fetch(stacks_config: &StacksConfig) -> Result<OracleFee, String> {
+        let governance_contract = &stacks_config.pyth_governance_contract_address;
+        let value = call_read_only_fn(
+            &stacks_config.stacks_node_rpc_url,
+            governance_contract,
+            "get-fee-info",
+            &[],
+            &governance_contract.issuer.to_string(),
+        )
+        .await?;
+        let fee_info = as_tuple(&value)?;
+        Ok(OracleFee {
+            mantissa: as_uint(tuple_get(fee_info, "mantissa")?)?,
+            exponent: as_uint(tuple_get(fee_info, "exponent")?)?,
+        })
+    }
+
+    /// Fee of a single price update, in µSTX, saturating at `u64::MAX`.
+    pub fn per_price_update(&self) -> u64 {
+        u32::try_from(self.exponent)
+            .ok()
+            .and_then(|exponent| 10u128.checked_pow(exponent))
+            .and_then(|scale| self.mantissa.checked_mul(scale))
+            .and_then(|fee| u64::try_from(fee).ok())
+            .unwrap_or(u64::MAX)
+    }
+}
+
+/// Maximum number of entries written by `pyth-store-v1.write` in one call.
+pub const MAX_PRICE_ENTRIES_PER_CALL: usize = 64;
+/// Size of the `price-feed-bytes` argument of `verify-and-update-price-feeds`.
+pub const MAX_PRICE_FEED_BYTES: usize = 8192;
+/// Length of the `(list 4 (buff 2048))` argument of `update-prices-feeds`.
+pub const LEGACY_MAX_VAAS_PER_CALL: usize = 4;
+pub const LEGACY_MAX_VAA_BYTES: usize = 2048;
+
+/// Group the price updates into batches fitting the arguments of the oracle
+/// entrypoint, one batch per contract call. Price updates that can't fit in
+/// any call are left out and reported.
+pub fn batch_price_updates(
+    pyth_oracle_entrypoint: &OracleEntrypoint,
+    price_updates: Vec<Vec<u8>>,
+) -> (Vec<Vec<Vec<u8>>>, Vec<String>) {
+    let mut batches = vec![];
+    let mut rejected = vec![];
+    match pyth_oracle_entrypoint {
+        OracleEntrypoint::VerifyAndUpdatePriceFeeds => {
+            for price_update in price_updates {
+                match Pnau::parse(&price_update) {
+                    Ok(pnau) => {
+                        match pnau
+                            .chunks(MAX_PRICE_FEED_BYTES, MAX_PRICE_ENTRIES_PER_CALL)
+                            .and_then(|chunks| {
+                                chunks
+                                    .iter()
+                                    .map(Pnau::serialize)
+                                    .collect::<Result<Vec<_>, _>>()
+                            }) {
+                            Ok(chunks) => {
+                                batches.extend(chunks.into_iter().map(|chunk| vec![chunk]))
+                            }
+                            Err(e) => rejected.push(e),
+                        }
+                    }
+                    Err(_) if price_update.len() > MAX_PRICE_FEED_BYTES => rejected.push(format!(
+                        "price update of {} bytes exceeds {} bytes",
+                        price_update.len(),
+                        MAX_PRICE_FEED_BYTES
+                    )),
+                    Err(_) => batches.push(vec![price_update]),
+                }
+            }
+        }
+        OracleEntrypoint::LegacyUpdatePricesFeeds => {
+            let mut batch = vec![];
+            let mut batch_entries = 0;
+            for price_update in price_updates {
+                if price_update.len() > LEGACY_MAX_VAA_BYTES {
+                    rejected.push(format!(
+                        "VAA of {} bytes exceeds {} bytes",
+                        price_update.len(),
+                        LEGACY_MAX_VAA_BYTES
+                    ));
+                    continue;
+                }
+                let entries = price_entries_count(&price_update);
+                if batch.len() == LEGACY_MAX_VAAS_PER_CALL
+                    || (!batch.is_empty() && batch_entries + entries > MAX_PRICE_ENTRIES_PER_CALL)
+                {
+                    batches.push(std::mem::take(&mut batch));
+                    batch_entries = 0;
+                }
+                batch.push(price_update);
+                batch_entries += entries;
+            }
+            if !batch.is_empty() {
+                batches.push(batch);
+            }
+        }
+    }
+    (batches, rejected)
+}
+
+/// Build the contract call pushing a batch of price updates.
+///
+/// `verify-and-update-price-feeds` takes a single price update along with the
+/// execution plan, the legacy `update-prices-feeds` entrypoint a list of VAAs.
+pub fn build_price_updates_payload(
+    stacks_config: &StacksConfig,
+    execution_plan: Option<&ExecutionPlan>,
+    batch: &[Vec<u8>],
+) -> Result<TransactionPayload, String> {
+    let contract_call = |function_name: &str, function_args: Vec<Value>| {
+        TransactionPayload::ContractCall(TransactionContractCall {
+            contract_name: stacks_config.pyth_oracle_contract_address.name.clone(),
+            address: stacks_config
+                .pyth_oracle_contract_address
+                .issuer
+                .clone()
+                .into(),
+            function_name: function_name.into(),
+            function_args,
+        })
+    };
+
+    match stacks_config.pyth_oracle_entrypoint {
+        OracleEntrypoint::VerifyAndUpdatePriceFeeds => {
+            let execution_plan =
+                execution_plan.ok_or("execution plan required by verify-and-update-price-feeds")?;
+            if execution_plan.pyth_oracle_contract != stacks_config.pyth_oracle_contract_address {
+                return Err(format!(
+                    "execution plan expects oracle {}, configured oracle is {}",
+                    execution_plan.pyth_oracle_contract, stacks_config.pyth_oracle_contract_address
+                ));
+            }
+            let price_update = match batch {
+                [price_update] => price_update,
+                _ => {
+                    return Err(format!(
+                        "verify-and-update-price-feeds takes one price update, got {}",
+                        batch.len()
+                    ))
+                }
+            };
+            let price_feed_bytes =
+                Value::buff_from(price_update.to_vec()).map_err(|e| e.to_string())?;
+            Ok(contract_call(
+                "verify-and-update-price-feeds",
+                vec![price_feed_bytes, execution_plan.to_clarity_value()?],
+            ))
+        }
+        OracleEntrypoint::LegacyUpdatePricesFeeds => {
+            let encoded_vaas = batch
+                .iter()
+                .map(|vaa| Value::buff_from(vaa.to_vec()))
+                .collect::<Result<Vec<Value>, _>>()
+                .map_err(|e| e.to_string())?;
+            let encoded_vaas_list = Value::list_from(encoded_vaas).map_err(|e| e.to_string())?;
+            Ok(contract_call(
+                "update-prices-feeds",
+                vec![encoded_vaas_list],
+            ))
+        }
+    }
+}
+
+/// Price feed as stored in the `prices` map of `pyth-store-v1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OnChainPrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+    pub publish_time: u64,
+    pub prev_publish_time: u64,
+}
+
+impl OnChainPrice {
+    pub fn from_clarity_value(value: &Value) -> Result<OnChainPrice, String> {
+        let entry = as_tuple(value)?;
+        let int = |field: &str| -> Result<i128, String> { as_int(tuple_get(entry, field)?) };
+        let uint = |field: &str| -> Result<u128, String> { as_uint(tuple_get(entry, field)?) };
+        let out_of_range = |field: &str| format!("{} out of range", field);
+        Ok(OnChainPrice {
+            price: int("price")?
+                .try_into()
+                .map_err(|_| out_of_range("price"))?,
+            conf: uint("conf")?.try_into().map_err(|_| out_of_range("conf"))?,
+            expo: int("expo")?.try_into().map_err(|_| out_of_range("expo"))?,
+            ema_price: int("ema-price")?
+                .try_into()
+                .map_err(|_| out_of_range("ema-price"))?,
+            ema_conf: uint("ema-conf")?
+                .try_into()
+                .map_err(|_| out_of_range("ema-conf"))?,
+            publish_time: uint("publish-time")?
+                .try_into()
+                .map_err(|_| out_of_range("publish-time"))?,
+            prev_publish_time: uint("prev-publish-time")?
+                .try_into()
+                .map_err(|_| out_of_range("prev-publish-time"))?,
+        })
+    }
+
+    /// Read a price feed from the `prices` map of the storage contract, `read`
+    /// being a public function that nodes refuse to evaluate in read-only calls.
+    pub async fn fetch(
+        stacks_node_rpc_url: &str,
+        pyth_storage_contract: &QualifiedContractIdentifier,
+        price_feed_id: &[u8; 32],
+    ) -> Result<Option<OnChainPrice>, String> {
+        let key = Value::buff_from(price_feed_id.to_vec()).map_err(|e| e.to_string())?;
+        get_map_entry(stacks_node_rpc_url, pyth_storage_contract, "prices", &key)
+            .await?
+            .as_ref()
+            .map(OnChainPrice::from_clarity_value)
+            .transpose()
+    }
+}
+
+/// Storage contract holding the prices: the one of the execution plan with
+/// `verify-and-update-price-feeds`, `pyth-store-v1` next to the oracle otherwise.
+pub async fn resolve_storage_contract(
+    stacks_config: &StacksConfig,
+) -> Result<QualifiedContractIdentifier, String> {
+    match stacks_config.pyth_oracle_entrypoint {
+        OracleEntrypoint::VerifyAndUpdatePriceFeeds => ExecutionPlan::fetch(stacks_config)
+            .await
+            .map(|execution_plan| execution_plan.pyth_storage_contract),
+        OracleEntrypoint::LegacyUpdatePricesFeeds => QualifiedContractIdentifier::parse(&format!(
+            "{}.pyth-store-v1",
+            stacks_config.pyth_oracle_contract_address.issuer
+        ))
+        .map_err(|e| e.to_string()),
+    }
+}
+
+/// Read the on-chain price of each of the given price feeds.
+pub async fn fetch_on_chain_prices(
+    stacks_config: &StacksConfig,
+    price_feeds_ids: &[String],
+) -> Result<Vec<(String, Option<OnChainPrice>)>, String> {
+    let pyth_storage_contract = resolve_storage_contract(stacks_config).await?;
+    let mut prices = vec![];
+    for price_feed_id in price_feeds_ids.iter() {
+        let price_identifier = hex::decode(normalize_price_feed_id(price_feed_id))
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or(format!("invalid price feed id {}", price_feed_id))?;
+        let price = OnChainPrice::fetch(
+            &stacks_config.stacks_node_rpc_url,
+            &pyth_storage_contract,
+            &price_identifier,
+        )
+        .await
+        .map_err(|e| format!("unable to read price feed {}: {}", price_feed_id, e))?;
+        prices.push((price_feed_id.clone(), price));
+    }
+    Ok(prices)
+}
+
+#[cfg(test)]
+mod tests {
+    use chainhook_sdk::types::StacksNetwork;
+
+    use super::*;
+    use crate::config::generator::generate_config;
+    use crate::config::Config;
+    use crate::pyth::pnau::tests::PNAU_STX_USD;
+
+    /// Batch price attestation of 5 price feeds, signed by guardian set 0 on testnet,
+    /// as recorded in `unit-tests/pyth/fixtures.ts`.
+    const P2WH_TESTNET_VAA: &str = "01000000000100888fa734558a251d97d73318de190a281dd2913c412e2656fc5c5a4023f5e2e26e8e4b7bf884880e5acc119f207248cdd62f65cca8f422b3f624a707af765ac701648b651c00000000001aa27839d641b07743c0cb5f68c51f8cd31d2c0762bec00dc6fcd25433ef1ab5b600000000052cf1670150325748000300010001020005009d1cdb1a5e1e3456d2977ee0d3d70765239f08a42855b9508fd479e15c6dc4d1feecf553770d9b10965f8fb64771e93f5690a182edc32be4a3236e0caaa6e0581a00000005810dc6000000000001a63b48fffffff80000000577e64610000000000185330c01000000010000000200000000648b651c00000000648b651c00000000648b651a00000005813a13cd000000000179ed7b00000000648b651a6a20671c0e3f8cb219ce3f46e5ae096a4f2fdf936d2bd4da8925f70087d51dd830029479598797290e3638a1712c29bde2367d0eca794f778b25b5a472f192de00000002719137720000000000da2f1ffffffff8000000026c540eac0000000000c2702501000000010000000200000000648b651c00000000648b651c00000000648b651a000000027192eddb0000000000d878b600000000648b651a28fe05d2708c6571182a7c9d1ff457a221b465edf5ea9af1373f9562d16b8d15f9c0172ba10dfa4d19088d94f5bf61d3b54d5bd7483a322a982e1373ee8ea31b0000024f477ea9b1000000005e977a96fffffff80000024b91f168800000000064b5bb4801000000010000000200000000648b651c00000000648b651c00000000648b651a0000024f4dc86f84000000006418cbfc00000000648b651a8b38db700e8b34640e681ec9a73e89608bda29415547a224f96585192b4b9dc794bce4aee88fdfa5b58d81090bd6b3784717fa6df85419d9f04433bb3d615d5c0000000003aaffab000000000001698cfffffff800000000039cca0c00000000000130a901000000010000000200000000648b651c00000000648b651c00000000648b651a0000000003ab5b030000000000013f8e00000000648b651a3b69a3cf075646c5fd8148b705b8107e61a1a253d5d8a84355dcb628b3f1d12031775e1d6897129e8a84eeba975778fb50015b88039e9bc140bbd839694ac0ae00000000005dfcd10000000000001343fffffff800000000005d7613000000000000151301000000010000000200000000648b651c00000000648b651c00000000648b651a00000000005dfd96000000000000170400000000648b651a";
+
+    fn pnau_with_updates(count: usize, with_proofs: bool) -> Pnau {
+        let mut pnau = Pnau::parse(&hex::decode(PNAU_STX_USD).unwrap()).unwrap();
+        let mut update = pnau.updates[0].clone();
+        if !with_proofs {
+            update.proof.clear();
+        }
+        pnau.updates = vec![update; count];
+        pnau
+    }
+
+    fn entries(batches: &[Vec<Vec<u8>>]) -> Vec<usize> {
+        batches
+            .iter()
+            .map(|batch| batch.iter().map(|update| price_entries_count(update)).sum())
+            .collect()
+    }
+
+    fn stacks_config() -> StacksConfig {
+        let source = generate_config(&StacksNetwork::Devnet);
+        Config::from_config_file(toml::from_str(&source).unwrap())
+            .unwrap()
+            .stacks
+    }
+
+    fn execution_plan(stacks_config: &StacksConfig) -> ExecutionPlan {
+        let deployer = &stacks_config.pyth_oracle_contract_address.issuer;
+        let contract =
+            |name: &str| QualifiedContractIdentifier::parse(&format!("{}.{}", deployer, name));
+        ExecutionPlan {
+            pyth_oracle_contract: stacks_config.pyth_oracle_contract_address.clone(),
+            pyth_decoder_contract: contract("pyth-pnau-decoder-v1").unwrap(),
+            pyth_storage_contract: contract("pyth-store-v1").unwrap(),
+            wormhole_core_contract: contract("wormhole-core-v2").unwrap(),
+        }
+    }
+
+    fn contract_call(payload: TransactionPayload) -> TransactionContractCall {
+        match payload {
+            TransactionPayload::ContractCall(contract_call) => contract_call,
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+    }
+
+    #[test]
+    fn execution_plan_tuple() {
+        let execution_plan = execution_plan(&stacks_config());
+        let value = execution_plan.to_clarity_value().unwrap();
+        let tuple = as_tuple(&value).unwrap();
+        assert_eq!(
+            tuple
+                .data_map
+                .keys()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "pyth-decoder-contract",
+                "pyth-storage-contract",
+                "wormhole-core-contract"
+            ]
+        );
+        assert_eq!(
+            as_contract_principal(tuple_get(tuple, "pyth-storage-contract").unwrap()).unwrap(),
+            execution_plan.pyth_storage_contract
+        );
+        assert_eq!(
+            as_contract_principal(tuple_get(tuple, "pyth-decoder-contract").unwrap()).unwrap(),
+            execution_plan.pyth_decoder_contract
+        );
+        assert_eq!(
+            as_contract_principal(tuple_get(tuple, "wormhole-core-contract").unwrap()).unwrap(),
+            execution_plan.wormhole_core_contract
+        );
+    }
+
+    #[test]
+    fn verify_and_update_price_feeds_payload() {
+        let stacks_config = stacks_config();
+        let execution_plan = execution_plan(&stacks_config);
+        let price_update = hex::decode(PNAU_STX_USD).unwrap();
+        let contract_call = contract_call(
+            build_price_updates_payload(
+                &stacks_config,
+                Some(&execution_plan),
+                &[price_update.clone()],
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            contract_call.function_name.as_str(),
+            "verify-and-update-price-feeds"
+        );
+        assert_eq!(
+            contract_call.function_args,
+            vec![
+                Value::buff_from(price_update.clone()).unwrap(),
+                execution_plan.to_clarity_value().unwrap()
+            ]
+        );
+
+        assert_eq!(
+            build_price_updates_payload(
+                &stacks_config,
+                Some(&execution_plan),
+                &[price_update.clone(), price_update.clone()],
+            )
+            .unwrap_err(),
+            "verify-and-update-price-feeds takes one price update, got 2"
+        );
+        assert_eq!(
+            build_price_updates_payload(&stacks_config, None, &[price_update.clone()]).unwrap_err(),
+            "execution plan required by verify-and-update-price-feeds"
+        );
+    }
+
+    #[test]
+    fn reject_execution_plan_of_another_oracle() {
+        let stacks_config = stacks_config();
+        let mut execution_plan = execution_plan(&stacks_config);
+        execution_plan.pyth_oracle_contract = QualifiedContractIdentifier::parse(&format!(
+            "{}.pyth-oracle-v3",
+            stacks_config.pyth_oracle_contract_address.issuer
+        ))
+        .unwrap();
+        assert_eq!(
+            build_price_updates_payload(
+                &stacks_config,
+                Some(&execution_plan),
+                &[hex::decode(PNAU_STX_USD).unwrap()],
+            )
+            .unwrap_err(),
+            format!(
+                "execution plan expects oracle {}, configured oracle is {}",
+                execution_plan.pyth_oracle_contract, stacks_config.pyth_oracle_contract_address
+            )
+        );
+    }
+
+    #[test]
+    fn update_prices_feeds_payload() {
+        let mut stacks_config = stacks_config();
+        stacks_config.pyth_oracle_entrypoint = OracleEntrypoint::LegacyUpdatePricesFeeds;
+        let vaa = hex::decode(P2WH_TESTNET_VAA).unwrap();
+        let contract_call = contract_call(
+            build_price_updates_payload(&stacks_config, None, &[vaa.clone(), vaa.clone()]).unwrap(),
+        );
+        assert_eq!(contract_call.function_name.as_str(), "update-prices-feeds");
+        assert_eq!(
+            contract_call.function_args,
+            vec![Value::list_from(vec![
+                Value::buff_from(vaa.clone()).unwrap(),
+                Value::buff_from(vaa).unwrap()
+            ])
+            .unwrap()]
+        );
+    }
+
+    fn prices_entry(expo: i128) -> Value {
+        Value::Tuple(
+            TupleData::from_data(vec![
+                ("price".into(), Value::Int(46098556)),
+                ("conf".into(), Value::UInt(37359)),
+                ("expo".into(), Value::Int(expo)),
+                ("ema-price".into(), Value::Int(46167004)),
+                ("ema-conf".into(), Value::UInt(36191)),
+                ("publish-time".into(), Value::UInt(1695751649)),
+                ("prev-publish-time".into(), Value::UInt(1695751648)),
+            ])
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn decode_prices_entry() {
+        assert_eq!(
+            OnChainPrice::from_clarity_value(&prices_entry(-8)).unwrap(),
+            OnChainPrice {
+                price: 46098556,
+                conf: 37359,
+                expo: -8,
+                ema_price: 46167004,
+                ema_conf: 36191,
+                publish_time: 1695751649,
+                prev_publish_time: 1695751648,
+            }
+        );
+        assert_eq!(
+            OnChainPrice::from_clarity_value(&prices_entry(i64::MIN as i128)).unwrap_err(),
+            "expo out of range"
+        );
+        let timestamp = Value::Tuple(
+            TupleData::from_data(vec![("publish-time".into(), Value::UInt(1695751649))]).unwrap(),
+        );
+        assert_eq!(
+            OnChainPrice::from_clarity_value(&timestamp).unwrap_err(),
+            "tuple field 'price' missing"
+        );
+    }
+
+    #[test]
+    fn batch_at_most_64_price_entries() {
+        let pnau = pnau_with_updates(100, false);
+        let (batches, rejected) = batch_price_updates(
+            &OracleEntrypoint::VerifyAndUpdatePriceFeeds,
+            vec![pnau.serialize().unwrap()],
+        );
+        assert!(rejected.is_empty());
+        assert_eq!(entries(&batches), vec![64, 36]);
+        assert!(batches.iter().all(|batch| batch.len() == 1));
+    }
+
+    #[test]
+    fn batch_at_most_8192_bytes() {
+        let pnau = pnau_with_updates(100, true);
+        let update_size = pnau_with_updates(2, true).serialize().unwrap().len()
+            - pnau_with_updates(1, true).serialize().unwrap().len();
+        let (batches, rejected) = batch_price_updates(
+            &OracleEntrypoint::VerifyAndUpdatePriceFeeds,
+            vec![pnau.serialize().unwrap()],
+        );
+        assert!(rejected.is_empty());
+        assert!(batches.len() > 1);
+        assert_eq!(entries(&batches).iter().sum::<usize>(), 100);
+        for (i, batch) in batches.iter().enumerate() {
+            assert!(batch[0].len() <= MAX_PRICE_FEED_BYTES);
+            assert!(price_entries_count(&batch[0]) < MAX_PRICE_ENTRIES_PER_CALL);
+            if i < batches.len() - 1 {
+                assert!(batch[0].len() + update_size > MAX_PRICE_FEED_BYTES);
+            }
+        }
+    }
+
+    #[test]
+    fn reject_price_updates_too_large() {
+        let mut pnau = pnau_with_updates(1, true);
+        // Padded VAA and longest proof: the message alone overflows a price update
+        pnau.vaa_bytes.extend_from_slice(&[0; 3000]);
+        pnau.updates[0].proof = vec![[0; 20]; 255];
+        assert!(Pnau::parse(&pnau.serialize().unwrap()).is_ok());
+        let (batches, rejected) = batch_price_updates(
+            &OracleEntrypoint::VerifyAndUpdatePriceFeeds,
+            vec![
+                pnau.serialize().unwrap(),
+                vec![0; MAX_PRICE_FEED_BYTES + 1],
+                vec![0; MAX_PRICE_FEED_BYTES],
+            ],
+        );
+        assert_eq!(batches, vec![vec![vec![0; MAX_PRICE_FEED_BYTES]]]);
+        assert_eq!(
+            rejected,
+            vec![
+                format!(
+                    "price message 0x{} does not fit in 8192 bytes",
+                    hex::encode(pnau.updates[0].message.price_identifier)
+                ),
+                "price update of 8193 bytes exceeds 8192 bytes".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_at_most_4_legacy_vaas() {
+        let vaa = hex::decode(P2WH_TESTNET_VAA).unwrap();
+        assert_eq!(price_entries_count(&vaa), 5);
+        let (batches, rejected) = batch_price_updates(
+            &OracleEntrypoint::LegacyUpdatePricesFeeds,
+            vec![vaa.clone(); 6],
+        );
+        assert!(rejected.is_empty());
+        assert_eq!(batches, vec![vec![vaa.clone(); 4], vec![vaa; 2]]);
+    }
+
+    #[test]
+    fn reject_legacy_vaas_over_2048_bytes() {
+        let (batches, rejected) = batch_price_updates(
+            &OracleEntrypoint::LegacyUpdatePricesFeeds,
+            vec![
+                vec![0; LEGACY_MAX_VAA_BYTES + 1],
+                vec![0; LEGACY_MAX_VAA_BYTES],
+            ],
+        );
+        assert_eq!(batches, vec![vec![vec![0; LEGACY_MAX_VAA_BYTES]]]);
+        assert_eq!(rejected, vec!["VAA of 2049 bytes exceeds 2048 bytes"]);
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::Cursor;
+
+use chainhook_sdk::stacks_rpc_client::clarity::stacks_common::codec::StacksMessageCodec;
+use chainhook_sdk::stacks_rpc_client::clarity::vm::types::{QualifiedContractIdentifier, Value};
+
+#[derive(Serialize, Debug)]
+struct ReadOnlyCallRequest {
+    sender: String,
+    arguments: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReadOnlyCallResponse {
+    okay: bool,
+    result: Option<String>,
+    cause: Option<String>,
+}
+
+/// Evaluate a read-only function through the node's `/v2/contracts/call-read` endpoint.
+pub async fn call_read_only_fn(
+    stacks_node_rpc_url: &str,
+    contract_id: &QualifiedContractIdentifier,
+    method: &str,
+    args: &[Value],
+    sender: &str,
+) -> Result<Value, String> {
+    let url = format!(
+        "{}/v2/contracts/call-read/{}/{}/{}",
+        stacks_node_rpc_url, contract_id.issuer, contract_id.name, method
+    );
+    let request = ReadOnlyCallRequest {
+        sender: sender.to_string(),
+        arguments: args
+            .iter()
+            .map(|arg| format!("0x{}", hex::encode(arg.serialize_to_vec())))
+            .collect(),
+    };
+
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| format!("unable to call {}::{}: {}", contract_id, method, e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "unable to call {}::{}: {} {}",
+            contract_id, method, status, message
+        ));
+    }
+    let response = response.json::<ReadOnlyCallResponse>().await.map_err(|e| {
+        format!(
+            "unable to decode {}::{} response: {}",
+            contract_id, method, e
+        )
+    })?;
+
+    match (response.okay, response.result) {
+        (true, Some(result)) => {
+            let bytes = hex::decode(result.trim_start_matches("0x")).map_err(|e| {
+                format!("unable to decode {}::{} result: {}", contract_id, method, e)
+            })?;
+            Value::consensus_deserialize(&mut Cursor::new(&bytes))
+                .map_err(|e| format!("unable to decode {}::{} result: {}", contract_id, method, e))
+        }
+        _ => Err(format!(
+            "call to {}::{} failed: {}",
+            contract_id,
+            method,
+            response.cause.unwrap_or_default()
+        )),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct MapEntryResponse {
+    data: String,
+}
+
+/// Read a data map entry through the node's `/v2/map_entry` endpoint.
+pub async fn get_map_entry(
+    stacks_node_rpc_url: &str,
+    contract_id: &QualifiedContractIdentifier,
+    map_name: &str,
+    key: &Value,
+) -> Result<Option<Value>, String> {
+    let url = format!(
+        "{}/v2/map_entry/{}/{}/{}?proof=0",
+        stacks_node_rpc_url, contract_id.issuer, contract_id.name, map_name
+    );
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&format!("0x{}", hex::encode(key.serialize_to_vec())))
+        .send()
+        .await
+        .map_err(|e| format!("unable to read {}::{}: {}", contract_id, map_name, e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "unable to read {}::{}: {} {}",
+            contract_id, map_name, status, message
+        ));
+    }
+    let response = response.json::<MapEntryResponse>().await.map_err(|e| {
+        format!(
+            "unable to decode {}::{} entry: {}",
+            contract_id, map_name, e
+        )
+    })?;
+    let bytes = hex::decode(response.data.trim_start_matches("0x")).map_err(|e| {
+        format!(
+            "unable to decode {}::{} entry: {}",
+            contract_id, map_name, e
+        )
+    })?;
+    match Value::consensus_deserialize(&mut Cursor::new(&bytes)) {
+        Ok(Value::Optional(entry)) => Ok(entry.data.map(|data| *data)),
+        Ok(value) => Err(format!(
+            "unexpected {}::{} entry: {}",
+            contract_id, map_name, value
+        )),
+        Err(e) => Err(format!(
+            "unable to decode {}::{} entry: {}",
+            contract_id, map_name, e
+        )),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct AccountResponse {
+    /// Hex encoded balance, in µSTX.
+    balance: String,
+    nonce: u64,
+}
+
+async fn get_account(stacks_node_rpc_url: &str, address: &str) -> Result<AccountResponse, String> {
+    let url = format!("{}/v2/accounts/{}?proof=0", stacks_node_rpc_url, address);
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("unable to retrieve account {}: {}", address, e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "unable to retrieve account {}: {} {}",
+            address, status, message
+        ));
+    }
+    response
+        .json::<AccountResponse>()
+        .await
+        .map_err(|e| format!("unable to decode account {}: {}", address, e))
+}
+
+/// Retrieve the next nonce of an account through the node's `/v2/accounts` endpoint.
+/// Transactions still in the mempool are not accounted for.
+pub async fn get_account_nonce(stacks_node_rpc_url: &str, address: &str) -> Result<u64, String> {
+    get_account(stacks_node_rpc_url, address)
+        .await
+        .map(|account| account.nonce)
+}
+
+/// Retrieve the STX balance of an account, in µSTX, through the node's `/v2/accounts` endpoint.
+pub async fn get_account_balance(stacks_node_rpc_url: &str, address: &str) -> Result<u128, String> {
+    let account = get_account(stacks_node_rpc_url, address).await?;
+    u128::from_str_radix(account.balance.trim_start_matches("0x"), 16).map_err(|e| {
+        format!(
+            "unable to decode balance of {} '{}': {}",
+            address, account.balance, e
+        )
+    })
+}
+
+/// Check through the node's `/v2/transactions/unconfirmed` endpoint whether a transaction
+/// is still waiting in the mempool, or only included in a microblock.
+pub async fn is_transaction_unconfirmed(
+    stacks_node_rpc_url: &str,
+    txid: &str,
+) -> Result<bool, String> {
+    let url = format!(
+        "{}/v2/transactions/unconfirmed/{}",
+        stacks_node_rpc_url,
+        txid.trim_start_matches("0x")
+    );
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("unable to retrieve transaction {}: {}", txid, e))?;
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        reqwest::StatusCode::NOT_FOUND => Ok(false),
+        status => {
+            let message = response.text().await.unwrap_or_default();
+            Err(format!(
+                "unable to retrieve transaction {}: {} {}",
+                txid, status, message
+            ))
+        }
+    }
+}
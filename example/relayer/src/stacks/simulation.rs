@@ -0,0 +1,364 @@
+//! Pre-flight simulation of the price update transactions against the chain tip.
+//!
+//! `/v2/contracts/call-read` refuses to evaluate public functions, and the state of
+//! the contracts is out of reach of a local Clarity VM. The checks of `pyth-oracle-v2`,
+//! its decoders and `pyth-store-v1` are replayed instead, against the state read
+//! through their read-only functions.
+
+use std::collections::HashMap;
+
+use crate::config::{OracleEntrypoint, StacksConfig};
+use crate::pyth::pnau::Pnau;
+use crate::pyth::price_entries_publish_times;
+
+use super::clarity::{as_buff, as_list, as_tuple, as_uint, tuple_get};
+use super::confirmations::PriceUpdateError;
+use super::oracle::{fetch_on_chain_prices, OracleFee};
+use super::rpc::{call_read_only_fn, get_account_balance};
+
+/// Outcome of a price update transaction expected to succeed.
+#[derive(Clone, Debug, Default)]
+pub struct Simulation {
+    /// Price entries written to `pyth-store-v1`.
+    pub written: usize,
+    /// Oracle fees charged for the price entries written, in µSTX.
+    pub oracle_fees: u64,
+    /// Price entries discarded by `pyth-store-v1`, by price feed id.
+    pub discarded: Vec<(String, PriceUpdateError)>,
+}
+
+/// State of the chain tip the price update transactions are checked against.
+#[derive(Clone, Debug)]
+pub struct ChainState {
+    pyth_oracle_entrypoint: OracleEntrypoint,
+    /// Balance of the relayer accounts, in µSTX.
+    balances: HashMap<String, u128>,
+    /// Fee charged for each price entry written, in µSTX.
+    oracle_fee: u64,
+    stale_price_threshold: u64,
+    /// Emitters authorized to publish accumulator updates.
+    prices_data_sources: Vec<(u128, [u8; 32])>,
+    /// Publish time of the prices stored on chain, by price feed id.
+    publish_times: HashMap<[u8; 32], u64>,
+}
+
+impl ChainState {
+    /// Read the state checked by the price updates of `price_feeds_ids`, on behalf of `senders`.
+    pub async fn fetch(
+        stacks_config: &StacksConfig,
+        senders: &[String],
+        price_feeds_ids: &[String],
+    ) -> Result<ChainState, String> {
+        let rpc_url = &stacks_config.stacks_node_rpc_url;
+        let governance_contract = &stacks_config.pyth_governance_contract_address;
+        let governance_issuer = governance_contract.issuer.to_string();
+
+        let mut balances = HashMap::new();
+        for sender in senders.iter() {
+            balances.insert(sender.clone(), get_account_balance(rpc_url, sender).await?);
+        }
+        // update-prices-feeds doesn't charge any fee
+        let oracle_fee = match stacks_config.pyth_oracle_entrypoint {
+            OracleEntrypoint::VerifyAndUpdatePriceFeeds => {
+                OracleFee::fetch(stacks_config).await?.per_price_update()
+            }
+            OracleEntrypoint::LegacyUpdatePricesFeeds => 0,
+        };
+        let value = call_read_only_fn(
+            rpc_url,
+            governance_contract,
+            "get-stale-price-threshold",
+            &[],
+            &governance_issuer,
+        )
+        .await?;
+        let stale_price_threshold = u64::try_from(as_uint(&value)?).unwrap_or(u64::MAX);
+        let value = call_read_only_fn(
+            rpc_url,
+            governance_contract,
+            "get-authorized-prices-data-sources",
+            &[],
+            &governance_issuer,
+        )
+        .await?;
+        let prices_data_sources = as_list(&value)?
+            .iter()
+            .map(|data_source| {
+                let data_source = as_tuple(data_source)?;
+                let emitter_address = as_buff(tuple_get(data_source, "emitter-address")?)?;
+                Ok((
+                    as_uint(tuple_get(data_source, "emitter-chain")?)?,
+                    emitter_address
+                        .try_into()
+                        .map_err(|_| "invalid emitter-address".to_string())?,
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut publish_times = HashMap::new();
+        for (price_feed_id, on_chain_price) in
+            fetch_on_chain_prices(stacks_config, price_feeds_ids).await?
+        {
+            let price_identifier = hex::decode(&price_feed_id)
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok());
+            if let (Some(price_identifier), Some(on_chain_price)) =
+                (price_identifier, on_chain_price)
+            {
+                publish_times.insert(price_identifier, on_chain_price.publish_time);
+            }
+        }
+
+        Ok(ChainState {
+            pyth_oracle_entrypoint: stacks_config.pyth_oracle_entrypoint.clone(),
+            balances,
+            oracle_fee,
+            stale_price_threshold,
+            prices_data_sources,
+            publish_times,
+        })
+    }
+
+    /// Simulate a transaction of `sender` paying `fee` to push a batch of price updates at `timestamp`.
+    /// The effects of a successful transaction are applied, so that the next batches
+    /// get simulated on top of it.
+    ///
+    /// The local clock stands in for the time of the last Bitcoin block, which can only
+    /// make the stale price check stricter.
+    pub fn simulate(
+        &mut self,
+        sender: &str,
+        batch: &[Vec<u8>],
+        fee: u64,
+        timestamp: u64,
+    ) -> Result<Simulation, PriceUpdateError> {
+        let balance = self
+            .balances
+            .get(sender)
+            .cloned()
+            .unwrap_or(0)
+            .checked_sub(fee as u128)
+            .ok_or(PriceUpdateError::BalanceInsufficient)?;
+
+        // pyth-pnau-decoder-v1 only accepts the updates of an authorized emitter, the
+        // legacy VAAs are decoded by pyth-p2wh-decoder-v1
+        if self.pyth_oracle_entrypoint == OracleEntrypoint::VerifyAndUpdatePriceFeeds {
+            for price_update in batch.iter() {
+                let pnau =
+                    Pnau::parse(price_update).map_err(|_| PriceUpdateError::InvalidUpdates)?;
+                let emitter = (
+                    pnau.vaa.body.emitter_chain as u128,
+                    pnau.vaa.body.emitter_address,
+                );
+                if !self.prices_data_sources.contains(&emitter) {
+                    return Err(PriceUpdateError::UnauthorizedPriceUpdate);
+                }
+            }
+        }
+
+        // pyth-store-v1 discards the entries not newer than the stored ones, or stale
+        let mut publish_times = self.publish_times.clone();
+        let mut simulation = Simulation::default();
+        let oldest_publish_time = timestamp.saturating_sub(self.stale_price_threshold);
+        for (price_identifier, publish_time) in batch
+            .iter()
+            .flat_map(|price_update| price_entries_publish_times(price_update))
+        {
+            let stored_publish_time = publish_times.get(&price_identifier).cloned();
+            let discarded = if publish_time <= stored_publish_time.unwrap_or(0) {
+                PriceUpdateError::NewerPriceAvailable
+            } else if publish_time < oldest_publish_time {
+                PriceUpdateError::StalePrice
+            } else {
+                publish_times.insert(price_identifier, publish_time);
+                simulation.written += 1;
+                continue;
+            };
+            simulation
+                .discarded
+                .push((hex::encode(price_identifier), discarded));
+        }
+        if simulation.written == 0 {
+            return Err(PriceUpdateError::InvalidUpdates);
+        }
+
+        // pyth-oracle-v2 charges its fee for each price entry written
+        simulation.oracle_fees = self.oracle_fee.saturating_mul(simulation.written as u64);
+        let balance = balance
+            .checked_sub(simulation.oracle_fees as u128)
+            .ok_or(PriceUpdateError::BalanceInsufficient)?;
+
+        self.balances.insert(sender.to_string(), balance);
+        self.publish_times = publish_times;
+        Ok(simulation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pyth::pnau::tests::PNAU_STX_USD;
+    use crate::pyth::pnau::PriceFeedMessage;
+
+    const SENDER: &str = "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM";
+    const PUBLISH_TIME: u64 = 1695751649;
+    const ORACLE_FEE: u64 = 1;
+    const FEE: u64 = 1_000;
+
+    fn pnau() -> Pnau {
+        Pnau::parse(&hex::decode(PNAU_STX_USD).unwrap()).unwrap()
+    }
+
+    /// Accumulator update of the price feed `[feed; 32]`, published at `publish_time`.
+    fn price_update(feed: u8, publish_time: u64) -> Vec<u8> {
+        let mut pnau = pnau();
+        let update = &mut pnau.updates[0];
+        update.message_bytes[1..33].copy_from_slice(&[feed; 32]);
+        update.message_bytes[53..61].copy_from_slice(&publish_time.to_be_bytes());
+        update.message = PriceFeedMessage::parse(&update.message_bytes).unwrap();
+        pnau.serialize().unwrap()
+    }
+
+    fn chain_state(balance: u128, publish_times: &[(u8, u64)]) -> ChainState {
+        let vaa_body = pnau().vaa.body;
+        ChainState {
+            pyth_oracle_entrypoint: OracleEntrypoint::VerifyAndUpdatePriceFeeds,
+            balances: HashMap::from([(SENDER.to_string(), balance)]),
+            oracle_fee: ORACLE_FEE,
+            stale_price_threshold: 60,
+            prices_data_sources: vec![(vaa_body.emitter_chain as u128, vaa_body.emitter_address)],
+            publish_times: publish_times
+                .iter()
+                .map(|(feed, publish_time)| ([*feed; 32], *publish_time))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn balance_insufficient() {
+        let batch = vec![price_update(1, PUBLISH_TIME), price_update(2, PUBLISH_TIME)];
+        let oracle_fees = 2 * ORACLE_FEE as u128;
+
+        // For the transaction fee
+        let mut state = chain_state(FEE as u128 - 1, &[]);
+        assert_eq!(
+            state
+                .simulate(SENDER, &batch, FEE, PUBLISH_TIME)
+                .unwrap_err(),
+            PriceUpdateError::BalanceInsufficient
+        );
+        // Of an unknown account
+        assert_eq!(
+            state
+                .simulate(
+                    "ST2T5JKWWP3FYYX4YRK8GK5BG2YCNGEAEY1JKX06E",
+                    &batch,
+                    0,
+                    PUBLISH_TIME
+                )
+                .unwrap_err(),
+            PriceUpdateError::BalanceInsufficient
+        );
+        // For the oracle fees on top of it
+        let mut state = chain_state(FEE as u128 + oracle_fees - 1, &[]);
+        assert_eq!(
+            state
+                .simulate(SENDER, &batch, FEE, PUBLISH_TIME)
+                .unwrap_err(),
+            PriceUpdateError::BalanceInsufficient
+        );
+        let mut state = chain_state(FEE as u128 + oracle_fees, &[]);
+        let simulation = state.simulate(SENDER, &batch, FEE, PUBLISH_TIME).unwrap();
+        assert_eq!(simulation.written, 2);
+        assert_eq!(simulation.oracle_fees, 2 * ORACLE_FEE);
+        assert_eq!(state.balances[SENDER], 0);
+    }
+
+    #[test]
+    fn unauthorized_emitter() {
+        let mut state = chain_state(u128::MAX, &[]);
+        state.prices_data_sources[0].1[0] ^= 1;
+        assert_eq!(
+            state
+                .simulate(SENDER, &[price_update(1, PUBLISH_TIME)], FEE, PUBLISH_TIME)
+                .unwrap_err(),
+            PriceUpdateError::UnauthorizedPriceUpdate
+        );
+    }
+
+    #[test]
+    fn invalid_updates() {
+        let mut state = chain_state(u128::MAX, &[]);
+        let mut truncated = price_update(1, PUBLISH_TIME);
+        truncated.pop();
+        assert_eq!(
+            state
+                .simulate(SENDER, &[truncated], FEE, PUBLISH_TIME)
+                .unwrap_err(),
+            PriceUpdateError::InvalidUpdates
+        );
+    }
+
+    #[test]
+    fn discard_entries_newer_on_chain_or_stale() {
+        let mut state = chain_state(u128::MAX, &[(1, PUBLISH_TIME), (2, PUBLISH_TIME - 1)]);
+        let batch = vec![
+            price_update(1, PUBLISH_TIME),
+            price_update(2, PUBLISH_TIME),
+            price_update(3, PUBLISH_TIME - 61),
+        ];
+        let simulation = state.simulate(SENDER, &batch, FEE, PUBLISH_TIME).unwrap();
+        assert_eq!(simulation.written, 1);
+        assert_eq!(simulation.oracle_fees, ORACLE_FEE);
+        assert_eq!(
+            simulation.discarded,
+            vec![
+                (hex::encode([1; 32]), PriceUpdateError::NewerPriceAvailable),
+                (hex::encode([3; 32]), PriceUpdateError::StalePrice),
+            ]
+        );
+
+        // Nothing written
+        let batch = vec![
+            price_update(1, PUBLISH_TIME),
+            price_update(3, PUBLISH_TIME - 61),
+        ];
+        assert_eq!(
+            state
+                .simulate(SENDER, &batch, FEE, PUBLISH_TIME)
+                .unwrap_err(),
+            PriceUpdateError::InvalidUpdates
+        );
+    }
+
+    #[test]
+    fn effects_carried_over_to_the_next_batch() {
+        let balance = 2 * FEE as u128 + ORACLE_FEE as u128;
+        let mut state = chain_state(balance, &[]);
+        let batch = vec![price_update(1, PUBLISH_TIME)];
+        state.simulate(SENDER, &batch, FEE, PUBLISH_TIME).unwrap();
+
+        // The price just written is as new as the update
+        assert_eq!(
+            state
+                .simulate(SENDER, &batch, FEE, PUBLISH_TIME)
+                .unwrap_err(),
+            PriceUpdateError::InvalidUpdates
+        );
+        // The fees just paid leave too little for the next update's oracle fee
+        assert_eq!(
+            state
+                .simulate(
+                    SENDER,
+                    &[price_update(1, PUBLISH_TIME + 1)],
+                    FEE,
+                    PUBLISH_TIME
+                )
+                .unwrap_err(),
+            PriceUpdateError::BalanceInsufficient
+        );
+        // Failed simulations leave the state untouched
+        assert_eq!(state.balances[SENDER], FEE as u128);
+        assert_eq!(state.publish_times[&[1; 32]], PUBLISH_TIME);
+    }
+}
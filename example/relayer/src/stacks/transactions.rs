@@ -0,0 +1,124 @@
+//! Price update transactions submitted by the relayer, keyed by nonce.
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug)]
+pub struct TrackedTransaction {
+    pub txid: String,
+    /// Address of the relayer account which signed the transaction.
+    pub sender: String,
+    pub nonce: u64,
+    pub fee: u64,
+    /// Number of price entries written by the transaction.
+    pub price_entries: usize,
+    /// Normalized ids of the price feeds updated by the transaction.
+    pub price_feeds_ids: Vec<String>,
+    /// Height of the chain tip when the transaction was submitted.
+    pub block_height: u64,
+    /// Transactions previously submitted with the same nonce, oldest first.
+    pub replaced: Vec<ReplacedTransaction>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplacedTransaction {
+    pub txid: String,
+    pub fee: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TransactionTracker {
+    transactions: BTreeMap<u64, TrackedTransaction>,
+}
+
+impl TransactionTracker {
+    /// Track a transaction, returning the one it replaces if any. The replaced
+    /// transaction is added to the replacements history of the new one.
+    pub fn track(&mut self, mut transaction: TrackedTransaction) -> Option<TrackedTransaction> {
+        if let Some(previous) = self.transactions.get(&transaction.nonce) {
+            transaction.replaced = previous.replaced.clone();
+            transaction.replaced.push(ReplacedTransaction {
+                txid: previous.txid.clone(),
+                fee: previous.fee,
+            });
+        }
+        self.transactions.insert(transaction.nonce, transaction)
+    }
+
+    pub fn get(&self, nonce: u64) -> Option<&TrackedTransaction> {
+        self.transactions.get(&nonce)
+    }
+
+    pub fn remove(&mut self, nonce: u64) -> Option<TrackedTransaction> {
+        self.transactions.remove(&nonce)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TrackedTransaction> {
+        self.transactions.values()
+    }
+
+    /// Stop tracking the transactions with a nonce lower than the next nonce of
+    /// the account: they were mined, or replaced by another transaction.
+    pub fn prune(&mut self, next_nonce: u64) -> Vec<TrackedTransaction> {
+        let pending = self.transactions.split_off(&next_nonce);
+        std::mem::replace(&mut self.transactions, pending)
+            .into_values()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(nonce: u64, txid: &str, fee: u64) -> TrackedTransaction {
+        TrackedTransaction {
+            txid: txid.into(),
+            sender: "ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM".into(),
+            nonce,
+            fee,
+            price_entries: 1,
+            price_feeds_ids: vec![],
+            block_height: 100,
+            replaced: vec![],
+        }
+    }
+
+    #[test]
+    fn track_replacements_history() {
+        let mut tracker = TransactionTracker::default();
+        assert!(tracker.track(transaction(3, "a", 1_000)).is_none());
+        assert!(tracker.track(transaction(4, "b", 1_000)).is_none());
+
+        let replaced = tracker.track(transaction(3, "c", 2_000)).unwrap();
+        assert_eq!(replaced.txid, "a");
+        let replaced = tracker.track(transaction(3, "d", 3_000)).unwrap();
+        assert_eq!(replaced.txid, "c");
+
+        let history = tracker
+            .get(3)
+            .unwrap()
+            .replaced
+            .iter()
+            .map(|replaced| (replaced.txid.as_str(), replaced.fee))
+            .collect::<Vec<_>>();
+        assert_eq!(history, vec![("a", 1_000), ("c", 2_000)]);
+        assert!(tracker.get(4).unwrap().replaced.is_empty());
+    }
+
+    #[test]
+    fn prune_settled_transactions() {
+        let mut tracker = TransactionTracker::default();
+        for nonce in 3..6 {
+            tracker.track(transaction(nonce, &nonce.to_string(), 1_000));
+        }
+        let pruned = tracker.prune(5);
+        assert_eq!(
+            pruned.iter().map(|tx| tx.nonce).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert_eq!(
+            tracker.iter().map(|tx| tx.nonce).collect::<Vec<_>>(),
+            vec![5]
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::io::{Cursor, Read};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{thread::sleep, time::Duration};
 
 use chainhook_sdk::utils::Context;
@@ -9,7 +11,7 @@ pub enum ClockCommand {
 }
 
 pub fn start_new_clock(
-    updates_per_min: u64,
+    interval: Duration,
     tenure_cmd_tx: crossbeam_channel::Sender<BridgeTenureCommand>,
     ctx: &Context,
 ) -> crossbeam_channel::Sender<ClockCommand> {
@@ -17,7 +19,7 @@ pub fn start_new_clock(
 
     let _context_cloned = ctx.clone();
     let _ = std::thread::spawn(move || loop {
-        sleep(Duration::from_secs(60 / updates_per_min));
+        sleep(interval);
         if let Ok(ClockCommand::Terminate) = clock_cmd_rx.try_recv() {
             break;
         }
@@ -25,3 +27,17 @@ pub fn start_new_clock(
     });
     clock_cmd_tx
 }
+
+/// Read exactly `N` bytes from the cursor, or return `err` if the buffer is exhausted.
+pub fn read_bytes<const N: usize, E>(cursor: &mut Cursor<&[u8]>, err: E) -> Result<[u8; N], E> {
+    let mut bytes = [0u8; N];
+    cursor.read_exact(&mut bytes).map_err(|_| err)?;
+    Ok(bytes)
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,428 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+
+use chainhook_sdk::stacks_rpc_client::clarity::vm::types::QualifiedContractIdentifier;
+use libsecp256k1::{PublicKey, PublicKeyFormat};
+
+use crate::stacks::clarity::{as_buff, as_list, as_tuple, as_uint, tuple_get, unwrap_response};
+use crate::stacks::rpc::call_read_only_fn;
+use crate::utils::read_bytes;
+
+use super::compute_eth_address;
+use super::vaa::{Vaa, VaaError};
+
+pub const VAA_GUARDIANS_SET_EPOCH_1: &str = "010000000001007ac31b282c2aeeeb37f3385ee0de5f8e421d30b9e5ae8ba3d4375c1c77a86e77159bb697d9c456d6f8c02d22a94b1279b65b0d6a9957e7d3857423845ac758e300610ac1d2000000030001000000000000000000000000000000000000000000000000000000000000000400000000000005390000000000000000000000000000000000000000000000000000000000436f7265020000000000011358cc3ae5c097b213ce3c81979e1b9f9570746aa5ff6cb952589bde862c25ef4392132fb9d4a42157114de8460193bdf3a2fcf81f86a09765f4762fd1107a0086b32d7a0977926a205131d8731d39cbeb8c82b2fd82faed2711d59af0f2499d16e726f6b211b39756c042441be6d8650b69b54ebe715e234354ce5b4d348fb74b958e8966e2ec3dbd4958a7cdeb5f7389fa26941519f0863349c223b73a6ddee774a3bf913953d695260d88bc1aa25a4eee363ef0000ac0076727b35fbea2dac28fee5ccb0fea768eaf45ced136b9d9e24903464ae889f5c8a723fc14f93124b7c738843cbb89e864c862c38cddcccf95d2cc37a4dc036a8d232b48f62cdd4731412f4890da798f6896a3331f64b48c12d1d57fd9cbe7081171aa1be1d36cafe3867910f99c09e347899c19c38192b6e7387ccd768277c17dab1b7a5027c0b3cf178e21ad2e77ae06711549cfbb1f9c7a9d8096e85e1487f35515d02a92753504a8d75471b9f49edb6fbebc898f403e4773e95feb15e80c9a99c8348d";
+pub const VAA_GUARDIANS_SET_EPOCH_2: &str = "01000000010d0012e6b39c6da90c5dfd3c228edbb78c7a4c97c488ff8a346d161a91db067e51d638c17216f368aa9bdf4836b8645a98018ca67d2fec87d769cabfdf2406bf790a0002ef42b288091a670ef3556596f4f47323717882881eaf38e03345078d07a156f312b785b64dae6e9a87e3d32872f59cb1931f728cecf511762981baf48303668f0103cef2616b84c4e511ff03329e0853f1bd7ee9ac5ba71d70a4d76108bddf94f69c2a8a84e4ee94065e8003c334e899184943634e12043d0dda78d93996da073d190104e76d166b9dac98f602107cc4b44ac82868faf00b63df7d24f177aa391e050902413b71046434e67c770b19aecdf7fce1d1435ea0be7262e3e4c18f50ddc8175c0105d9450e8216d741e0206a50f93b750a47e0a258b80eb8fed1314cc300b3d905092de25cd36d366097b7103ae2d184121329ba3aa2d7c6cc53273f11af14798110010687477c8deec89d36a23e7948feb074df95362fc8dcbd8ae910ac556a1dee1e755c56b9db5d710c940938ed79bc1895a3646523a58bc55f475a23435a373ecfdd0107fb06734864f79def4e192497362513171530daea81f07fbb9f698afe7e66c6d44db21323144f2657d4a5386a954bb94eef9f64148c33aef6e477eafa2c5c984c01088769e82216310d1827d9bd48645ec23e90de4ef8a8de99e2d351d1df318608566248d80cdc83bdcac382b3c30c670352be87f9069aab5037d0b747208eae9c650109e9796497ff9106d0d1c62e184d83716282870cef61a1ee13d6fc485b521adcce255c96f7d1bca8d8e7e7d454b65783a830bddc9d94092091a268d311ecd84c26010c468c9fb6d41026841ff9f8d7368fa309d4dbea3ea4bbd2feccf94a92cc8a20a226338a8e2126cd16f70eaf15b4fc9be2c3fa19def14e071956a605e9d1ac4162010e23fcb6bd445b7c25afb722250c1acbc061ed964ba9de1326609ae012acdfb96942b2a102a2de99ab96327859a34a2b49a767dbdb62e0a1fb26af60fe44fd496a00106bb0bac77ac68b347645f2fb1ad789ea9bd76fb9b2324f25ae06f97e65246f142df717f662e73948317182c62ce87d79c73def0dba12e5242dfc038382812cfe00126da03c5e56cb15aeeceadc1e17a45753ab4dc0ec7bf6a75ca03143ed4a294f6f61bc3f478a457833e43084ecd7c985bf2f55a55f168aac0e030fc49e845e497101626e9d9a5d9e343f00010000000000000000000000000000000000000000000000000000000000000004c1759167c43f501c2000000000000000000000000000000000000000000000000000000000436f7265020000000000021358cc3ae5c097b213ce3c81979e1b9f9570746aa5ff6cb952589bde862c25ef4392132fb9d4a42157114de8460193bdf3a2fcf81f86a09765f4762fd1107a0086b32d7a0977926a205131d8731d39cbeb8c82b2fd82faed2711d59af0f2499d16e726f6b211b39756c042441be6d8650b69b54ebe715e234354ce5b4d348fb74b958e8966e2ec3dbd4958a7cd66b9590e1c41e0b226937bf9217d1d67fd4e91f574a3bf913953d695260d88bc1aa25a4eee363ef0000ac0076727b35fbea2dac28fee5ccb0fea768eaf45ced136b9d9e24903464ae889f5c8a723fc14f93124b7c738843cbb89e864c862c38cddcccf95d2cc37a4dc036a8d232b48f62cdd4731412f4890da798f6896a3331f64b48c12d1d57fd9cbe7081171aa1be1d36cafe3867910f99c09e347899c19c38192b6e7387ccd768277c17dab1b7a5027c0b3cf178e21ad2e77ae06711549cfbb1f9c7a9d8096e85e1487f35515d02a92753504a8d75471b9f49edb6fbebc898f403e4773e95feb15e80c9a99c8348d";
+pub const VAA_GUARDIANS_SET_EPOCH_3: &str = "01000000020d00ce45474d9e1b1e7790a2d210871e195db53a70ffd6f237cfe70e2686a32859ac43c84a332267a8ef66f59719cf91cc8df0101fd7c36aa1878d5139241660edc0010375cc906156ae530786661c0cd9aef444747bc3d8d5aa84cac6a6d2933d4e1a031cffa30383d4af8131e929d9f203f460b07309a647d6cd32ab1cc7724089392c000452305156cfc90343128f97e499311b5cae174f488ff22fbc09591991a0a73d8e6af3afb8a5968441d3ab8437836407481739e9850ad5c95e6acfcc871e951bc30105a7956eefc23e7c945a1966d5ddbe9e4be376c2f54e45e3d5da88c2f8692510c7429b1ea860ae94d929bd97e84923a18187e777aa3db419813a80deb84cc8d22b00061b2a4f3d2666608e0aa96737689e3ba5793810ff3a52ff28ad57d8efb20967735dc5537a2e43ef10f583d144c12a1606542c207f5b79af08c38656d3ac40713301086b62c8e130af3411b3c0d91b5b50dcb01ed5f293963f901fc36e7b0e50114dce203373b32eb45971cef8288e5d928d0ed51cd86e2a3006b0af6a65c396c009080009e93ab4d2c8228901a5f4525934000b2c26d1dc679a05e47fdf0ff3231d98fbc207103159ff4116df2832eea69b38275283434e6cd4a4af04d25fa7a82990b707010aa643f4cf615dfff06ffd65830f7f6cf6512dabc3690d5d9e210fdc712842dc2708b8b2c22e224c99280cd25e5e8bfb40e3d1c55b8c41774e287c1e2c352aecfc010b89c1e85faa20a30601964ccc6a79c0ae53cfd26fb10863db37783428cd91390a163346558239db3cd9d420cfe423a0df84c84399790e2e308011b4b63e6b8015010ca31dcb564ac81a053a268d8090e72097f94f366711d0c5d13815af1ec7d47e662e2d1bde22678113d15963da100b668ba26c0c325970d07114b83c5698f46097010dc9fda39c0d592d9ed92cd22b5425cc6b37430e236f02d0d1f8a2ef45a00bde26223c0a6eb363c8b25fd3bf57234a1d9364976cefb8360e755a267cbbb674b39501108db01e444ab1003dd8b6c96f8eb77958b40ba7a85fefecf32ad00b7a47c0ae7524216262495977e09c0989dd50f280c21453d3756843608eacd17f4fdfe47600001261025228ef5af837cb060bcd986fcfa84ccef75b3fa100468cfd24e7fadf99163938f3b841a33496c2706d0208faab088bd155b2e20fd74c625bb1cc8c43677a0163c53c409e0c5dfa000100000000000000000000000000000000000000000000000000000000000000046c5a054d7833d1e42000000000000000000000000000000000000000000000000000000000436f7265020000000000031358cc3ae5c097b213ce3c81979e1b9f9570746aa5ff6cb952589bde862c25ef4392132fb9d4a42157114de8460193bdf3a2fcf81f86a09765f4762fd1107a0086b32d7a0977926a205131d8731d39cbeb8c82b2fd82faed2711d59af0f2499d16e726f6b211b39756c042441be6d8650b69b54ebe715e234354ce5b4d348fb74b958e8966e2ec3dbd4958a7cd15e7caf07c4e3dc8e7c469f92c8cd88fb8005a2074a3bf913953d695260d88bc1aa25a4eee363ef0000ac0076727b35fbea2dac28fee5ccb0fea768eaf45ced136b9d9e24903464ae889f5c8a723fc14f93124b7c738843cbb89e864c862c38cddcccf95d2cc37a4dc036a8d232b48f62cdd4731412f4890da798f6896a3331f64b48c12d1d57fd9cbe7081171aa1be1d36cafe3867910f99c09e347899c19c38192b6e7387ccd768277c17dab1b7a5027c0b3cf178e21ad2e77ae06711549cfbb1f9c7a9d8096e85e1487f35515d02a92753504a8d75471b9f49edb6fbebc898f403e4773e95feb15e80c9a99c8348d";
+
+/// Guardian set upgrades VAAs, in order, bundled with the relayer.
+pub const BUILTIN_GUARDIAN_SET_UPGRADES: [&str; 3] = [
+    VAA_GUARDIANS_SET_EPOCH_1,
+    VAA_GUARDIANS_SET_EPOCH_2,
+    VAA_GUARDIANS_SET_EPOCH_3,
+];
+
+/// A superseded guardian set keeps signing valid VAAs for 24 hours.
+pub const GUARDIAN_SET_EXPIRATION_SECS: u32 = 24 * 60 * 60;
+
+/// Wormhole core governance module ("Core", left padded to 32 bytes).
+pub const CORE_MODULE: [u8; 32] = {
+    let mut module = [0u8; 32];
+    module[28] = b'C';
+    module[29] = b'o';
+    module[30] = b'r';
+    module[31] = b'e';
+    module
+};
+/// Governance action id of a guardian set upgrade.
+pub const GUARDIAN_SET_UPGRADE_ACTION: u8 = 2;
+/// Guardian set upgrades are emitted by the governance contract on Solana.
+pub const GOVERNANCE_EMITTER_CHAIN: u16 = 1;
+pub const GOVERNANCE_EMITTER_ADDRESS: [u8; 32] = {
+    let mut address = [0u8; 32];
+    address[31] = 4;
+    address
+};
+
+/// Errors raised while decoding a guardian set upgrade. Variants follow the
+/// `ERR_GSU_*` constants of `wormhole-core-v2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GuardianSetUpgradeError {
+    ParsingModule,
+    ParsingAction,
+    ParsingChain,
+    ParsingIndex,
+    ParsingGuardianLen,
+    ParsingGuardiansBytes,
+    CheckModule,
+    CheckAction,
+    CheckChain,
+    CheckIndex,
+    CheckEmitter,
+}
+
+impl GuardianSetUpgradeError {
+    /// Error code that `wormhole-core-v2` would return for the same failure.
+    pub fn code(&self) -> u64 {
+        match self {
+            GuardianSetUpgradeError::ParsingModule => 1201,
+            GuardianSetUpgradeError::ParsingAction => 1202,
+            GuardianSetUpgradeError::ParsingChain => 1203,
+            GuardianSetUpgradeError::ParsingIndex => 1204,
+            GuardianSetUpgradeError::ParsingGuardianLen => 1205,
+            GuardianSetUpgradeError::ParsingGuardiansBytes => 1206,
+            GuardianSetUpgradeError::CheckModule => 1301,
+            GuardianSetUpgradeError::CheckAction => 1302,
+            GuardianSetUpgradeError::CheckChain => 1303,
+            GuardianSetUpgradeError::CheckIndex => 1304,
+            GuardianSetUpgradeError::CheckEmitter => 1305,
+        }
+    }
+}
+
+impl fmt::Display for GuardianSetUpgradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            GuardianSetUpgradeError::ParsingModule => "unable to parse module",
+            GuardianSetUpgradeError::ParsingAction => "unable to parse action",
+            GuardianSetUpgradeError::ParsingChain => "unable to parse chain",
+            GuardianSetUpgradeError::ParsingIndex => "unable to parse new guardian set index",
+            GuardianSetUpgradeError::ParsingGuardianLen => "unable to parse guardians count",
+            GuardianSetUpgradeError::ParsingGuardiansBytes => "unable to parse guardians",
+            GuardianSetUpgradeError::CheckModule => "module is not Core",
+            GuardianSetUpgradeError::CheckAction => "action is not a guardian set upgrade",
+            GuardianSetUpgradeError::CheckChain => "upgrade is not targeting all chains",
+            GuardianSetUpgradeError::CheckIndex => "new index is not greater than current index",
+            GuardianSetUpgradeError::CheckEmitter => "emitter is not the governance contract",
+        };
+        write!(f, "{} (u{})", reason, self.code())
+    }
+}
+
+/// Set of guardians allowed to sign VAAs, identified by their Ethereum-style
+/// addresses, in the order used by the guardian indices of signatures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuardianSet {
+    pub index: u32,
+    pub addresses: Vec<[u8; 20]>,
+    /// Unix timestamp after which the set can no longer sign, once superseded.
+    pub expiration_time: Option<u32>,
+}
+
+impl GuardianSet {
+    /// Minimum number of valid signatures required (2/3 + 1).
+    pub fn quorum(&self) -> usize {
+        self.addresses.len() * 2 / 3 + 1
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        match self.expiration_time {
+            Some(expiration_time) => now > expiration_time as u64,
+            None => false,
+        }
+    }
+}
+
+/// Payload of a Wormhole core governance "guardian set upgrade" VAA
+///
+/// [32]byte    module              ("Core", left padded)
+/// u8          action              (2: guardian set upgrade)
+/// u16         chain               (0: all chains)
+/// u32         new_index           (Index of the new guardian set)
+/// u8          len_guardians       (Number of guardians)
+/// [][20]byte  guardians           (Guardians Ethereum-style addresses)
+#[derive(Clone, Debug)]
+pub struct GuardianSetUpgrade {
+    pub guardian_set: GuardianSet,
+}
+
+impl GuardianSetUpgrade {
+    pub fn parse(payload: &[u8]) -> Result<GuardianSetUpgrade, GuardianSetUpgradeError> {
+        let mut cursor = Cursor::new(payload);
+        let module: [u8; 32] = read_bytes(&mut cursor, GuardianSetUpgradeError::ParsingModule)?;
+        let [action] = read_bytes(&mut cursor, GuardianSetUpgradeError::ParsingAction)?;
+        let chain = u16::from_be_bytes(read_bytes(
+            &mut cursor,
+            GuardianSetUpgradeError::ParsingChain,
+        )?);
+        let index = u32::from_be_bytes(read_bytes(
+            &mut cursor,
+            GuardianSetUpgradeError::ParsingIndex,
+        )?);
+        let [guardians_len] = read_bytes(&mut cursor, GuardianSetUpgradeError::ParsingGuardianLen)?;
+        let mut addresses = Vec::with_capacity(guardians_len as usize);
+        for _ in 0..guardians_len {
+            addresses.push(read_bytes(
+                &mut cursor,
+                GuardianSetUpgradeError::ParsingGuardiansBytes,
+            )?);
+        }
+
+        if module != CORE_MODULE {
+            return Err(GuardianSetUpgradeError::CheckModule);
+        }
+        if action != GUARDIAN_SET_UPGRADE_ACTION {
+            return Err(GuardianSetUpgradeError::CheckAction);
+        }
+        if chain != 0 {
+            return Err(GuardianSetUpgradeError::CheckChain);
+        }
+
+        Ok(GuardianSetUpgrade {
+            guardian_set: GuardianSet {
+                index,
+                addresses,
+                expiration_time: None,
+            },
+        })
+    }
+
+    /// Decode the guardian set upgrade carried by a governance VAA.
+    pub fn from_vaa(vaa: &Vaa) -> Result<GuardianSetUpgrade, GuardianSetUpgradeError> {
+        if vaa.body.emitter_chain != GOVERNANCE_EMITTER_CHAIN
+            || vaa.body.emitter_address != GOVERNANCE_EMITTER_ADDRESS
+        {
+            return Err(GuardianSetUpgradeError::CheckEmitter);
+        }
+        GuardianSetUpgrade::parse(&vaa.body.payload)
+    }
+}
+
+/// Guardian sets known by the relayer, indexed by guardian set index.
+#[derive(Clone, Debug, Default)]
+pub struct GuardianSetRegistry {
+    guardian_sets: BTreeMap<u32, GuardianSet>,
+}
+
+impl GuardianSetRegistry {
+    pub fn new() -> GuardianSetRegistry {
+        GuardianSetRegistry::default()
+    }
+
+    /// Replay the guardian set upgrades bundled with the relayer.
+    pub fn from_builtin() -> Result<GuardianSetRegistry, String> {
+        GuardianSetRegistry::from_upgrade_vaas(BUILTIN_GUARDIAN_SET_UPGRADES.iter().copied())
+    }
+
+    /// Replay the guardian set upgrades listed in a file, one hex encoded VAA
+    /// per line. Empty lines and lines starting with `#` are ignored.
+    pub fn from_file(path: &str) -> Result<GuardianSetRegistry, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read file {}\n{:?}", path, e))?;
+        GuardianSetRegistry::from_upgrade_vaas(
+            content
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        )
+        .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Fetch the active guardian set from `wormhole-core-v2.get-active-guardian-set`.
+    pub async fn from_chain(
+        stacks_node_rpc_url: &str,
+        wormhole_core_contract: &QualifiedContractIdentifier,
+    ) -> Result<GuardianSetRegistry, String> {
+        let value = call_read_only_fn(
+            stacks_node_rpc_url,
+            wormhole_core_contract,
+            "get-active-guardian-set",
+            &[],
+            &wormhole_core_contract.issuer.to_string(),
+        )
+        .await?;
+        let value = unwrap_response(value)?;
+        let active_guardian_set = as_tuple(&value)?;
+        let index = as_uint(tuple_get(active_guardian_set, "set-id")?)?;
+        let mut addresses = vec![];
+        for guardian in as_list(tuple_get(active_guardian_set, "guardians")?)? {
+            let uncompressed_public_key =
+                as_buff(tuple_get(as_tuple(guardian)?, "uncompressed-public-key")?)?;
+            let public_key =
+                PublicKey::parse_slice(uncompressed_public_key, Some(PublicKeyFormat::Raw))
+                    .map_err(|e| format!("invalid guardian public key: {}", e))?;
+            addresses.push(compute_eth_address(&public_key));
+        }
+
+        let mut registry = GuardianSetRegistry::new();
+        registry.insert(GuardianSet {
+            index: u32::try_from(index).map_err(|e| e.to_string())?,
+            addresses,
+            expiration_time: None,
+        });
+        Ok(registry)
+    }
+
+    fn from_upgrade_vaas<'a>(
+        upgrade_vaas: impl Iterator<Item = &'a str>,
+    ) -> Result<GuardianSetRegistry, String> {
+        let mut registry = GuardianSetRegistry::new();
+        for vaa_hex in upgrade_vaas {
+            let vaa_bytes = hex::decode(vaa_hex.trim_start_matches("0x"))
+                .map_err(|e| format!("unable to decode guardian set upgrade: {}", e))?;
+            let vaa = Vaa::parse(&vaa_bytes)
+                .map_err(|e| format!("unable to decode guardian set upgrade: {}", e))?;
+            registry.register_upgrade(&vaa)?;
+        }
+        Ok(registry)
+    }
+
+    /// Apply a guardian set upgrade. Unless the registry is empty, the upgrade
+    /// must be signed by a known guardian set, and the previous active set
+    /// expires 24 hours after the upgrade.
+    pub fn register_upgrade(&mut self, vaa: &Vaa) -> Result<&GuardianSet, String> {
+        if !self.guardian_sets.is_empty() {
+            self.verify(vaa, vaa.body.timestamp as u64)
+                .map_err(|e| format!("guardian set upgrade rejected: {}", e))?;
+        }
+        let upgrade = GuardianSetUpgrade::from_vaa(vaa)
+            .map_err(|e| format!("guardian set upgrade rejected: {}", e))?;
+        let new_index = upgrade.guardian_set.index;
+        if let Some(active) = self.guardian_sets.values_mut().next_back() {
+            if new_index <= active.index {
+                return Err(format!(
+                    "guardian set upgrade rejected: {}",
+                    GuardianSetUpgradeError::CheckIndex
+                ));
+            }
+            active.expiration_time = Some(vaa.body.timestamp + GUARDIAN_SET_EXPIRATION_SECS);
+        }
+        self.insert(upgrade.guardian_set);
+        Ok(&self.guardian_sets[&new_index])
+    }
+
+    pub fn insert(&mut self, guardian_set: GuardianSet) {
+        self.guardian_sets.insert(guardian_set.index, guardian_set);
+    }
+
+    pub fn get(&self, index: u32) -> Option<&GuardianSet> {
+        self.guardian_sets.get(&index)
+    }
+
+    /// Guardian set with the highest index, the only one `wormhole-core-v2` accepts.
+    pub fn active(&self) -> Option<&GuardianSet> {
+        self.guardian_sets.values().next_back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GuardianSet> {
+        self.guardian_sets.values()
+    }
+
+    /// Guardian set able to verify the VAA at the given time.
+    pub fn guardian_set_for(&self, vaa: &Vaa, now: u64) -> Result<&GuardianSet, VaaError> {
+        let index = vaa.header.guardian_set_index;
+        let guardian_set = self
+            .get(index)
+            .ok_or(VaaError::ChecksGuardianSetConsistency(index))?;
+        if guardian_set.is_expired(now) {
+            return Err(VaaError::ChecksGuardianSetExpired(index));
+        }
+        Ok(guardian_set)
+    }
+
+    /// Verify the VAA against the guardian set that signed it.
+    pub fn verify(&self, vaa: &Vaa, now: u64) -> Result<&GuardianSet, VaaError> {
+        let guardian_set = self.guardian_set_for(vaa, now)?;
+        vaa.verify(guardian_set)?;
+        Ok(guardian_set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vaa(vaa_hex: &str) -> Vaa {
+        Vaa::parse(&hex::decode(vaa_hex).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn replay_builtin_upgrades() {
+        let registry = GuardianSetRegistry::from_builtin().unwrap();
+        assert_eq!(
+            registry.iter().map(|set| set.index).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        let active = registry.active().unwrap();
+        assert_eq!(active.index, 3);
+        assert_eq!(active.addresses.len(), 19);
+        assert_eq!(active.quorum(), 13);
+        assert_eq!(active.expiration_time, None);
+        assert_eq!(
+            hex::encode(active.addresses[0]),
+            "58cc3ae5c097b213ce3c81979e1b9f9570746aa5"
+        );
+    }
+
+    #[test]
+    fn superseded_sets_expire_after_24_hours() {
+        let registry = GuardianSetRegistry::from_builtin().unwrap();
+        let upgrade_timestamp = vaa(VAA_GUARDIANS_SET_EPOCH_3).body.timestamp;
+        let expiration_time = upgrade_timestamp + GUARDIAN_SET_EXPIRATION_SECS;
+        assert_eq!(
+            registry.get(2).unwrap().expiration_time,
+            Some(expiration_time)
+        );
+
+        // The upgrade to set 3 is signed by set 2
+        let signed_by_2 = vaa(VAA_GUARDIANS_SET_EPOCH_3);
+        assert_eq!(
+            registry
+                .verify(&signed_by_2, expiration_time as u64)
+                .unwrap()
+                .index,
+            2
+        );
+        assert_eq!(
+            registry
+                .verify(&signed_by_2, expiration_time as u64 + 1)
+                .unwrap_err(),
+            VaaError::ChecksGuardianSetExpired(2)
+        );
+        assert_eq!(
+            registry
+                .guardian_set_for(&vaa(VAA_GUARDIANS_SET_EPOCH_1), 0)
+                .unwrap_err(),
+            VaaError::ChecksGuardianSetConsistency(0)
+        );
+    }
+
+    #[test]
+    fn reject_index_regression() {
+        let mut registry = GuardianSetRegistry::new();
+        registry
+            .register_upgrade(&vaa(VAA_GUARDIANS_SET_EPOCH_1))
+            .unwrap();
+        registry
+            .register_upgrade(&vaa(VAA_GUARDIANS_SET_EPOCH_2))
+            .unwrap();
+        assert_eq!(
+            registry
+                .register_upgrade(&vaa(VAA_GUARDIANS_SET_EPOCH_2))
+                .unwrap_err(),
+            "guardian set upgrade rejected: new index is not greater than current index (u1304)"
+        );
+        assert_eq!(registry.active().unwrap().index, 2);
+        assert!(registry.get(1).unwrap().expiration_time.is_some());
+    }
+
+    #[test]
+    fn from_file_skips_comments_and_blank_lines() {
+        let path = std::env::temp_dir().join(format!("guardian-sets-{}.txt", std::process::id()));
+        let content = format!(
+            "# Guardian set upgrades\n\n{}\n   \n  # Set 2\n0x{}\n{}\n",
+            VAA_GUARDIANS_SET_EPOCH_1, VAA_GUARDIANS_SET_EPOCH_2, VAA_GUARDIANS_SET_EPOCH_3
+        );
+        fs::write(&path, content).unwrap();
+        let registry = GuardianSetRegistry::from_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        let registry = registry.unwrap();
+        let builtin = GuardianSetRegistry::from_builtin().unwrap();
+        assert_eq!(
+            registry.iter().collect::<Vec<_>>(),
+            builtin.iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,14 @@
+pub mod guardians;
+pub mod vaa;
+
+use libsecp256k1::PublicKey;
+use sha3::{Digest, Keccak256};
+
+/// Compute the Ethereum-style address (last 20 bytes of the Keccak256 of the
+/// uncompressed public key) that Wormhole uses to identify a guardian.
+pub fn compute_eth_address(public_key: &PublicKey) -> [u8; 20] {
+    let public_key_hash = Keccak256::digest(&public_key.serialize()[1..]);
+    let mut eth_address = [0u8; 20];
+    eth_address.copy_from_slice(&public_key_hash[12..32]);
+    eth_address
+}
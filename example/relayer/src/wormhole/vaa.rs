@@ -0,0 +1,421 @@
+use std::fmt;
+use std::io::{Cursor, Read};
+
+use libsecp256k1::{recover, Message, PublicKey, RecoveryId, Signature};
+use sha3::{Digest, Keccak256};
+
+use crate::utils::read_bytes;
+
+use super::compute_eth_address;
+use super::guardians::GuardianSet;
+
+/// Errors raised while decoding or verifying a VAA. Variants follow the
+/// `ERR_VAA_PARSING_*` and `ERR_VAA_CHECKS_*` constants of `wormhole-core-v2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VaaError {
+    ParsingVersion,
+    ParsingGuardianSet,
+    ParsingSignaturesLen,
+    ParsingSignatures,
+    ParsingTimestamp,
+    ParsingNonce,
+    ParsingEmitterChain,
+    ParsingEmitterAddress,
+    ParsingSequence,
+    ParsingConsistencyLevel,
+    ParsingPayload,
+    InvalidSignature(u8),
+    InvalidRecoveryId(u8),
+    PublicKeyRecovery(u8),
+    ChecksVersionUnsupported(u8),
+    ChecksThresholdSignature { valid: usize, quorum: usize },
+    ChecksRedundantSignature(u8),
+    ChecksGuardianSetConsistency(u32),
+    ChecksGuardianSetExpired(u32),
+    ChecksGuardianIndex(u8),
+}
+
+impl VaaError {
+    /// Error code that `wormhole-core-v2` would return for the same failure.
+    pub fn code(&self) -> u64 {
+        match self {
+            VaaError::ParsingVersion => 1001,
+            VaaError::ParsingGuardianSet => 1002,
+            VaaError::ParsingSignaturesLen => 1003,
+            VaaError::ParsingSignatures
+            | VaaError::InvalidSignature(_)
+            | VaaError::InvalidRecoveryId(_)
+            | VaaError::PublicKeyRecovery(_) => 1004,
+            VaaError::ParsingTimestamp => 1005,
+            VaaError::ParsingNonce => 1006,
+            VaaError::ParsingEmitterChain => 1007,
+            VaaError::ParsingEmitterAddress => 1008,
+            VaaError::ParsingSequence => 1009,
+            VaaError::ParsingConsistencyLevel => 1010,
+            VaaError::ParsingPayload => 1011,
+            VaaError::ChecksVersionUnsupported(_) => 1101,
+            VaaError::ChecksThresholdSignature { .. } => 1102,
+            VaaError::ChecksRedundantSignature(_) => 1103,
+            VaaError::ChecksGuardianSetConsistency(_)
+            | VaaError::ChecksGuardianSetExpired(_)
+            | VaaError::ChecksGuardianIndex(_) => 1105,
+        }
+    }
+}
+
+impl fmt::Display for VaaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            VaaError::ParsingVersion => "unable to parse version".to_string(),
+            VaaError::ParsingGuardianSet => "unable to parse guardian set index".to_string(),
+            VaaError::ParsingSignaturesLen => "unable to parse signatures length".to_string(),
+            VaaError::ParsingSignatures => "unable to parse signatures".to_string(),
+            VaaError::ParsingTimestamp => "unable to parse timestamp".to_string(),
+            VaaError::ParsingNonce => "unable to parse nonce".to_string(),
+            VaaError::ParsingEmitterChain => "unable to parse emitter chain".to_string(),
+            VaaError::ParsingEmitterAddress => "unable to parse emitter address".to_string(),
+            VaaError::ParsingSequence => "unable to parse sequence".to_string(),
+            VaaError::ParsingConsistencyLevel => "unable to parse consistency level".to_string(),
+            VaaError::ParsingPayload => "unable to parse payload".to_string(),
+            VaaError::InvalidSignature(guardian_index) => {
+                format!("invalid signature from guardian {}", guardian_index)
+            }
+            VaaError::InvalidRecoveryId(guardian_index) => {
+                format!("invalid recovery id from guardian {}", guardian_index)
+            }
+            VaaError::PublicKeyRecovery(guardian_index) => {
+                format!(
+                    "unable to recover public key of guardian {}",
+                    guardian_index
+                )
+            }
+            VaaError::ChecksVersionUnsupported(version) => {
+                format!("version {} unsupported", version)
+            }
+            VaaError::ChecksThresholdSignature { valid, quorum } => {
+                format!("{} valid signatures, quorum is {}", valid, quorum)
+            }
+            VaaError::ChecksRedundantSignature(guardian_index) => format!(
+                "signature from guardian {} is duplicated or out of order",
+                guardian_index
+            ),
+            VaaError::ChecksGuardianSetConsistency(guardian_set_index) => format!(
+                "signed by guardian set {} which is unknown or not active",
+                guardian_set_index
+            ),
+            VaaError::ChecksGuardianSetExpired(guardian_set_index) => {
+                format!("guardian set {} expired", guardian_set_index)
+            }
+            VaaError::ChecksGuardianIndex(guardian_index) => {
+                format!("guardian {} not part of the guardian set", guardian_index)
+            }
+        };
+        write!(f, "{} (u{})", reason, self.code())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GuardianSignature {
+    pub guardian_index: u8,
+    /// 64 bytes (r, s) followed by the recovery id.
+    pub signature: [u8; 65],
+}
+
+#[derive(Clone, Debug)]
+pub struct VaaHeader {
+    pub version: u8,
+    pub guardian_set_index: u32,
+    pub signatures: Vec<GuardianSignature>,
+}
+
+#[derive(Clone, Debug)]
+pub struct VaaBody {
+    pub timestamp: u32,
+    pub nonce: u32,
+    pub emitter_chain: u16,
+    pub emitter_address: [u8; 32],
+    pub sequence: u64,
+    pub consistency_level: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Verified Action Approval
+///
+/// VAA Header
+/// byte        version             (VAA Version)
+/// u32         guardian_set_index  (Indicates which guardian set is signing)
+/// u8          len_signatures      (Number of signatures stored)
+/// [][66]byte  signatures          (Collection of ecdsa signatures)
+///
+/// VAA Body
+/// u32         timestamp           (Timestamp of the block where the source transaction occurred)
+/// u32         nonce               (A grouping number)
+/// u16         emitter_chain       (Wormhole ChainId of emitter contract)
+/// [32]byte    emitter_address     (Emitter contract address, in Wormhole format)
+/// u64         sequence            (Strictly increasing sequence, tied to emitter address & chain)
+/// u8          consistency_level   (What finality level was reached before emitting this message)
+/// []byte      payload             (VAA message content)
+#[derive(Clone, Debug)]
+pub struct Vaa {
+    pub header: VaaHeader,
+    pub body: VaaBody,
+}
+
+impl Vaa {
+    pub fn parse(bytes: &[u8]) -> Result<Vaa, VaaError> {
+        let mut cursor = Cursor::new(bytes);
+
+        let [version] = read_bytes(&mut cursor, VaaError::ParsingVersion)?;
+        let guardian_set_index =
+            u32::from_be_bytes(read_bytes(&mut cursor, VaaError::ParsingGuardianSet)?);
+        let [signatures_len] = read_bytes(&mut cursor, VaaError::ParsingSignaturesLen)?;
+        let mut signatures = Vec::with_capacity(signatures_len as usize);
+        for _ in 0..signatures_len {
+            let [guardian_index] = read_bytes(&mut cursor, VaaError::ParsingSignatures)?;
+            let signature = read_bytes(&mut cursor, VaaError::ParsingSignatures)?;
+            signatures.push(GuardianSignature {
+                guardian_index,
+                signature,
+            });
+        }
+
+        let timestamp = u32::from_be_bytes(read_bytes(&mut cursor, VaaError::ParsingTimestamp)?);
+        let nonce = u32::from_be_bytes(read_bytes(&mut cursor, VaaError::ParsingNonce)?);
+        let emitter_chain =
+            u16::from_be_bytes(read_bytes(&mut cursor, VaaError::ParsingEmitterChain)?);
+        let emitter_address = read_bytes(&mut cursor, VaaError::ParsingEmitterAddress)?;
+        let sequence = u64::from_be_bytes(read_bytes(&mut cursor, VaaError::ParsingSequence)?);
+        let [consistency_level] = read_bytes(&mut cursor, VaaError::ParsingConsistencyLevel)?;
+        let mut payload = vec![];
+        cursor
+            .read_to_end(&mut payload)
+            .map_err(|_| VaaError::ParsingPayload)?;
+        if payload.is_empty() {
+            return Err(VaaError::ParsingPayload);
+        }
+
+        Ok(Vaa {
+            header: VaaHeader {
+                version,
+                guardian_set_index,
+                signatures,
+            },
+            body: VaaBody {
+                timestamp,
+                nonce,
+                emitter_chain,
+                emitter_address,
+                sequence,
+                consistency_level,
+                payload,
+            },
+        })
+    }
+
+    /// Double Keccak256 of the body, the message signed by the guardians.
+    pub fn body_hash(&self) -> [u8; 32] {
+        let pass_1 = Keccak256::digest(self.body.serialize());
+        Keccak256::digest(pass_1).into()
+    }
+
+    /// Recover the public key of every guardian that signed this VAA.
+    pub fn recover_public_keys(&self) -> Result<Vec<(u8, PublicKey)>, VaaError> {
+        let message = Message::parse(&self.body_hash());
+        self.header
+            .signatures
+            .iter()
+            .map(|entry| Ok((entry.guardian_index, entry.recover(&message)?)))
+            .collect()
+    }
+
+    /// Perform the checks of `wormhole-core-v2.parse-and-verify-vaa` against
+    /// the given guardian set: supported version, matching guardian set,
+    /// strictly increasing guardian indices and a 2/3+1 quorum of signatures
+    /// recovering to the expected guardian addresses.
+    pub fn verify(&self, guardian_set: &GuardianSet) -> Result<(), VaaError> {
+        if self.header.version != 1 {
+            return Err(VaaError::ChecksVersionUnsupported(self.header.version));
+        }
+        if self.header.guardian_set_index != guardian_set.index {
+            return Err(VaaError::ChecksGuardianSetConsistency(
+                self.header.guardian_set_index,
+            ));
+        }
+
+        let message = Message::parse(&self.body_hash());
+        let mut previous_guardian_index = None;
+        let mut valid_signatures = 0;
+        for entry in self.header.signatures.iter() {
+            let guardian_index = entry.guardian_index;
+            if let Some(previous) = previous_guardian_index {
+                if guardian_index <= previous {
+                    return Err(VaaError::ChecksRedundantSignature(guardian_index));
+                }
+            }
+            previous_guardian_index = Some(guardian_index);
+
+            let expected_address = guardian_set
+                .addresses
+                .get(guardian_index as usize)
+                .ok_or(VaaError::ChecksGuardianIndex(guardian_index))?;
+            // Like the contract, signatures that can't be attributed to their
+            // guardian are not counted rather than failing the whole VAA.
+            if let Ok(public_key) = entry.recover(&message) {
+                if &compute_eth_address(&public_key) == expected_address {
+                    valid_signatures += 1;
+                }
+            }
+        }
+
+        let quorum = guardian_set.quorum();
+        if valid_signatures < quorum {
+            return Err(VaaError::ChecksThresholdSignature {
+                valid: valid_signatures,
+                quorum,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl GuardianSignature {
+    pub fn recover(&self, message: &Message) -> Result<PublicKey, VaaError> {
+        let signature = Signature::parse_standard_slice(&self.signature[..64])
+            .map_err(|_| VaaError::InvalidSignature(self.guardian_index))?;
+        let recovery_id = RecoveryId::parse(self.signature[64])
+            .map_err(|_| VaaError::InvalidRecoveryId(self.guardian_index))?;
+        recover(message, &signature, &recovery_id)
+            .map_err(|_| VaaError::PublicKeyRecovery(self.guardian_index))
+    }
+}
+
+impl VaaBody {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(51 + self.payload.len());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce.to_be_bytes());
+        bytes.extend_from_slice(&self.emitter_chain.to_be_bytes());
+        bytes.extend_from_slice(&self.emitter_address);
+        bytes.extend_from_slice(&self.sequence.to_be_bytes());
+        bytes.push(self.consistency_level);
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wormhole::guardians::{
+        GuardianSetUpgrade, VAA_GUARDIANS_SET_EPOCH_1, VAA_GUARDIANS_SET_EPOCH_2,
+    };
+
+    /// Guardian set upgrade to set 2, signed by 13 of the 19 guardians of set 1.
+    fn upgrade_vaa_bytes() -> Vec<u8> {
+        hex::decode(VAA_GUARDIANS_SET_EPOCH_2).unwrap()
+    }
+
+    fn guardian_set_1() -> GuardianSet {
+        let vaa = Vaa::parse(&hex::decode(VAA_GUARDIANS_SET_EPOCH_1).unwrap()).unwrap();
+        GuardianSetUpgrade::from_vaa(&vaa).unwrap().guardian_set
+    }
+
+    #[test]
+    fn parse_guardian_set_upgrade() {
+        let vaa = Vaa::parse(&upgrade_vaa_bytes()).unwrap();
+        assert_eq!(vaa.header.version, 1);
+        assert_eq!(vaa.header.guardian_set_index, 1);
+        assert_eq!(vaa.header.signatures.len(), 13);
+        assert_eq!(vaa.body.emitter_chain, 1);
+        assert_eq!(vaa.body.emitter_address[31], 4);
+        assert_eq!(vaa.body.consistency_level, 32);
+        let body_len = vaa.body.serialize().len();
+        assert_eq!(body_len, upgrade_vaa_bytes().len() - 6 - 13 * 66);
+    }
+
+    #[test]
+    fn parse_truncated() {
+        let bytes = upgrade_vaa_bytes();
+        let body = 6 + 13 * 66;
+        let cases = [
+            (0, VaaError::ParsingVersion),
+            (1, VaaError::ParsingGuardianSet),
+            (4, VaaError::ParsingGuardianSet),
+            (5, VaaError::ParsingSignaturesLen),
+            (6, VaaError::ParsingSignatures),
+            (body - 1, VaaError::ParsingSignatures),
+            (body, VaaError::ParsingTimestamp),
+            (body + 3, VaaError::ParsingTimestamp),
+            (body + 4, VaaError::ParsingNonce),
+            (body + 8, VaaError::ParsingEmitterChain),
+            (body + 10, VaaError::ParsingEmitterAddress),
+            (body + 41, VaaError::ParsingEmitterAddress),
+            (body + 42, VaaError::ParsingSequence),
+            (body + 50, VaaError::ParsingConsistencyLevel),
+            (body + 51, VaaError::ParsingPayload),
+        ];
+        for (len, error) in cases {
+            assert_eq!(
+                Vaa::parse(&bytes[..len]).unwrap_err(),
+                error,
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn verify_quorum() {
+        let guardian_set = guardian_set_1();
+        assert_eq!(guardian_set.quorum(), 13);
+        let mut vaa = Vaa::parse(&upgrade_vaa_bytes()).unwrap();
+        assert_eq!(vaa.verify(&guardian_set), Ok(()));
+
+        vaa.header.signatures.pop();
+        assert_eq!(
+            vaa.verify(&guardian_set),
+            Err(VaaError::ChecksThresholdSignature {
+                valid: 12,
+                quorum: 13
+            })
+        );
+    }
+
+    #[test]
+    fn verify_out_of_order_signatures() {
+        let guardian_set = guardian_set_1();
+        let mut vaa = Vaa::parse(&upgrade_vaa_bytes()).unwrap();
+        vaa.header.signatures.swap(3, 4);
+        let guardian_index = vaa.header.signatures[4].guardian_index;
+        assert_eq!(
+            vaa.verify(&guardian_set),
+            Err(VaaError::ChecksRedundantSignature(guardian_index))
+        );
+
+        let mut vaa = Vaa::parse(&upgrade_vaa_bytes()).unwrap();
+        vaa.header.signatures[1] = vaa.header.signatures[0].clone();
+        assert_eq!(
+            vaa.verify(&guardian_set),
+            Err(VaaError::ChecksRedundantSignature(0))
+        );
+    }
+
+    #[test]
+    fn verify_tampered_body() {
+        let guardian_set = guardian_set_1();
+        let mut vaa = Vaa::parse(&upgrade_vaa_bytes()).unwrap();
+        vaa.body.sequence += 1;
+        assert_eq!(
+            vaa.verify(&guardian_set),
+            Err(VaaError::ChecksThresholdSignature {
+                valid: 0,
+                quorum: 13
+            })
+        );
+        vaa.header.guardian_set_index = 2;
+        assert_eq!(
+            vaa.verify(&guardian_set),
+            Err(VaaError::ChecksGuardianSetConsistency(2))
+        );
+    }
+}